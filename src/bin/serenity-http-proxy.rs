@@ -0,0 +1,26 @@
+//! Forwards the Discord REST requests of any number of local clients within the ratelimits of a
+//! single bot token.
+//!
+//! Usage: `DISCORD_TOKEN=... serenity-http-proxy [address]`. The address defaults to
+//! `127.0.0.1:3000`. Clients connect to it with `HttpBuilder::proxy`, see the
+//! `serenity::http::proxy` module.
+
+use serenity::http::proxy::HttpProxy;
+use serenity::http::HttpBuilder;
+use tokio::net::TcpListener;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> serenity::Result<()> {
+    let Ok(token) = std::env::var("DISCORD_TOKEN") else {
+        eprintln!("The DISCORD_TOKEN environment variable must be set");
+        std::process::exit(1);
+    };
+    let addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:3000".into());
+
+    let ratelimiter =
+        HttpBuilder::new(token).build().ratelimiter.expect("the ratelimiter is enabled by default");
+    let listener = TcpListener::bind(&addr).await?;
+    eprintln!("Proxying Discord REST requests on {addr}");
+
+    HttpProxy::new(ratelimiter).serve(listener).await
+}
@@ -0,0 +1,27 @@
+//! Shares the HTTP ratelimits of every bot process using the same token.
+//!
+//! Usage: `serenity-ratelimit-daemon [socket path]`. The socket defaults to
+//! `/tmp/serenity-ratelimits.sock`. Bot processes connect to it with a `UnixRatelimitStore`, see
+//! the `serenity::http::ratelimit_daemon` module.
+
+#[cfg(unix)]
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> serenity::Result<()> {
+    use serenity::http::ratelimit_daemon::RatelimitDaemon;
+    use tokio::net::UnixListener;
+
+    let path = std::env::args().nth(1).unwrap_or_else(|| "/tmp/serenity-ratelimits.sock".into());
+
+    // A socket left behind by a previous run would make binding fail.
+    drop(std::fs::remove_file(&path));
+    let listener = UnixListener::bind(&path)?;
+    eprintln!("Sharing ratelimits on {path}");
+
+    RatelimitDaemon::new().serve(listener).await
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("serenity-ratelimit-daemon is only supported on Unix");
+    std::process::exit(1);
+}
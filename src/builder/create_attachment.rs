@@ -1,7 +1,12 @@
+use std::fmt;
+use std::future::Future;
+use std::io::{self, SeekFrom};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
+use futures::future::BoxFuture;
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
 #[cfg(feature = "http")]
 use url::Url;
 
@@ -25,9 +30,15 @@ pub struct CreateAttachment {
     pub(crate) id: u64, // Placeholder ID will be filled in when sending the request
     pub filename: String,
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) uploaded_filename: Option<String>,
 
     #[serde(skip)]
     pub data: Vec<u8>,
+    #[serde(skip)]
+    pub(crate) stream: Option<AttachmentStream>,
+    #[serde(skip)]
+    pub(crate) progress: Option<ProgressCallback>,
 }
 
 impl CreateAttachment {
@@ -37,7 +48,10 @@ impl CreateAttachment {
             data: data.into(),
             filename: filename.into(),
             description: None,
+            uploaded_filename: None,
             id: 0,
+            stream: None,
+            progress: None,
         }
     }
 
@@ -73,6 +87,146 @@ impl CreateAttachment {
         Ok(CreateAttachment::bytes(data, filename))
     }
 
+    /// Builds a [`CreateAttachment`] that is read from a local file while the request is sent,
+    /// instead of being loaded into memory beforehand. The file is opened again if the request has
+    /// to be retried.
+    ///
+    /// Streamed attachments can only be uploaded as files, such as message attachments or
+    /// stickers. Their [`Self::data`] is empty, so they can't be used as images like guild icons.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Io`] if reading the metadata of the file fails.
+    pub async fn path_stream(path: impl AsRef<Path>) -> Result<CreateAttachment> {
+        let path = path.as_ref().to_owned();
+        let length = tokio::fs::metadata(&path).await?.len();
+
+        let filename = path
+            .file_name()
+            .ok_or_else(|| io::Error::other("attachment path must not be a directory"))?;
+        let filename = filename.to_string_lossy().to_string();
+
+        Ok(CreateAttachment::stream(move || File::open(path.clone()), length, filename))
+    }
+
+    /// Builds a [`CreateAttachment`] that is read from a file handler while the request is sent,
+    /// from its current position to its end. The file is rewound if the request has to be retried.
+    ///
+    /// See [`Self::path_stream`] for the limitations of streamed attachments.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Io`] if reading the position or the metadata of the file fails.
+    pub async fn file_stream(
+        mut file: File,
+        filename: impl Into<String>,
+    ) -> Result<CreateAttachment> {
+        let start = file.stream_position().await?;
+        let length = file.metadata().await?.len().saturating_sub(start);
+
+        let file = Arc::new(file);
+        let open = move || {
+            let file = Arc::clone(&file);
+            async move {
+                let mut file = file.try_clone().await?;
+                file.seek(SeekFrom::Start(start)).await?;
+                Ok(file)
+            }
+        };
+
+        Ok(CreateAttachment::stream(open, length, filename))
+    }
+
+    /// Builds a [`CreateAttachment`] that is read from the given reader while the request is sent.
+    /// The reader must yield exactly `length` bytes.
+    ///
+    /// A reader can only be read once, so the request fails with an [`Error::Io`] if it has to be
+    /// retried, for example after hitting a ratelimit. Use [`Self::stream`] to upload data that
+    /// can be read again instead. See [`Self::path_stream`] for the limitations of streamed
+    /// attachments.
+    pub fn reader(
+        reader: impl AsyncRead + Send + Unpin + 'static,
+        length: u64,
+        filename: impl Into<String>,
+    ) -> CreateAttachment {
+        let reader: Mutex<Option<AttachmentReader>> = Mutex::new(Some(Box::new(reader)));
+        let open = move || {
+            let reader = reader.lock().expect("poisoned attachment reader").take();
+            async move { reader.ok_or_else(|| io::Error::other("attachment reader was already read")) }
+        };
+
+        CreateAttachment::stream(open, length, filename)
+    }
+
+    /// Builds a [`CreateAttachment`] that is read from the reader returned by `open` while the
+    /// request is sent. `open` is called again every time the request is retried, and the reader
+    /// must yield exactly `length` bytes.
+    ///
+    /// See [`Self::path_stream`] for the limitations of streamed attachments.
+    ///
+    /// # Examples
+    ///
+    /// Upload the end of a log file, opening it again on every attempt:
+    ///
+    /// ```rust,no_run
+    /// # use serenity::http::Http;
+    /// # use serenity::model::id::ChannelId;
+    /// # async fn run(http: &Http, channel_id: ChannelId, offset: u64, length: u64) -> serenity::Result<()> {
+    /// use std::io::SeekFrom;
+    ///
+    /// use serenity::builder::{CreateAttachment, CreateMessage};
+    /// use tokio::io::AsyncSeekExt;
+    ///
+    /// let attachment = CreateAttachment::stream(
+    ///     move || async move {
+    ///         let mut file = tokio::fs::File::open("bot.log").await?;
+    ///         file.seek(SeekFrom::Start(offset)).await?;
+    ///         Ok(file)
+    ///     },
+    ///     length,
+    ///     "bot.log",
+    /// );
+    /// channel_id.send_files(http, [attachment], CreateMessage::new()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream<F, Fut, R>(open: F, length: u64, filename: impl Into<String>) -> CreateAttachment
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<R>> + Send + 'static,
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let mut attachment = CreateAttachment::bytes(Vec::new(), filename);
+        attachment.stream = Some(AttachmentStream::new(open, length));
+        attachment
+    }
+
+    /// Builds a [`CreateAttachment`] referring to a file that was already uploaded to Discord, as
+    /// returned by [`ChannelId::upload_attachments`].
+    ///
+    /// `uploaded_filename` is the [`AttachmentUpload::upload_filename`] of the slot the file was
+    /// uploaded to. The file can only be sent in the channel the slot was requested for.
+    ///
+    /// [`ChannelId::upload_attachments`]: crate::model::id::ChannelId::upload_attachments
+    /// [`AttachmentUpload::upload_filename`]: crate::model::channel::AttachmentUpload::upload_filename
+    pub fn uploaded(
+        filename: impl Into<String>,
+        uploaded_filename: impl Into<String>,
+    ) -> CreateAttachment {
+        let mut attachment = CreateAttachment::bytes(Vec::new(), filename);
+        attachment.uploaded_filename = Some(uploaded_filename.into());
+        attachment
+    }
+
+    /// The size of the data of the attachment, in bytes.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        match &self.stream {
+            Some(stream) => stream.length,
+            None => self.data.len() as u64,
+        }
+    }
+
     /// Builds an [`CreateAttachment`] by downloading attachment data from a URL.
     ///
     /// # Errors
@@ -112,6 +266,89 @@ impl CreateAttachment {
         self.description = Some(description.into());
         self
     }
+
+    /// Sets a callback to be called every time a chunk of the attachment has been sent. The
+    /// progress starts over if the request is retried.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(UploadProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressCallback(Arc::new(callback)));
+        self
+    }
+}
+
+/// Passed to the callback set with [`CreateAttachment::on_progress`] as an attachment is uploaded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct UploadProgress {
+    /// The number of bytes sent so far.
+    pub sent: u64,
+    /// The size of the attachment, in bytes.
+    pub total: u64,
+}
+
+/// A reader the data of a streamed [`CreateAttachment`] is read from.
+pub(crate) type AttachmentReader = Box<dyn AsyncRead + Send + Unpin>;
+
+type OpenReader = dyn Fn() -> BoxFuture<'static, io::Result<AttachmentReader>> + Send + Sync;
+
+/// The source of a streamed [`CreateAttachment`], opened again for every attempt at a request.
+#[derive(Clone)]
+pub(crate) struct AttachmentStream {
+    pub(crate) open: Arc<OpenReader>,
+    pub(crate) length: u64,
+}
+
+impl AttachmentStream {
+    fn new<F, Fut, R>(open: F, length: u64) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<R>> + Send + 'static,
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        Self {
+            open: Arc::new(move || {
+                let reader = open();
+                Box::pin(async move { Ok(Box::new(reader.await?) as AttachmentReader) })
+            }),
+            length,
+        }
+    }
+
+    /// Streams data that is already in memory, to report the progress of its upload.
+    pub(crate) fn bytes(data: Vec<u8>) -> Self {
+        let length = data.len() as u64;
+        let data: Arc<[u8]> = data.into();
+        Self::new(move || std::future::ready(Ok(io::Cursor::new(Arc::clone(&data)))), length)
+    }
+}
+
+impl PartialEq for AttachmentStream {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.open, &other.open) && self.length == other.length
+    }
+}
+
+impl fmt::Debug for AttachmentStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttachmentStream").field("length", &self.length).finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(pub(crate) Arc<dyn Fn(UploadProgress) + Send + Sync>);
+
+impl PartialEq for ProgressCallback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Fn(UploadProgress)")
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, PartialEq)]
@@ -255,16 +492,27 @@ impl EditAttachments {
     /// Clones all new attachments into a new Vec, keeping only data and filename, because those
     /// are needed for the multipart form data. The data is taken out of `self` in the process, so
     /// this method can only be called once.
+    ///
+    /// Attachments that were already uploaded are only referred to in the JSON payload, so they
+    /// are not part of the returned files.
     pub(crate) fn take_files(&mut self) -> Vec<CreateAttachment> {
         let mut id_placeholder = 0;
 
         let mut files = Vec::new();
         for attachment in &mut self.new_and_existing_attachments {
             if let NewOrExisting::New(attachment) = attachment {
+                if attachment.uploaded_filename.is_some() {
+                    attachment.id = id_placeholder;
+                    id_placeholder += 1;
+                    continue;
+                }
+
                 let mut cloned_attachment = CreateAttachment::bytes(
                     std::mem::take(&mut attachment.data),
                     attachment.filename.clone(),
                 );
+                cloned_attachment.stream = attachment.stream.take();
+                cloned_attachment.progress = attachment.progress.take();
 
                 // Assign placeholder IDs so Discord can match metadata to file contents
                 attachment.id = id_placeholder;
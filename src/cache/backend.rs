@@ -0,0 +1,470 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use dashmap::DashMap;
+
+use super::wrappers::{BuildHasher, MaybeMap, ReadOnlyMapRef};
+use super::{CacheRef, ChannelMessagesRef, GuildRef, MessageRef, Settings, UserRef};
+use crate::model::prelude::*;
+
+/// The storage of the guilds, channels, users and messages of a [`Cache`].
+///
+/// The [`CacheUpdate`] implementations of the gateway events read and write the cache through
+/// this trait, so that the data can be kept somewhere other than in memory, such as in shared
+/// memory or an embedded database, without changing how events update it. The default backend is
+/// [`InMemoryBackend`].
+///
+/// Lookups return a [`CacheRef`], which a backend not keeping its data in memory creates from an
+/// owned value with [`CacheRef::from_owned`]. Updates are given as a closure, which such a backend
+/// runs on a loaded copy of the value before storing it back.
+///
+/// [`Cache`]: super::Cache
+/// [`CacheUpdate`]: super::CacheUpdate
+pub trait CacheBackend: fmt::Debug + Send + Sync {
+    /// Gets a guild.
+    fn guild(&self, guild_id: GuildId) -> Option<GuildRef<'_>>;
+
+    /// Returns the Ids of every stored guild, in no particular order.
+    fn guild_ids(&self) -> Vec<GuildId>;
+
+    /// Returns the number of stored guilds.
+    fn guild_count(&self) -> usize;
+
+    /// Inserts a guild, returning the guild it replaced, if any.
+    fn insert_guild(&self, guild: Guild) -> Option<Guild>;
+
+    /// Updates a guild in place, returning whether it was stored.
+    fn update_guild(&self, guild_id: GuildId, update: &mut dyn FnMut(&mut Guild)) -> bool;
+
+    /// Removes a guild, returning it if it was stored.
+    fn remove_guild(&self, guild_id: GuildId) -> Option<Guild>;
+
+    /// Gets the Id of the guild a channel is in. The channel itself is stored in its [`Guild`].
+    fn channel_guild(&self, channel_id: ChannelId) -> Option<GuildId>;
+
+    /// Returns the number of stored channels.
+    fn channel_count(&self) -> usize;
+
+    /// Records the guild a channel is in, returning the guild previously recorded, if any.
+    fn insert_channel(&self, channel_id: ChannelId, guild_id: GuildId) -> Option<GuildId>;
+
+    /// Forgets the guild a channel is in, returning it if it was recorded.
+    fn remove_channel(&self, channel_id: ChannelId) -> Option<GuildId>;
+
+    /// Gets a user.
+    fn user(&self, user_id: UserId) -> Option<UserRef<'_>>;
+
+    /// Returns the Ids of every stored user, in no particular order.
+    fn user_ids(&self) -> Vec<UserId>;
+
+    /// Returns the number of stored users.
+    fn user_count(&self) -> usize;
+
+    /// Returns the map of stored users, if they are kept in one.
+    ///
+    /// Defaults to an empty map, as a backend storing users elsewhere has no such map to return.
+    fn users(&self) -> ReadOnlyMapRef<'_, UserId, User> {
+        ReadOnlyMapRef::empty()
+    }
+
+    /// Inserts a user, returning the user it replaced, if any.
+    fn insert_user(&self, user: User) -> Option<User>;
+
+    /// Updates a user in place, returning whether it was stored.
+    fn update_user(&self, user_id: UserId, update: &mut dyn FnMut(&mut User)) -> bool;
+
+    /// Removes a user, returning it if it was stored.
+    fn remove_user(&self, user_id: UserId) -> Option<User>;
+
+    /// Returns the Ids of every channel with stored messages, in no particular order.
+    fn message_channel_ids(&self) -> Vec<ChannelId>;
+
+    /// Gets the stored messages of a channel.
+    fn channel_messages(&self, channel_id: ChannelId) -> Option<ChannelMessagesRef<'_>>;
+
+    /// Gets a message.
+    fn message(&self, channel_id: ChannelId, message_id: MessageId) -> Option<MessageRef<'_>> {
+        self.channel_messages(channel_id)?.try_map(|messages| messages.get(&message_id))
+    }
+
+    /// Inserts a message. If its channel already had `max_messages` stored messages, the oldest
+    /// one is removed and returned.
+    fn insert_message(&self, message: Message, max_messages: usize) -> Option<Message>;
+
+    /// Updates a message in place, returning whether it was stored.
+    fn update_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        update: &mut dyn FnMut(&mut Message),
+    ) -> bool;
+
+    /// Removes a message, returning it if it was stored.
+    fn remove_message(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message>;
+
+    /// Removes every message of a channel, returning them if any were stored.
+    fn remove_channel_messages(&self, channel_id: ChannelId)
+        -> Option<HashMap<MessageId, Message>>;
+
+    /// Returns the memory used by the backend, beyond its own size.
+    #[cfg(feature = "typesize")]
+    fn extra_size(&self) -> usize {
+        0
+    }
+}
+
+/// The default [`CacheBackend`], keeping everything in memory.
+///
+/// Guilds, channels and users are only stored if enabled in the [`Settings`] the backend is
+/// created with.
+#[cfg_attr(feature = "typesize", derive(typesize::derive::TypeSize))]
+#[derive(Debug)]
+pub struct InMemoryBackend {
+    /// A map of channel ids to the guilds in which the channel data is stored.
+    channels: MaybeMap<ChannelId, GuildId>,
+    /// A map of guilds with full data available. This includes data like [`Role`]s and [`Emoji`]s
+    /// that are not available through the REST API.
+    guilds: MaybeMap<GuildId, Guild>,
+    /// A map of users that the current user sees.
+    users: MaybeMap<UserId, User>,
+    messages: DashMap<ChannelId, HashMap<MessageId, Message>, BuildHasher>,
+    /// Queue of message IDs for each channel.
+    ///
+    /// This is simply a vecdeque so we can keep track of the order of messages inserted into the
+    /// cache. When a maximum number of messages are in a channel's cache, we can pop the front and
+    /// remove that ID from the cache.
+    message_queue: DashMap<ChannelId, VecDeque<MessageId>, BuildHasher>,
+}
+
+impl InMemoryBackend {
+    /// Creates an empty backend, storing what is enabled in the given settings.
+    #[must_use]
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            channels: MaybeMap(settings.cache_channels.then(DashMap::default)),
+            guilds: MaybeMap(settings.cache_guilds.then(DashMap::default)),
+            users: MaybeMap(settings.cache_users.then(DashMap::default)),
+            messages: DashMap::default(),
+            message_queue: DashMap::default(),
+        }
+    }
+}
+
+impl Default for InMemoryBackend {
+    fn default() -> Self {
+        Self::new(&Settings::default())
+    }
+}
+
+impl CacheBackend for InMemoryBackend {
+    fn guild(&self, guild_id: GuildId) -> Option<GuildRef<'_>> {
+        self.guilds.get(&guild_id).map(CacheRef::from_ref)
+    }
+
+    fn guild_ids(&self) -> Vec<GuildId> {
+        self.guilds.iter().map(|i| *i.key()).collect()
+    }
+
+    fn guild_count(&self) -> usize {
+        self.guilds.len()
+    }
+
+    fn insert_guild(&self, guild: Guild) -> Option<Guild> {
+        self.guilds.insert(guild.id, guild)
+    }
+
+    fn update_guild(&self, guild_id: GuildId, update: &mut dyn FnMut(&mut Guild)) -> bool {
+        self.guilds.get_mut(&guild_id).map(|mut guild| update(&mut guild)).is_some()
+    }
+
+    fn remove_guild(&self, guild_id: GuildId) -> Option<Guild> {
+        self.guilds.remove(&guild_id).map(|(_, guild)| guild)
+    }
+
+    fn channel_guild(&self, channel_id: ChannelId) -> Option<GuildId> {
+        self.channels.get(&channel_id).map(|guild_id| *guild_id)
+    }
+
+    fn channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    fn insert_channel(&self, channel_id: ChannelId, guild_id: GuildId) -> Option<GuildId> {
+        self.channels.insert(channel_id, guild_id)
+    }
+
+    fn remove_channel(&self, channel_id: ChannelId) -> Option<GuildId> {
+        self.channels.remove(&channel_id).map(|(_, guild_id)| guild_id)
+    }
+
+    fn user(&self, user_id: UserId) -> Option<UserRef<'_>> {
+        self.users.get(&user_id).map(CacheRef::from_ref)
+    }
+
+    fn user_ids(&self) -> Vec<UserId> {
+        self.users.iter().map(|i| *i.key()).collect()
+    }
+
+    fn user_count(&self) -> usize {
+        self.users.len()
+    }
+
+    fn users(&self) -> ReadOnlyMapRef<'_, UserId, User> {
+        self.users.as_read_only()
+    }
+
+    fn insert_user(&self, user: User) -> Option<User> {
+        self.users.insert(user.id, user)
+    }
+
+    fn update_user(&self, user_id: UserId, update: &mut dyn FnMut(&mut User)) -> bool {
+        self.users.get_mut(&user_id).map(|mut user| update(&mut user)).is_some()
+    }
+
+    fn remove_user(&self, user_id: UserId) -> Option<User> {
+        self.users.remove(&user_id).map(|(_, user)| user)
+    }
+
+    fn message_channel_ids(&self) -> Vec<ChannelId> {
+        self.messages.iter().map(|i| *i.key()).collect()
+    }
+
+    fn channel_messages(&self, channel_id: ChannelId) -> Option<ChannelMessagesRef<'_>> {
+        self.messages.get(&channel_id).map(CacheRef::from_ref)
+    }
+
+    fn insert_message(&self, message: Message, max_messages: usize) -> Option<Message> {
+        let mut messages = self.messages.entry(message.channel_id).or_default();
+        let mut queue = self.message_queue.entry(message.channel_id).or_default();
+
+        let mut removed_msg = None;
+
+        if messages.len() == max_messages {
+            if let Some(id) = queue.pop_front() {
+                removed_msg = messages.remove(&id);
+            }
+        }
+
+        queue.push_back(message.id);
+        messages.insert(message.id, message);
+
+        removed_msg
+    }
+
+    fn update_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        update: &mut dyn FnMut(&mut Message),
+    ) -> bool {
+        let Some(mut messages) = self.messages.get_mut(&channel_id) else { return false };
+        messages.get_mut(&message_id).map(update).is_some()
+    }
+
+    fn remove_message(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message> {
+        if let Some(mut queue) = self.message_queue.get_mut(&channel_id) {
+            queue.retain(|id| *id != message_id);
+        }
+
+        self.messages.get_mut(&channel_id)?.remove(&message_id)
+    }
+
+    fn remove_channel_messages(
+        &self,
+        channel_id: ChannelId,
+    ) -> Option<HashMap<MessageId, Message>> {
+        self.message_queue.remove(&channel_id);
+        self.messages.remove(&channel_id).map(|(_, messages)| messages)
+    }
+
+    #[cfg(feature = "typesize")]
+    fn extra_size(&self) -> usize {
+        typesize::TypeSize::extra_size(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::cache::test::create_guild;
+    use crate::cache::Cache;
+
+    /// A backend handing out copies, as one storing data outside of memory would.
+    #[derive(Debug, Default)]
+    struct CopyingBackend {
+        guilds: Mutex<HashMap<GuildId, Guild>>,
+        channels: Mutex<HashMap<ChannelId, GuildId>>,
+        users: Mutex<HashMap<UserId, User>>,
+        messages: Mutex<HashMap<ChannelId, HashMap<MessageId, Message>>>,
+    }
+
+    impl CacheBackend for CopyingBackend {
+        fn guild(&self, guild_id: GuildId) -> Option<GuildRef<'_>> {
+            self.guilds.lock().unwrap().get(&guild_id).cloned().map(CacheRef::from_owned)
+        }
+
+        fn guild_ids(&self) -> Vec<GuildId> {
+            self.guilds.lock().unwrap().keys().copied().collect()
+        }
+
+        fn guild_count(&self) -> usize {
+            self.guilds.lock().unwrap().len()
+        }
+
+        fn insert_guild(&self, guild: Guild) -> Option<Guild> {
+            self.guilds.lock().unwrap().insert(guild.id, guild)
+        }
+
+        fn update_guild(&self, guild_id: GuildId, update: &mut dyn FnMut(&mut Guild)) -> bool {
+            self.guilds.lock().unwrap().get_mut(&guild_id).map(update).is_some()
+        }
+
+        fn remove_guild(&self, guild_id: GuildId) -> Option<Guild> {
+            self.guilds.lock().unwrap().remove(&guild_id)
+        }
+
+        fn channel_guild(&self, channel_id: ChannelId) -> Option<GuildId> {
+            self.channels.lock().unwrap().get(&channel_id).copied()
+        }
+
+        fn channel_count(&self) -> usize {
+            self.channels.lock().unwrap().len()
+        }
+
+        fn insert_channel(&self, channel_id: ChannelId, guild_id: GuildId) -> Option<GuildId> {
+            self.channels.lock().unwrap().insert(channel_id, guild_id)
+        }
+
+        fn remove_channel(&self, channel_id: ChannelId) -> Option<GuildId> {
+            self.channels.lock().unwrap().remove(&channel_id)
+        }
+
+        fn user(&self, user_id: UserId) -> Option<UserRef<'_>> {
+            self.users.lock().unwrap().get(&user_id).cloned().map(CacheRef::from_owned)
+        }
+
+        fn user_ids(&self) -> Vec<UserId> {
+            self.users.lock().unwrap().keys().copied().collect()
+        }
+
+        fn user_count(&self) -> usize {
+            self.users.lock().unwrap().len()
+        }
+
+        fn insert_user(&self, user: User) -> Option<User> {
+            self.users.lock().unwrap().insert(user.id, user)
+        }
+
+        fn update_user(&self, user_id: UserId, update: &mut dyn FnMut(&mut User)) -> bool {
+            self.users.lock().unwrap().get_mut(&user_id).map(update).is_some()
+        }
+
+        fn remove_user(&self, user_id: UserId) -> Option<User> {
+            self.users.lock().unwrap().remove(&user_id)
+        }
+
+        fn message_channel_ids(&self) -> Vec<ChannelId> {
+            self.messages.lock().unwrap().keys().copied().collect()
+        }
+
+        fn channel_messages(&self, channel_id: ChannelId) -> Option<ChannelMessagesRef<'_>> {
+            self.messages.lock().unwrap().get(&channel_id).cloned().map(CacheRef::from_owned)
+        }
+
+        fn insert_message(&self, message: Message, _max_messages: usize) -> Option<Message> {
+            let mut messages = self.messages.lock().unwrap();
+            messages.entry(message.channel_id).or_default().insert(message.id, message);
+            None
+        }
+
+        fn update_message(
+            &self,
+            channel_id: ChannelId,
+            message_id: MessageId,
+            update: &mut dyn FnMut(&mut Message),
+        ) -> bool {
+            let mut messages = self.messages.lock().unwrap();
+            let message = messages.get_mut(&channel_id).and_then(|m| m.get_mut(&message_id));
+            message.map(update).is_some()
+        }
+
+        fn remove_message(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message> {
+            self.messages.lock().unwrap().get_mut(&channel_id)?.remove(&message_id)
+        }
+
+        fn remove_channel_messages(
+            &self,
+            channel_id: ChannelId,
+        ) -> Option<HashMap<MessageId, Message>> {
+            self.messages.lock().unwrap().remove(&channel_id)
+        }
+    }
+
+    #[test]
+    fn events_write_through_backend() {
+        let settings = Settings {
+            max_messages: 10,
+            ..Default::default()
+        };
+        let cache = Cache::new_with_backend(settings, CopyingBackend::default());
+
+        let (guild_id, channel_id) = (GuildId::new(1), ChannelId::new(2));
+        let channel = GuildChannel {
+            id: channel_id,
+            guild_id,
+            ..Default::default()
+        };
+        create_guild(&cache, Guild {
+            id: guild_id,
+            channels: HashMap::from([(channel_id, channel)]),
+            ..Default::default()
+        });
+
+        let user = User {
+            id: UserId::new(3),
+            name: "ferris".to_string(),
+            ..Default::default()
+        };
+        let mut member_add = GuildMemberAddEvent {
+            member: Member {
+                guild_id,
+                user: user.clone(),
+                ..Default::default()
+            },
+        };
+        cache.update(&mut member_add);
+
+        let mut message_create = MessageCreateEvent {
+            message: Message {
+                id: MessageId::new(4),
+                channel_id,
+                guild_id: Some(guild_id),
+                content: "hello".to_string(),
+                ..Default::default()
+            },
+        };
+        cache.update(&mut message_create);
+
+        let guild = cache.guild(guild_id).unwrap();
+        assert!(guild.members.contains_key(&user.id));
+        assert_eq!(guild.channels[&channel_id].last_message_id, Some(MessageId::new(4)));
+        assert_eq!(cache.user(user.id).unwrap().name, "ferris");
+        // The backend keeps no map of users to lend out, but their Ids are still listed.
+        assert_eq!(cache.users().len(), 0);
+        assert_eq!(cache.user_ids(), [user.id]);
+        assert_eq!(cache.message(channel_id, MessageId::new(4)).unwrap().content, "hello");
+        assert_eq!(cache.guild_channel_count(), 1);
+
+        let mut guild_delete = GuildDeleteEvent {
+            guild: UnavailableGuild {
+                id: guild_id,
+                unavailable: false,
+            },
+        };
+        assert!(cache.update(&mut guild_delete).is_some());
+        assert_eq!(cache.guild_count(), 0);
+        assert!(cache.channel_messages(channel_id).is_none());
+    }
+}
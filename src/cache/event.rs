@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use super::{Cache, CacheUpdate};
+use super::{Cache, CacheUpdate, GuildFields};
 use crate::model::channel::{GuildChannel, Message};
 use crate::model::event::{
     ChannelCreateEvent,
@@ -40,12 +40,18 @@ impl CacheUpdate for ChannelCreateEvent {
     type Output = GuildChannel;
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
+        let cache_channels = cache.settings().guild_fields.contains(GuildFields::CHANNELS);
         let old_channel = cache
-            .guilds
-            .get_mut(&self.channel.guild_id)
-            .and_then(|mut g| g.channels.insert(self.channel.id, self.channel.clone()));
+            .update_guild(self.channel.guild_id, |g| {
+                if !cache_channels {
+                    return None;
+                }
+
+                g.channels.insert(self.channel.id, self.channel.clone())
+            })
+            .flatten();
 
-        cache.channels.insert(self.channel.id, self.channel.guild_id);
+        cache.backend.insert_channel(self.channel.id, self.channel.guild_id);
         old_channel
     }
 }
@@ -56,11 +62,14 @@ impl CacheUpdate for ChannelDeleteEvent {
     fn update(&mut self, cache: &Cache) -> Option<Vec<Message>> {
         let (channel_id, guild_id) = (self.channel.id, self.channel.guild_id);
 
-        cache.channels.remove(&channel_id);
-        cache.guilds.get_mut(&guild_id).map(|mut g| g.channels.remove(&channel_id));
+        cache.backend.remove_channel(channel_id);
+        cache.update_guild(guild_id, |g| g.channels.remove(&channel_id));
 
         // Remove the cached messages for the channel.
-        cache.messages.remove(&channel_id).map(|(_, messages)| messages.into_values().collect())
+        cache
+            .backend
+            .remove_channel_messages(channel_id)
+            .map(|messages| messages.into_values().collect())
     }
 }
 
@@ -68,12 +77,18 @@ impl CacheUpdate for ChannelUpdateEvent {
     type Output = GuildChannel;
 
     fn update(&mut self, cache: &Cache) -> Option<GuildChannel> {
-        cache.channels.insert(self.channel.id, self.channel.guild_id);
+        cache.backend.insert_channel(self.channel.id, self.channel.guild_id);
 
+        let cache_channels = cache.settings().guild_fields.contains(GuildFields::CHANNELS);
         cache
-            .guilds
-            .get_mut(&self.channel.guild_id)
-            .and_then(|mut g| g.channels.insert(self.channel.id, self.channel.clone()))
+            .update_guild(self.channel.guild_id, |g| {
+                if !cache_channels {
+                    return None;
+                }
+
+                g.channels.insert(self.channel.id, self.channel.clone())
+            })
+            .flatten()
     }
 }
 
@@ -82,11 +97,11 @@ impl CacheUpdate for ChannelPinsUpdateEvent {
 
     fn update(&mut self, cache: &Cache) -> Option<()> {
         if let Some(guild_id) = self.guild_id {
-            if let Some(mut guild) = cache.guilds.get_mut(&guild_id) {
+            cache.update_guild(guild_id, |guild| {
                 if let Some(channel) = guild.channels.get_mut(&self.channel_id) {
                     channel.last_pin_timestamp = self.last_pin_timestamp;
                 }
-            }
+            });
         }
 
         None
@@ -99,6 +114,7 @@ impl CacheUpdate for GuildCreateEvent {
     fn update(&mut self, cache: &Cache) -> Option<()> {
         cache.unavailable_guilds.remove(&self.guild.id);
         let mut guild = self.guild.clone();
+        cache.settings().filter_guild(&mut guild);
 
         for (user_id, member) in &mut guild.members {
             cache.update_user_entry(&member.user);
@@ -107,10 +123,14 @@ impl CacheUpdate for GuildCreateEvent {
             }
         }
 
-        cache.guilds.insert(self.guild.id, guild);
-        for channel_id in self.guild.channels.keys() {
-            cache.channels.insert(*channel_id, self.guild.id);
+        for channel_id in guild.channels.keys() {
+            cache.backend.insert_channel(*channel_id, self.guild.id);
         }
+        let member_ids: Vec<_> = guild.members.keys().copied().collect();
+        cache.backend.insert_guild(guild);
+
+        cache.forget_guild(self.guild.id);
+        cache.touch_members(self.guild.id, member_ids);
 
         None
     }
@@ -122,22 +142,24 @@ impl CacheUpdate for GuildDeleteEvent {
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
         if self.guild.unavailable {
             cache.unavailable_guilds.insert(self.guild.id, ());
-            cache.guilds.remove(&self.guild.id);
+            cache.backend.remove_guild(self.guild.id);
+            cache.forget_guild(self.guild.id);
 
             return None;
         }
 
-        match cache.guilds.remove(&self.guild.id) {
+        cache.forget_guild(self.guild.id);
+        match cache.backend.remove_guild(self.guild.id) {
             Some(guild) => {
-                for channel_id in guild.1.channels.keys() {
+                for channel_id in guild.channels.keys() {
                     // Remove the channel from the cache.
-                    cache.channels.remove(channel_id);
+                    cache.backend.remove_channel(*channel_id);
 
                     // Remove the channel's cached messages.
-                    cache.messages.remove(channel_id);
+                    cache.backend.remove_channel_messages(*channel_id);
                 }
 
-                Some(guild.1)
+                Some(guild)
             },
             None => None,
         }
@@ -148,8 +170,8 @@ impl CacheUpdate for GuildEmojisUpdateEvent {
     type Output = ();
 
     fn update(&mut self, cache: &Cache) -> Option<()> {
-        if let Some(mut guild) = cache.guilds.get_mut(&self.guild_id) {
-            guild.emojis.clone_from(&self.emojis);
+        if cache.settings().guild_fields.contains(GuildFields::EMOJIS) {
+            cache.update_guild(self.guild_id, |guild| guild.emojis.clone_from(&self.emojis));
         }
 
         None
@@ -161,14 +183,23 @@ impl CacheUpdate for GuildMemberAddEvent {
 
     fn update(&mut self, cache: &Cache) -> Option<()> {
         let user_id = self.member.user.id;
-        cache.update_user_entry(&self.member.user);
+        let mut member = self.member.clone();
+        let cache_member = cache.settings().filter_member(&mut member);
+
+        cache.update_member_user(&self.member.user, cache_member);
         if let Some(u) = cache.user(user_id) {
             self.member.user = u.clone();
         }
 
-        if let Some(mut guild) = cache.guilds.get_mut(&self.member.guild_id) {
+        let updated = cache.update_guild(self.member.guild_id, |guild| {
             guild.member_count += 1;
-            guild.members.insert(user_id, self.member.clone());
+            if cache_member {
+                guild.members.insert(user_id, member);
+            }
+        });
+
+        if cache_member && updated.is_some() {
+            cache.touch_members(self.member.guild_id, [user_id]);
         }
 
         None
@@ -179,12 +210,13 @@ impl CacheUpdate for GuildMemberRemoveEvent {
     type Output = Member;
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
-        if let Some(mut guild) = cache.guilds.get_mut(&self.guild_id) {
-            guild.member_count -= 1;
-            return guild.members.remove(&self.user.id);
-        }
-
-        None
+        cache.forget_member(self.guild_id, self.user.id);
+        cache
+            .update_guild(self.guild_id, |guild| {
+                guild.member_count -= 1;
+                guild.members.remove(&self.user.id)
+            })
+            .flatten()
     }
 }
 
@@ -192,52 +224,56 @@ impl CacheUpdate for GuildMemberUpdateEvent {
     type Output = Member;
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
-        cache.update_user_entry(&self.user);
-
-        if let Some(mut guild) = cache.guilds.get_mut(&self.guild_id) {
-            let item = if let Some(member) = guild.members.get_mut(&self.user.id) {
-                let item = Some(member.clone());
-
-                member.joined_at.clone_from(&Some(self.joined_at));
-                member.nick.clone_from(&self.nick);
-                member.roles.clone_from(&self.roles);
-                member.user.clone_from(&self.user);
-                member.pending.clone_from(&self.pending);
-                member.premium_since.clone_from(&self.premium_since);
-                member.deaf.clone_from(&self.deaf);
-                member.mute.clone_from(&self.mute);
-                member.avatar.clone_from(&self.avatar);
-                member.communication_disabled_until.clone_from(&self.communication_disabled_until);
-                member.unusual_dm_activity_until.clone_from(&self.unusual_dm_activity_until);
-
-                item
+        let item =
+            cache.guild(self.guild_id).and_then(|guild| guild.members.get(&self.user.id).cloned());
+
+        let mut member = item.clone().unwrap_or_else(|| Member {
+            deaf: false,
+            guild_id: self.guild_id,
+            joined_at: None,
+            mute: false,
+            nick: None,
+            roles: Vec::new(),
+            user: self.user.clone(),
+            pending: false,
+            premium_since: None,
+            permissions: None,
+            avatar: None,
+            communication_disabled_until: None,
+            flags: GuildMemberFlags::default(),
+            unusual_dm_activity_until: None,
+        });
+        member.joined_at = Some(self.joined_at);
+        member.nick.clone_from(&self.nick);
+        member.roles.clone_from(&self.roles);
+        member.user.clone_from(&self.user);
+        member.pending.clone_from(&self.pending);
+        member.premium_since.clone_from(&self.premium_since);
+        member.deaf.clone_from(&self.deaf);
+        member.mute.clone_from(&self.mute);
+        member.avatar.clone_from(&self.avatar);
+        member.communication_disabled_until.clone_from(&self.communication_disabled_until);
+        member.unusual_dm_activity_until.clone_from(&self.unusual_dm_activity_until);
+
+        // The member may not match the settings anymore, such as after gaining a role.
+        let cache_member = cache.settings().filter_member(&mut member);
+        cache.update_member_user(&self.user, cache_member);
+
+        cache.update_guild(self.guild_id, |guild| {
+            if cache_member {
+                guild.members.insert(self.user.id, member);
             } else {
-                None
-            };
-
-            if item.is_none() {
-                guild.members.insert(self.user.id, Member {
-                    deaf: false,
-                    guild_id: self.guild_id,
-                    joined_at: Some(self.joined_at),
-                    mute: false,
-                    nick: self.nick.clone(),
-                    roles: self.roles.clone(),
-                    user: self.user.clone(),
-                    pending: self.pending,
-                    premium_since: self.premium_since,
-                    permissions: None,
-                    avatar: self.avatar,
-                    communication_disabled_until: self.communication_disabled_until,
-                    flags: GuildMemberFlags::default(),
-                    unusual_dm_activity_until: self.unusual_dm_activity_until,
-                });
+                guild.members.remove(&self.user.id);
             }
+        })?;
 
-            item
+        if cache_member {
+            cache.touch_members(self.guild_id, [self.user.id]);
         } else {
-            None
+            cache.forget_member(self.guild_id, self.user.id);
         }
+
+        item
     }
 }
 
@@ -245,12 +281,20 @@ impl CacheUpdate for GuildMembersChunkEvent {
     type Output = ();
 
     fn update(&mut self, cache: &Cache) -> Option<()> {
+        let mut members = self.members.clone();
+        let settings = cache.settings();
+        members.retain(|_, member| settings.filter_member(member));
+        drop(settings);
+
         for member in self.members.values() {
-            cache.update_user_entry(&member.user);
+            cache.update_member_user(&member.user, members.contains_key(&member.user.id));
         }
 
-        if let Some(mut g) = cache.guilds.get_mut(&self.guild_id) {
-            g.members.extend(self.members.clone());
+        if !members.is_empty() {
+            let member_ids: Vec<_> = members.keys().copied().collect();
+            if cache.update_guild(self.guild_id, |g| g.members.extend(members)).is_some() {
+                cache.touch_members(self.guild_id, member_ids);
+            }
         }
 
         None
@@ -261,10 +305,11 @@ impl CacheUpdate for GuildRoleCreateEvent {
     type Output = ();
 
     fn update(&mut self, cache: &Cache) -> Option<()> {
-        cache
-            .guilds
-            .get_mut(&self.role.guild_id)
-            .map(|mut g| g.roles.insert(self.role.id, self.role.clone()));
+        if cache.settings().guild_fields.contains(GuildFields::ROLES) {
+            cache.update_guild(self.role.guild_id, |g| {
+                g.roles.insert(self.role.id, self.role.clone())
+            });
+        }
 
         None
     }
@@ -274,7 +319,7 @@ impl CacheUpdate for GuildRoleDeleteEvent {
     type Output = Role;
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
-        cache.guilds.get_mut(&self.guild_id).and_then(|mut g| g.roles.remove(&self.role_id))
+        cache.update_guild(self.guild_id, |g| g.roles.remove(&self.role_id)).flatten()
     }
 }
 
@@ -282,13 +327,12 @@ impl CacheUpdate for GuildRoleUpdateEvent {
     type Output = Role;
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
-        if let Some(mut guild) = cache.guilds.get_mut(&self.role.guild_id) {
-            if let Some(role) = guild.roles.get_mut(&self.role.id) {
-                return Some(std::mem::replace(role, self.role.clone()));
-            }
-        }
-
-        None
+        cache
+            .update_guild(self.role.guild_id, |guild| {
+                let role = guild.roles.get_mut(&self.role.id)?;
+                Some(std::mem::replace(role, self.role.clone()))
+            })
+            .flatten()
     }
 }
 
@@ -296,8 +340,8 @@ impl CacheUpdate for GuildStickersUpdateEvent {
     type Output = ();
 
     fn update(&mut self, cache: &Cache) -> Option<()> {
-        if let Some(mut guild) = cache.guilds.get_mut(&self.guild_id) {
-            guild.stickers.clone_from(&self.stickers);
+        if cache.settings().guild_fields.contains(GuildFields::STICKERS) {
+            cache.update_guild(self.guild_id, |guild| guild.stickers.clone_from(&self.stickers));
         }
 
         None
@@ -308,7 +352,9 @@ impl CacheUpdate for GuildUpdateEvent {
     type Output = ();
 
     fn update(&mut self, cache: &Cache) -> Option<()> {
-        if let Some(mut guild) = cache.guilds.get_mut(&self.guild.id) {
+        let cache_roles = cache.settings().guild_fields.contains(GuildFields::ROLES);
+
+        cache.update_guild(self.guild.id, |guild| {
             guild.afk_metadata.clone_from(&self.guild.afk_metadata);
             guild.banner.clone_from(&self.guild.banner);
             guild.discovery_splash.clone_from(&self.guild.discovery_splash);
@@ -316,7 +362,9 @@ impl CacheUpdate for GuildUpdateEvent {
             guild.icon.clone_from(&self.guild.icon);
             guild.name.clone_from(&self.guild.name);
             guild.owner_id.clone_from(&self.guild.owner_id);
-            guild.roles.clone_from(&self.guild.roles);
+            if cache_roles {
+                guild.roles.clone_from(&self.guild.roles);
+            }
             guild.splash.clone_from(&self.guild.splash);
             guild.vanity_url_code.clone_from(&self.guild.vanity_url_code);
             guild.welcome_screen.clone_from(&self.guild.welcome_screen);
@@ -335,7 +383,7 @@ impl CacheUpdate for GuildUpdateEvent {
             guild.verification_level = self.guild.verification_level;
             guild.widget_channel_id = self.guild.widget_channel_id;
             guild.widget_enabled = self.guild.widget_enabled;
-        }
+        });
 
         None
     }
@@ -347,18 +395,26 @@ impl CacheUpdate for MessageCreateEvent {
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
         // Update the relevant channel object with the new latest message if this message is newer
-        let guild = self.message.guild_id.and_then(|g_id| cache.guilds.get_mut(&g_id));
-
-        if let Some(mut guild) = guild {
-            if let Some(channel) = guild.channels.get_mut(&self.message.channel_id) {
-                update_channel_last_message_id(&self.message, channel, cache);
-            } else {
-                // This may be a thread.
-                let thread =
-                    guild.threads.iter_mut().find(|thread| thread.id == self.message.channel_id);
-                if let Some(thread) = thread {
-                    update_channel_last_message_id(&self.message, thread, cache);
+        if let Some(guild_id) = self.message.guild_id {
+            let author_cached = cache.update_guild(guild_id, |guild| {
+                if let Some(channel) = guild.channels.get_mut(&self.message.channel_id) {
+                    update_channel_last_message_id(&self.message, channel, cache);
+                } else {
+                    // This may be a thread.
+                    let thread = guild
+                        .threads
+                        .iter_mut()
+                        .find(|thread| thread.id == self.message.channel_id);
+                    if let Some(thread) = thread {
+                        update_channel_last_message_id(&self.message, thread, cache);
+                    }
                 }
+
+                guild.members.contains_key(&self.message.author.id)
+            });
+
+            if author_cached == Some(true) {
+                cache.touch_members(guild_id, [self.message.author.id]);
             }
         }
 
@@ -369,21 +425,7 @@ impl CacheUpdate for MessageCreateEvent {
             return None;
         }
 
-        let mut messages = cache.messages.entry(self.message.channel_id).or_default();
-        let mut queue = cache.message_queue.entry(self.message.channel_id).or_default();
-
-        let mut removed_msg = None;
-
-        if messages.len() == max {
-            if let Some(id) = queue.pop_front() {
-                removed_msg = messages.remove(&id);
-            }
-        }
-
-        queue.push_back(self.message.id);
-        messages.insert(self.message.id, self.message.clone());
-
-        removed_msg
+        cache.backend.insert_message(self.message.clone(), max)
     }
 }
 
@@ -406,13 +448,13 @@ impl CacheUpdate for MessageUpdateEvent {
     type Output = Message;
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
-        let mut messages = cache.messages.get_mut(&self.channel_id)?;
-        let message = messages.get_mut(&self.id)?;
-        let old_message = message.clone();
+        cache.update_message(self.channel_id, self.id, |message| {
+            let old_message = message.clone();
 
-        self.apply_to_message(message);
+            self.apply_to_message(message);
 
-        Some(old_message)
+            old_message
+        })
     }
 }
 
@@ -429,33 +471,48 @@ impl CacheUpdate for PresenceUpdateEvent {
         }
 
         if let Some(guild_id) = self.presence.guild_id {
-            if let Some(mut guild) = cache.guilds.get_mut(&guild_id) {
-                // If the member went offline, remove them from the presence list.
-                if self.presence.status == OnlineStatus::Offline {
-                    guild.presences.remove(&self.presence.user.id);
-                } else {
-                    guild.presences.insert(self.presence.user.id, self.presence.clone());
+            let cache_presences = cache.settings().guild_fields.contains(GuildFields::PRESENCES);
+
+            // Create a partial member instance out of the presence update data.
+            let member = self.presence.user.to_user().map(|user| Member {
+                deaf: false,
+                guild_id,
+                joined_at: None,
+                mute: false,
+                nick: None,
+                user,
+                roles: vec![],
+                pending: false,
+                premium_since: None,
+                permissions: None,
+                avatar: None,
+                communication_disabled_until: None,
+                flags: GuildMemberFlags::default(),
+                unusual_dm_activity_until: None,
+            });
+            let member = member.and_then(|mut member| {
+                cache.settings().filter_member(&mut member).then_some(member)
+            });
+
+            let member_cached = cache.update_guild(guild_id, |guild| {
+                if cache_presences {
+                    // If the member went offline, remove them from the presence list.
+                    if self.presence.status == OnlineStatus::Offline {
+                        guild.presences.remove(&self.presence.user.id);
+                    } else {
+                        guild.presences.insert(self.presence.user.id, self.presence.clone());
+                    }
                 }
 
-                // Create a partial member instance out of the presence update data.
-                if let Some(user) = self.presence.user.to_user() {
-                    guild.members.entry(self.presence.user.id).or_insert_with(|| Member {
-                        deaf: false,
-                        guild_id,
-                        joined_at: None,
-                        mute: false,
-                        nick: None,
-                        user,
-                        roles: vec![],
-                        pending: false,
-                        premium_since: None,
-                        permissions: None,
-                        avatar: None,
-                        communication_disabled_until: None,
-                        flags: GuildMemberFlags::default(),
-                        unusual_dm_activity_until: None,
-                    });
+                if let Some(member) = member {
+                    guild.members.entry(member.user.id).or_insert(member);
                 }
+
+                guild.members.contains_key(&self.presence.user.id)
+            });
+
+            if member_cached == Some(true) {
+                cache.touch_members(guild_id, [self.presence.user.id]);
             }
         }
 
@@ -470,7 +527,7 @@ impl CacheUpdate for ReadyEvent {
         let ready = self.ready.clone();
 
         for unavailable in ready.guilds {
-            cache.guilds.remove(&unavailable.id);
+            cache.backend.remove_guild(unavailable.id);
             cache.unavailable_guilds.insert(unavailable.id, ());
         }
 
@@ -480,18 +537,17 @@ impl CacheUpdate for ReadyEvent {
             self.ready.guilds.iter().map(|status| status.id).collect::<HashSet<_>>();
         let shard_data = self.ready.shard.unwrap_or_else(|| ShardInfo::new(ShardId(1), 1));
 
-        for guild_entry in cache.guilds.iter() {
-            let guild = guild_entry.key();
+        for guild in cache.backend.guild_ids() {
             // Only handle data for our shard.
-            if crate::utils::shard_id(*guild, shard_data.total) == shard_data.id.0
-                && !ready_guilds_hashset.contains(guild)
+            if crate::utils::shard_id(guild, shard_data.total) == shard_data.id.0
+                && !ready_guilds_hashset.contains(&guild)
             {
-                guilds_to_remove.push(*guild);
+                guilds_to_remove.push(guild);
             }
         }
         if !guilds_to_remove.is_empty() {
             for guild in guilds_to_remove {
-                cache.guilds.remove(&guild);
+                cache.backend.remove_guild(guild);
             }
         }
 
@@ -511,15 +567,20 @@ impl CacheUpdate for ThreadCreateEvent {
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
         let (guild_id, thread_id) = (self.thread.guild_id, self.thread.id);
+        if !cache.settings().guild_fields.contains(GuildFields::THREADS) {
+            return None;
+        }
 
-        cache.guilds.get_mut(&guild_id).and_then(|mut g| {
-            if let Some(i) = g.threads.iter().position(|e| e.id == thread_id) {
-                Some(std::mem::replace(&mut g.threads[i], self.thread.clone()))
-            } else {
-                g.threads.push(self.thread.clone());
-                None
-            }
-        })
+        cache
+            .update_guild(guild_id, |g| {
+                if let Some(i) = g.threads.iter().position(|e| e.id == thread_id) {
+                    Some(std::mem::replace(&mut g.threads[i], self.thread.clone()))
+                } else {
+                    g.threads.push(self.thread.clone());
+                    None
+                }
+            })
+            .flatten()
     }
 }
 
@@ -528,15 +589,20 @@ impl CacheUpdate for ThreadUpdateEvent {
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
         let (guild_id, thread_id) = (self.thread.guild_id, self.thread.id);
+        if !cache.settings().guild_fields.contains(GuildFields::THREADS) {
+            return None;
+        }
 
-        cache.guilds.get_mut(&guild_id).and_then(|mut g| {
-            if let Some(i) = g.threads.iter().position(|e| e.id == thread_id) {
-                Some(std::mem::replace(&mut g.threads[i], self.thread.clone()))
-            } else {
-                g.threads.push(self.thread.clone());
-                None
-            }
-        })
+        cache
+            .update_guild(guild_id, |g| {
+                if let Some(i) = g.threads.iter().position(|e| e.id == thread_id) {
+                    Some(std::mem::replace(&mut g.threads[i], self.thread.clone()))
+                } else {
+                    g.threads.push(self.thread.clone());
+                    None
+                }
+            })
+            .flatten()
     }
 }
 
@@ -546,9 +612,11 @@ impl CacheUpdate for ThreadDeleteEvent {
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
         let (guild_id, thread_id) = (self.thread.guild_id, self.thread.id);
 
-        cache.guilds.get_mut(&guild_id).and_then(|mut g| {
-            g.threads.iter().position(|e| e.id == thread_id).map(|i| g.threads.remove(i))
-        })
+        cache
+            .update_guild(guild_id, |g| {
+                g.threads.iter().position(|e| e.id == thread_id).map(|i| g.threads.remove(i))
+            })
+            .flatten()
     }
 }
 
@@ -565,25 +633,42 @@ impl CacheUpdate for VoiceStateUpdateEvent {
     type Output = VoiceState;
 
     fn update(&mut self, cache: &Cache) -> Option<VoiceState> {
-        if let Some(guild_id) = self.voice_state.guild_id {
-            if let Some(mut guild) = cache.guilds.get_mut(&guild_id) {
-                if let Some(member) = &self.voice_state.member {
-                    guild.members.insert(member.user.id, member.clone());
-                }
+        let guild_id = self.voice_state.guild_id?;
+
+        let (member, cache_voice_states) = {
+            let settings = cache.settings();
+            let member = self
+                .voice_state
+                .member
+                .clone()
+                .and_then(|mut member| settings.filter_member(&mut member).then_some(member));
+            (member, settings.guild_fields.contains(GuildFields::VOICE_STATES))
+        };
+
+        let (old, member_cached) = cache.update_guild(guild_id, |guild| {
+            let member_cached = member.is_some();
+            if let Some(member) = member {
+                guild.members.insert(member.user.id, member);
+            }
 
-                if self.voice_state.channel_id.is_some() {
-                    // Update or add to the voice state list
-                    guild.voice_states.insert(self.voice_state.user_id, self.voice_state.clone())
-                } else {
-                    // Remove the user from the voice state list
-                    guild.voice_states.remove(&self.voice_state.user_id)
-                }
-            } else {
+            let old = if !cache_voice_states {
                 None
-            }
-        } else {
-            None
+            } else if self.voice_state.channel_id.is_some() {
+                // Update or add to the voice state list
+                guild.voice_states.insert(self.voice_state.user_id, self.voice_state.clone())
+            } else {
+                // Remove the user from the voice state list
+                guild.voice_states.remove(&self.voice_state.user_id)
+            };
+
+            (old, member_cached)
+        })?;
+
+        if member_cached {
+            cache.touch_members(guild_id, [self.voice_state.user_id]);
         }
+
+        old
     }
 }
 
@@ -591,11 +676,14 @@ impl CacheUpdate for VoiceChannelStatusUpdateEvent {
     type Output = String;
 
     fn update(&mut self, cache: &Cache) -> Option<Self::Output> {
-        let mut guild = cache.guilds.get_mut(&self.guild_id)?;
-        let channel = guild.channels.get_mut(&self.id)?;
-
-        let old = channel.status.clone();
-        channel.status.clone_from(&self.status);
-        old
+        cache
+            .update_guild(self.guild_id, |guild| {
+                let channel = guild.channels.get_mut(&self.id)?;
+
+                let old = channel.status.clone();
+                channel.status.clone_from(&self.status);
+                old
+            })
+            .flatten()
     }
 }
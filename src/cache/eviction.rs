@@ -0,0 +1,357 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "gateway")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+
+use super::wrappers::BuildHasher;
+use super::Cache;
+use crate::model::prelude::*;
+
+/// How often idle members and orphaned users are swept by the task of [`spawn_sweeper`].
+#[cfg_attr(not(feature = "gateway"), allow(dead_code))]
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The number of members and users evicted from a [`Cache`], as returned by
+/// [`Cache::eviction_metrics`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct EvictionMetrics {
+    /// Members evicted as the least recently seen of a guild over [`Settings::max_members`].
+    ///
+    /// [`Settings::max_members`]: super::Settings::max_members
+    pub members_over_limit: u64,
+    /// Members evicted after going unseen for [`Settings::member_ttl`].
+    ///
+    /// [`Settings::member_ttl`]: super::Settings::member_ttl
+    pub idle_members: u64,
+    /// Users removed as no cached member or presence referred to them anymore.
+    pub orphaned_users: u64,
+}
+
+/// When the members of each guild were last seen in an event, to evict them by recency.
+///
+/// Members are only tracked while eviction is enabled in the settings of the cache.
+#[derive(Debug)]
+pub(super) struct MemberActivity {
+    last_seen: DashMap<GuildId, HashMap<UserId, Instant>, BuildHasher>,
+    /// Users of evicted members, removed on the next sweep unless something still refers to them.
+    orphan_candidates: Mutex<HashSet<UserId>>,
+    members_over_limit: AtomicU64,
+    idle_members: AtomicU64,
+    orphaned_users: AtomicU64,
+}
+
+impl MemberActivity {
+    pub fn new() -> Self {
+        Self {
+            last_seen: DashMap::default(),
+            orphan_candidates: Mutex::new(HashSet::new()),
+            members_over_limit: AtomicU64::new(0),
+            idle_members: AtomicU64::new(0),
+            orphaned_users: AtomicU64::new(0),
+        }
+    }
+}
+
+#[cfg(feature = "typesize")]
+impl typesize::TypeSize for MemberActivity {
+    fn extra_size(&self) -> usize {
+        let entry_size = std::mem::size_of::<(UserId, Instant)>();
+        self.last_seen.iter().map(|guild| guild.capacity() * entry_size).sum()
+    }
+}
+
+impl Cache {
+    /// Records that members of a guild were seen in an event, evicting the least recently seen
+    /// members of the guild if it is over its limit.
+    ///
+    /// This runs as events are dispatched, so it only ever looks at the given guild. Idle members
+    /// and orphaned users are left to [`Self::evict_members`].
+    pub(crate) fn touch_members(
+        &self,
+        guild_id: GuildId,
+        user_ids: impl IntoIterator<Item = UserId>,
+    ) {
+        self.touch_members_at(guild_id, user_ids, Instant::now());
+    }
+
+    /// Like [`Self::touch_members`], but records the members as seen at `now`.
+    pub(crate) fn touch_members_at(
+        &self,
+        guild_id: GuildId,
+        user_ids: impl IntoIterator<Item = UserId>,
+        now: Instant,
+    ) {
+        let (max_members, member_ttl) = {
+            let settings = self.settings();
+            (settings.max_members, settings.member_ttl)
+        };
+        if max_members.is_none() && member_ttl.is_none() {
+            return;
+        }
+
+        let over_limit = {
+            let mut last_seen = self.member_activity.last_seen.entry(guild_id).or_default();
+            last_seen.extend(user_ids.into_iter().map(|user_id| (user_id, now)));
+            max_members.is_some_and(|max| last_seen.len() > max)
+        };
+
+        if over_limit {
+            self.evict_over_limit(guild_id);
+        }
+    }
+
+    /// Stops tracking a member removed from the cache.
+    pub(crate) fn forget_member(&self, guild_id: GuildId, user_id: UserId) {
+        if let Some(mut last_seen) = self.member_activity.last_seen.get_mut(&guild_id) {
+            last_seen.remove(&user_id);
+        }
+    }
+
+    /// Stops tracking the members of a guild removed from the cache.
+    pub(crate) fn forget_guild(&self, guild_id: GuildId) {
+        self.member_activity.last_seen.remove(&guild_id);
+    }
+
+    /// Evicts the members that went unseen for longer than [`Settings::member_ttl`], and the least
+    /// recently seen members of the guilds over [`Settings::max_members`]. The users no cached
+    /// member or presence refers to anymore are then removed, if [`Settings::prune_users`] is set.
+    ///
+    /// This is done once a minute in the background by the [`Client`], but can be done at any
+    /// time, such as after changing the settings.
+    ///
+    /// [`Client`]: crate::Client
+    ///
+    /// [`Settings::member_ttl`]: super::Settings::member_ttl
+    /// [`Settings::max_members`]: super::Settings::max_members
+    /// [`Settings::prune_users`]: super::Settings::prune_users
+    pub fn evict_members(&self) {
+        self.evict_idle_members();
+
+        let guild_ids: Vec<_> = self.member_activity.last_seen.iter().map(|e| *e.key()).collect();
+        for guild_id in guild_ids {
+            self.evict_over_limit(guild_id);
+        }
+
+        let user_ids = std::mem::take(&mut *self.member_activity.orphan_candidates.lock());
+        if !user_ids.is_empty() {
+            self.prune_users(user_ids);
+        }
+    }
+
+    /// Returns the number of members and users evicted from the cache so far.
+    #[must_use]
+    pub fn eviction_metrics(&self) -> EvictionMetrics {
+        let activity = &self.member_activity;
+        EvictionMetrics {
+            members_over_limit: activity.members_over_limit.load(Ordering::Relaxed),
+            idle_members: activity.idle_members.load(Ordering::Relaxed),
+            orphaned_users: activity.orphaned_users.load(Ordering::Relaxed),
+        }
+    }
+
+    fn evict_idle_members(&self) {
+        let Some(member_ttl) = self.settings().member_ttl else { return };
+
+        let mut evicted = Vec::new();
+        for mut guild in self.member_activity.last_seen.iter_mut() {
+            let guild_id = *guild.key();
+            guild.retain(|user_id, last_seen| {
+                let idle = last_seen.elapsed() >= member_ttl;
+                if idle {
+                    evicted.push((guild_id, *user_id));
+                }
+                !idle
+            });
+        }
+
+        let count = self.remove_members(evicted);
+        self.member_activity.idle_members.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn evict_over_limit(&self, guild_id: GuildId) {
+        let Some(max_members) = self.settings().max_members else { return };
+
+        let evicted = {
+            let Some(mut last_seen) = self.member_activity.last_seen.get_mut(&guild_id) else {
+                return;
+            };
+            if last_seen.len() <= max_members {
+                return;
+            }
+
+            // Evict down to a tenth under the limit, so that a full guild doesn't have to look
+            // for its least recently seen member on every event.
+            let target = max_members - max_members / 10;
+            let mut members: Vec<_> = last_seen.iter().map(|(id, seen)| (*seen, *id)).collect();
+            let excess = members.len() - target;
+            if excess < members.len() {
+                members.select_nth_unstable(excess);
+            }
+            members.truncate(excess);
+
+            for (_, user_id) in &members {
+                last_seen.remove(user_id);
+            }
+            members.into_iter().map(|(_, user_id)| (guild_id, user_id)).collect::<Vec<_>>()
+        };
+
+        let count = self.remove_members(evicted);
+        self.member_activity.members_over_limit.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Removes members from their guilds, and marks the users they referred to to be pruned on the
+    /// next sweep. Returns the number of members removed.
+    fn remove_members(&self, members: Vec<(GuildId, UserId)>) -> u64 {
+        if members.is_empty() {
+            return 0;
+        }
+
+        let mut by_guild: HashMap<GuildId, Vec<UserId>> = HashMap::new();
+        for (guild_id, user_id) in members {
+            by_guild.entry(guild_id).or_default().push(user_id);
+        }
+
+        let mut removed = 0;
+        let mut users = HashSet::new();
+        for (guild_id, user_ids) in by_guild {
+            self.update_guild(guild_id, |guild| {
+                for user_id in user_ids {
+                    if guild.members.remove(&user_id).is_some() {
+                        removed += 1;
+                        users.insert(user_id);
+                    }
+                }
+            });
+        }
+
+        if self.settings().prune_users {
+            self.member_activity.orphan_candidates.lock().extend(users);
+        }
+
+        removed
+    }
+
+    /// Removes the given users, unless a cached member or presence still refers to them.
+    fn prune_users(&self, mut user_ids: HashSet<UserId>) {
+        user_ids.remove(&self.current_user().id);
+
+        for guild_id in self.backend.guild_ids() {
+            if user_ids.is_empty() {
+                return;
+            }
+
+            if let Some(guild) = self.backend.guild(guild_id) {
+                user_ids.retain(|user_id| {
+                    !guild.members.contains_key(user_id) && !guild.presences.contains_key(user_id)
+                });
+            }
+        }
+
+        for user_id in user_ids {
+            if self.backend.remove_user(user_id).is_some() {
+                self.member_activity.orphaned_users.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Spawns a task calling [`Cache::evict_members`] every [`SWEEP_INTERVAL`], until the cache is
+/// dropped.
+#[cfg(feature = "gateway")]
+pub(crate) fn spawn_sweeper(cache: &Arc<Cache>) {
+    let cache = Arc::downgrade(cache);
+    crate::internal::tokio::spawn_named("cache::eviction::sweep", async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            let Some(cache) = cache.upgrade() else { break };
+            // The sweep walks every guild, so keep it off the runtime's worker threads.
+            if tokio::task::spawn_blocking(move || cache.evict_members()).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test::create_guild;
+    use crate::cache::Settings;
+
+    fn member_add(guild_id: GuildId, user_id: u64) -> GuildMemberAddEvent {
+        GuildMemberAddEvent {
+            member: Member {
+                guild_id,
+                user: User {
+                    id: UserId::new(user_id),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_seen_members() {
+        let cache = Cache::new_with_settings(Settings::default());
+
+        let (guild_id, other_guild_id) = (GuildId::new(1), GuildId::new(2));
+        for id in [guild_id, other_guild_id] {
+            create_guild(&cache, Guild {
+                id,
+                ..Default::default()
+            });
+        }
+
+        // Nothing is tracked without a limit, so the members are seen at explicit instants below.
+        // The first member is in both guilds, so their user stays cached.
+        cache.update(&mut member_add(other_guild_id, 2));
+        for user_id in 2..=12 {
+            cache.update(&mut member_add(guild_id, user_id));
+        }
+        cache.set_max_members(Some(10));
+
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        cache.touch_members_at(other_guild_id, [UserId::new(2)], at(1));
+        for user_id in 2..=11 {
+            cache.touch_members_at(guild_id, [UserId::new(user_id)], at(user_id));
+        }
+        // Seeing the second member again makes the third one the least recently seen.
+        cache.touch_members_at(guild_id, [UserId::new(3)], at(20));
+        cache.touch_members_at(guild_id, [UserId::new(12)], at(30));
+
+        let guild = cache.guild(guild_id).unwrap();
+        assert_eq!(guild.members.len(), 9);
+        for user_id in [2, 4] {
+            assert!(!guild.members.contains_key(&UserId::new(user_id)));
+        }
+        assert!(guild.members.contains_key(&UserId::new(3)));
+        drop(guild);
+
+        // Users are only pruned once the cache is swept.
+        assert!(cache.user(UserId::new(4)).is_some());
+        cache.evict_members();
+        assert!(cache.user(UserId::new(2)).is_some());
+        assert!(cache.user(UserId::new(4)).is_none());
+        assert_eq!(cache.eviction_metrics(), EvictionMetrics {
+            members_over_limit: 2,
+            idle_members: 0,
+            orphaned_users: 1,
+        });
+
+        cache.set_member_ttl(Some(Duration::ZERO));
+        cache.evict_members();
+        assert!(cache.guild(guild_id).unwrap().members.is_empty());
+        assert_eq!(cache.eviction_metrics().idle_members, 10);
+        assert_eq!(cache.user_count(), 0);
+    }
+}
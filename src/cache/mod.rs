@@ -24,14 +24,12 @@
 //! [`http`]: crate::http
 //! [Manage Guild]: Permissions::MANAGE_GUILD
 
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
 use std::hash::Hash;
 #[cfg(feature = "temp_cache")]
 use std::sync::Arc;
-#[cfg(feature = "temp_cache")]
 use std::time::Duration;
 
-use dashmap::mapref::entry::Entry;
 use dashmap::mapref::one::{MappedRef, Ref};
 use dashmap::DashMap;
 #[cfg(feature = "temp_cache")]
@@ -39,26 +37,34 @@ use mini_moka::sync::Cache as MokaCache;
 use parking_lot::RwLock;
 use tracing::instrument;
 
+pub use self::backend::{CacheBackend, InMemoryBackend};
 pub use self::cache_update::CacheUpdate;
-pub use self::settings::Settings;
+pub use self::eviction::EvictionMetrics;
+use self::eviction::MemberActivity;
+pub use self::settings::{GuildFields, MemberFilter, MemberPredicate, Settings};
+pub use self::snapshot::CacheSnapshot;
 use crate::model::prelude::*;
 
+mod backend;
 mod cache_update;
 mod event;
+mod eviction;
 mod settings;
+mod snapshot;
 mod wrappers;
 
+#[cfg(feature = "gateway")]
+pub(crate) use eviction::spawn_sweeper;
 #[cfg(feature = "temp_cache")]
 pub(crate) use wrappers::MaybeOwnedArc;
 use wrappers::{BuildHasher, MaybeMap, ReadOnlyMapRef};
 
-type MessageCache = DashMap<ChannelId, HashMap<MessageId, Message>, BuildHasher>;
-
 struct NotSend;
 
 enum CacheRefInner<'a, K, V, T> {
     #[cfg(feature = "temp_cache")]
     Arc(Arc<V>),
+    Owned(Box<V>),
     DashRef(Ref<'a, K, V, BuildHasher>),
     DashMappedRef(MappedRef<'a, K, T, V, BuildHasher>),
     ReadGuard(parking_lot::RwLockReadGuard<'a, V>),
@@ -77,6 +83,12 @@ impl<'a, K, V, T> CacheRef<'a, K, V, T> {
         }
     }
 
+    /// Creates a reference to a value the cache doesn't hold on to, such as one loaded by a
+    /// [`CacheBackend`] from outside of memory.
+    pub fn from_owned(value: V) -> Self {
+        Self::new(CacheRefInner::Owned(Box::new(value)))
+    }
+
     #[cfg(feature = "temp_cache")]
     fn from_arc(inner: MaybeOwnedArc<V>) -> Self {
         Self::new(CacheRefInner::Arc(inner.get_inner()))
@@ -95,6 +107,20 @@ impl<'a, K, V, T> CacheRef<'a, K, V, T> {
     }
 }
 
+impl<'a, K: Eq + Hash, V> CacheRef<'a, K, V> {
+    /// Maps the reference to a part of the value, which is cloned if the value isn't borrowed
+    /// from a map.
+    pub(crate) fn try_map<U: Clone>(
+        self,
+        f: impl FnOnce(&V) -> Option<&U>,
+    ) -> Option<CacheRef<'a, K, U, V>> {
+        match self.inner {
+            CacheRefInner::DashRef(inner) => inner.try_map(f).ok().map(CacheRef::from_mapped_ref),
+            _ => f(&self).cloned().map(CacheRef::from_owned),
+        }
+    }
+}
+
 impl<K: Eq + Hash, V, T> std::ops::Deref for CacheRef<'_, K, V, T> {
     type Target = V;
 
@@ -102,6 +128,7 @@ impl<K: Eq + Hash, V, T> std::ops::Deref for CacheRef<'_, K, V, T> {
         match &self.inner {
             #[cfg(feature = "temp_cache")]
             CacheRefInner::Arc(inner) => inner,
+            CacheRefInner::Owned(inner) => inner,
             CacheRefInner::DashRef(inner) => inner.value(),
             CacheRefInner::DashMappedRef(inner) => inner.value(),
             CacheRefInner::ReadGuard(inner) => inner,
@@ -129,6 +156,8 @@ pub(crate) struct CachedShardData {
     pub total: u32,
     pub connected: HashSet<ShardId>,
     pub has_sent_shards_ready: bool,
+    /// The last sequence number each shard received, as of the last event put in the cache.
+    pub seqs: HashMap<ShardId, u64>,
 }
 
 /// A cache containing data received from [`Shard`]s.
@@ -145,6 +174,9 @@ pub(crate) struct CachedShardData {
 /// - presences: [`PresenceUpdateEvent`], [`ReadyEvent`]
 /// - messages: [`MessageCreateEvent`]
 ///
+/// Members are kept until they leave their guild, and users forever, unless eviction is enabled
+/// with [`Settings::member_ttl`] or [`Settings::max_members`], see [`Self::evict_members`].
+///
 /// The documentation of each event contains the required gateway intents.
 ///
 /// [`Shard`]: crate::gateway::Shard
@@ -176,47 +208,23 @@ pub struct Cache {
     #[cfg(feature = "temp_cache")]
     pub(crate) temp_users: MokaCache<UserId, MaybeOwnedArc<User>, BuildHasher>,
 
-    // Channels cache:
-    /// A map of channel ids to the guilds in which the channel data is stored.
-    pub(crate) channels: MaybeMap<ChannelId, GuildId>,
+    // Guilds, channels, users and messages:
+    // ---
+    /// The storage of guilds, channels, users and messages, in memory unless set otherwise.
+    backend: Box<dyn CacheBackend>,
 
-    // Guilds cache:
+    // Unavailable guilds cache:
     // ---
-    /// A map of guilds with full data available. This includes data like [`Role`]s and [`Emoji`]s
-    /// that are not available through the REST API.
-    pub(crate) guilds: MaybeMap<GuildId, Guild>,
     /// A list of guilds which are "unavailable".
     ///
     /// Additionally, guilds are always unavailable for bot users when a Ready is received. Guilds
     /// are "sent in" over time through the receiving of [`Event::GuildCreate`]s.
     pub(crate) unavailable_guilds: MaybeMap<GuildId, ()>,
 
-    // Users cache:
+    // Member eviction:
     // ---
-    /// A map of users that the current user sees.
-    ///
-    /// Users are added to - and updated from - this map via the following received events:
-    ///
-    /// - [`GuildMemberAdd`][`GuildMemberAddEvent`]
-    /// - [`GuildMemberRemove`][`GuildMemberRemoveEvent`]
-    /// - [`GuildMembersChunk`][`GuildMembersChunkEvent`]
-    /// - [`PresenceUpdate`][`PresenceUpdateEvent`]
-    /// - [`Ready`][`ReadyEvent`]
-    ///
-    /// Note, however, that users are _not_ removed from the map on removal events such as
-    /// [`GuildMemberRemove`][`GuildMemberRemoveEvent`], as other structs such as members or
-    /// recipients may still exist.
-    pub(crate) users: MaybeMap<UserId, User>,
-
-    // Messages cache:
-    // ---
-    pub(crate) messages: MessageCache,
-    /// Queue of message IDs for each channel.
-    ///
-    /// This is simply a vecdeque so we can keep track of the order of messages inserted into the
-    /// cache. When a maximum number of messages are in a channel's cache, we can pop the front and
-    /// remove that ID from the cache.
-    pub(crate) message_queue: DashMap<ChannelId, VecDeque<MessageId>, BuildHasher>,
+    /// When members were last seen, while eviction of members is enabled.
+    member_activity: MemberActivity,
 
     // Miscellanous fixed-size data
     // ---
@@ -255,6 +263,23 @@ impl Cache {
     /// ```
     #[instrument]
     pub fn new_with_settings(settings: Settings) -> Self {
+        let backend = InMemoryBackend::new(&settings);
+        Self::new_with_backend(settings, backend)
+    }
+
+    /// Creates a new cache instance with settings applied, storing guilds, channels, users and
+    /// messages in the given backend.
+    ///
+    /// The `cache_guilds`, `cache_channels` and `cache_users` settings are up to the backend to
+    /// apply, as [`InMemoryBackend::new`] does.
+    pub fn new_with_backend(settings: Settings, backend: impl CacheBackend + 'static) -> Self {
+        Self::new_with_boxed_backend(settings, Box::new(backend))
+    }
+
+    pub(crate) fn new_with_boxed_backend(
+        settings: Settings,
+        backend: Box<dyn CacheBackend>,
+    ) -> Self {
         #[cfg(feature = "temp_cache")]
         fn temp_cache<K, V>(ttl: Duration) -> MokaCache<K, V, BuildHasher>
         where
@@ -274,20 +299,15 @@ impl Cache {
             #[cfg(feature = "temp_cache")]
             temp_users: temp_cache(settings.time_to_live),
 
-            channels: MaybeMap(settings.cache_channels.then(DashMap::default)),
-
-            guilds: MaybeMap(settings.cache_guilds.then(DashMap::default)),
+            backend,
             unavailable_guilds: MaybeMap(settings.cache_guilds.then(DashMap::default)),
-
-            users: MaybeMap(settings.cache_users.then(DashMap::default)),
-
-            messages: DashMap::default(),
-            message_queue: DashMap::default(),
+            member_activity: MemberActivity::new(),
 
             shard_data: RwLock::new(CachedShardData {
                 total: 1,
                 connected: HashSet::new(),
                 has_sent_shards_ready: false,
+                seqs: HashMap::new(),
             }),
             user: RwLock::new(CurrentUser::default()),
             settings: RwLock::new(settings),
@@ -321,8 +341,8 @@ impl Cache {
     pub fn unknown_members(&self) -> u64 {
         let mut total = 0;
 
-        for guild_entry in self.guilds.iter() {
-            let guild = guild_entry.value();
+        for guild_id in self.backend.guild_ids() {
+            let Some(guild) = self.backend.guild(guild_id) else { continue };
 
             let members = guild.members.len() as u64;
 
@@ -367,7 +387,7 @@ impl Cache {
 
         let unavailable_guild_ids = unavailable_guilds.iter().map(|i| *i.key());
 
-        self.guilds.iter().map(|i| *i.key()).chain(unavailable_guild_ids).collect()
+        self.backend.guild_ids().into_iter().chain(unavailable_guild_ids).collect()
     }
 
     /// Retrieves a [`GuildChannel`] from the cache based on the given Id.
@@ -378,11 +398,11 @@ impl Cache {
     }
 
     fn _channel(&self, id: ChannelId) -> Option<GuildChannelRef<'_>> {
-        let guild_id = *self.channels.get(&id)?;
-        let guild_ref = self.guilds.get(&guild_id)?;
-        let channel = guild_ref.try_map(|g| g.channels.get(&id)).ok();
-        if let Some(channel) = channel {
-            return Some(CacheRef::from_mapped_ref(channel));
+        let guild_id = self.backend.channel_guild(id)?;
+        let guild_ref = self.backend.guild(guild_id)?;
+        let channel = guild_ref.try_map(|g| g.channels.get(&id));
+        if channel.is_some() {
+            return channel;
         }
 
         #[cfg(feature = "temp_cache")]
@@ -412,7 +432,7 @@ impl Cache {
         &self,
         channel_id: impl Into<ChannelId>,
     ) -> Option<ChannelMessagesRef<'_>> {
-        self.messages.get(&channel_id.into()).map(CacheRef::from_ref)
+        self.backend.channel_messages(channel_id.into())
     }
 
     /// Gets a reference to a guild from the cache based on the given `id`.
@@ -436,12 +456,12 @@ impl Cache {
     }
 
     fn _guild(&self, id: GuildId) -> Option<GuildRef<'_>> {
-        self.guilds.get(&id).map(CacheRef::from_ref)
+        self.backend.guild(id)
     }
 
     /// Returns the number of cached guilds.
     pub fn guild_count(&self) -> usize {
-        self.guilds.len()
+        self.backend.guild_count()
     }
 
     /// Retrieves a [`Guild`]'s member from the cache based on the guild's and user's given Ids.
@@ -498,8 +518,7 @@ impl Cache {
     }
 
     fn _member(&self, guild_id: GuildId, user_id: UserId) -> Option<MemberRef<'_>> {
-        let member = self.guilds.get(&guild_id)?.try_map(|g| g.members.get(&user_id)).ok()?;
-        Some(CacheRef::from_mapped_ref(member))
+        self.backend.guild(guild_id)?.try_map(|g| g.members.get(&user_id))
     }
 
     #[inline]
@@ -509,8 +528,7 @@ impl Cache {
     }
 
     fn _guild_roles(&self, guild_id: GuildId) -> Option<GuildRolesRef<'_>> {
-        let roles = self.guilds.get(&guild_id)?.map(|g| &g.roles);
-        Some(CacheRef::from_mapped_ref(roles))
+        self.backend.guild(guild_id)?.try_map(|g| Some(&g.roles))
     }
 
     /// This method clones and returns all unavailable guilds.
@@ -527,13 +545,12 @@ impl Cache {
     }
 
     fn _guild_channels(&self, guild_id: GuildId) -> Option<GuildChannelsRef<'_>> {
-        let channels = self.guilds.get(&guild_id)?.map(|g| &g.channels);
-        Some(CacheRef::from_mapped_ref(channels))
+        self.backend.guild(guild_id)?.try_map(|g| Some(&g.channels))
     }
 
     /// Returns the number of guild channels in the cache.
     pub fn guild_channel_count(&self) -> usize {
-        self.channels.len()
+        self.backend.channel_count()
     }
 
     /// Returns the number of shards.
@@ -580,9 +597,7 @@ impl Cache {
             return Some(CacheRef::from_arc(message));
         }
 
-        let channel_messages = self.messages.get(&channel_id)?;
-        let message = channel_messages.try_map(|messages| messages.get(&message_id)).ok()?;
-        Some(CacheRef::from_mapped_ref(message))
+        self.backend.message(channel_id, message_id)
     }
 
     /// Retrieves a [`Guild`]'s role by their Ids.
@@ -617,8 +632,7 @@ impl Cache {
     }
 
     fn _role(&self, guild_id: GuildId, role_id: RoleId) -> Option<GuildRoleRef<'_>> {
-        let role = self.guilds.get(&guild_id)?.try_map(|g| g.roles.get(&role_id)).ok()?;
-        Some(CacheRef::from_mapped_ref(role))
+        self.backend.guild(guild_id)?.try_map(|g| g.roles.get(&role_id))
     }
 
     /// Returns the settings.
@@ -646,6 +660,16 @@ impl Cache {
         self.settings.write().max_messages = max;
     }
 
+    /// Sets how long a member can go unseen before being evicted, see [`Settings::member_ttl`].
+    pub fn set_member_ttl(&self, ttl: Option<Duration>) {
+        self.settings.write().member_ttl = ttl;
+    }
+
+    /// Sets the maximum number of members to cache in a guild, see [`Settings::max_members`].
+    pub fn set_max_members(&self, max: Option<usize>) {
+        self.settings.write().max_members = max;
+    }
+
     /// Retrieves a [`User`] from the cache's [`Self::users`] map, if it exists.
     ///
     /// The only advantage of this method is that you can pass in anything that is indirectly a
@@ -672,28 +696,34 @@ impl Cache {
 
     #[cfg(feature = "temp_cache")]
     fn _user(&self, user_id: UserId) -> Option<UserRef<'_>> {
-        if let Some(user) = self.users.get(&user_id) {
-            Some(CacheRef::from_ref(user))
-        } else {
-            self.temp_users.get(&user_id).map(CacheRef::from_arc)
-        }
+        self.backend.user(user_id).or_else(|| self.temp_users.get(&user_id).map(CacheRef::from_arc))
     }
 
     #[cfg(not(feature = "temp_cache"))]
     fn _user(&self, user_id: UserId) -> Option<UserRef<'_>> {
-        self.users.get(&user_id).map(CacheRef::from_ref)
+        self.backend.user(user_id)
     }
 
     /// Clones all users and returns them.
+    ///
+    /// This is empty if the cache was created with a [`CacheBackend`] that doesn't keep its users
+    /// in a map, see [`CacheBackend::users`]. Use [`Self::user_ids`] and [`Self::user`] to go
+    /// through the users of any backend.
     #[inline]
     pub fn users(&self) -> ReadOnlyMapRef<'_, UserId, User> {
-        self.users.as_read_only()
+        self.backend.users()
+    }
+
+    /// Returns the Ids of all cached users, whatever the [`CacheBackend`] keeps them in.
+    #[inline]
+    pub fn user_ids(&self) -> Vec<UserId> {
+        self.backend.user_ids()
     }
 
     /// Returns the amount of cached users.
     #[inline]
     pub fn user_count(&self) -> usize {
-        self.users.len()
+        self.backend.user_count()
     }
 
     /// This method provides a reference to the user used by the bot.
@@ -723,7 +753,7 @@ impl Cache {
 
     /// Clones all channel categories in the given guild and returns them.
     pub fn guild_categories(&self, guild_id: GuildId) -> Option<HashMap<ChannelId, GuildChannel>> {
-        let guild = self.guilds.get(&guild_id)?;
+        let guild = self.backend.guild(guild_id)?;
         Some(
             guild
                 .channels
@@ -749,18 +779,60 @@ impl Cache {
         e.update(self)
     }
 
+    /// Returns the backend storing guilds, channels, users and messages.
+    pub fn backend(&self) -> &dyn CacheBackend {
+        &*self.backend
+    }
+
     pub(crate) fn update_user_entry(&self, user: &User) {
-        if let Some(users) = &self.users.0 {
-            match users.entry(user.id) {
-                Entry::Vacant(e) => {
-                    e.insert(user.clone());
-                },
-                Entry::Occupied(mut e) => {
-                    e.get_mut().clone_from(user);
-                },
-            }
+        if !self.backend.update_user(user.id, &mut |cached| cached.clone_from(user)) {
+            self.backend.insert_user(user.clone());
         }
     }
+
+    /// Updates the user of a member, only inserting it if the member itself is cached.
+    pub(crate) fn update_member_user(&self, user: &User, member_cached: bool) {
+        if member_cached {
+            self.update_user_entry(user);
+        } else {
+            self.backend.update_user(user.id, &mut |cached| cached.clone_from(user));
+        }
+    }
+
+    /// Updates a cached guild in place, returning the result of the update if the guild is cached.
+    pub(crate) fn update_guild<R>(
+        &self,
+        guild_id: GuildId,
+        update: impl FnOnce(&mut Guild) -> R,
+    ) -> Option<R> {
+        let (mut update, mut output) = (Some(update), None);
+        self.backend.update_guild(guild_id, &mut |guild| {
+            output = update.take().map(|update| update(guild));
+        });
+        output
+    }
+
+    /// Updates a cached message in place, returning the result of the update if the message is
+    /// cached.
+    pub(crate) fn update_message<R>(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        update: impl FnOnce(&mut Message) -> R,
+    ) -> Option<R> {
+        let (mut update, mut output) = (Some(update), None);
+        self.backend.update_message(channel_id, message_id, &mut |message| {
+            output = update.take().map(|update| update(message));
+        });
+        output
+    }
+}
+
+#[cfg(feature = "typesize")]
+impl typesize::TypeSize for Box<dyn CacheBackend> {
+    fn extra_size(&self) -> usize {
+        std::mem::size_of_val(&**self) + CacheBackend::extra_size(&**self)
+    }
 }
 
 impl Default for Cache {
@@ -775,6 +847,13 @@ mod test {
     use crate::cache::{Cache, CacheUpdate, Settings};
     use crate::model::prelude::*;
 
+    /// Puts a guild in the cache as if it was received in a [`GuildCreateEvent`].
+    pub(super) fn create_guild(cache: &Cache, guild: Guild) {
+        cache.update(&mut GuildCreateEvent {
+            guild,
+        });
+    }
+
     #[test]
     fn test_cache_messages() {
         let settings = Settings {
@@ -793,25 +872,25 @@ mod test {
         };
 
         // Check that the channel cache doesn't exist.
-        assert!(!cache.messages.contains_key(&event.message.channel_id));
+        assert!(cache.channel_messages(event.message.channel_id).is_none());
         // Add first message, none because message ID 2 doesn't already exist.
         assert!(event.update(&cache).is_none());
         // None, it only returns the oldest message if the cache was already full.
         assert!(event.update(&cache).is_none());
         // Assert there's only 1 message in the channel's message cache.
-        assert_eq!(cache.messages.get(&event.message.channel_id).unwrap().len(), 1);
+        assert_eq!(cache.channel_messages(event.message.channel_id).unwrap().len(), 1);
 
         // Add a second message, assert that channel message cache length is 2.
         event.message.id = MessageId::new(4);
         assert!(event.update(&cache).is_none());
-        assert_eq!(cache.messages.get(&event.message.channel_id).unwrap().len(), 2);
+        assert_eq!(cache.channel_messages(event.message.channel_id).unwrap().len(), 2);
 
         // Add a third message, the first should now be removed.
         event.message.id = MessageId::new(5);
         assert!(event.update(&cache).is_some());
 
         {
-            let channel = cache.messages.get(&event.message.channel_id).unwrap();
+            let channel = cache.channel_messages(event.message.channel_id).unwrap();
 
             assert_eq!(channel.len(), 2);
             // Check that the first message is now removed.
@@ -830,7 +909,7 @@ mod test {
             channel: channel.clone(),
         };
         assert!(cache.update(&mut delete).is_some());
-        assert!(!cache.messages.contains_key(&delete.channel.id));
+        assert!(cache.channel_messages(delete.channel.id).is_none());
 
         // Test deletion of a guild channel's message cache when a GuildDeleteEvent is received.
         let mut guild_create = GuildCreateEvent {
@@ -854,6 +933,6 @@ mod test {
         assert!(cache.update(&mut guild_delete).is_some());
 
         // Assert that the channel's message cache no longer exists.
-        assert!(!cache.messages.contains_key(&ChannelId::new(2)));
+        assert!(cache.channel_messages(ChannelId::new(2)).is_none());
     }
 }
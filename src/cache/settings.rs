@@ -1,5 +1,91 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::model::guild::{Guild, Member};
+use crate::model::id::GuildId;
+
+bitflags! {
+    /// The collections of a [`Guild`] to cache. The other fields of a guild are always cached.
+    #[cfg_attr(feature = "typesize", derive(typesize::derive::TypeSize))]
+    #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+    pub struct GuildFields: u64 {
+        /// [`Guild::channels`], which are also needed to look up channels by their Id.
+        const CHANNELS = 1 << 0;
+        /// [`Guild::roles`].
+        const ROLES = 1 << 1;
+        /// [`Guild::emojis`].
+        const EMOJIS = 1 << 2;
+        /// [`Guild::stickers`].
+        const STICKERS = 1 << 3;
+        /// [`Guild::members`], which can be filtered further with [`Settings::cache_members`].
+        const MEMBERS = 1 << 4;
+        /// [`Guild::presences`].
+        const PRESENCES = 1 << 5;
+        /// [`Guild::voice_states`].
+        const VOICE_STATES = 1 << 6;
+        /// [`Guild::threads`].
+        const THREADS = 1 << 7;
+        /// [`Guild::stage_instances`].
+        const STAGE_INSTANCES = 1 << 8;
+        /// [`Guild::scheduled_events`].
+        const SCHEDULED_EVENTS = 1 << 9;
+    }
+}
+
+/// A predicate deciding whether to cache a member.
+pub type MemberPredicate = Arc<dyn Fn(&Member) -> bool + Send + Sync>;
+
+/// Which members of a guild to cache, as set in [`Settings::cache_members`].
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum MemberFilter {
+    /// Cache every member.
+    #[default]
+    All,
+    /// Cache no members.
+    None,
+    /// Cache the members of the given guilds only.
+    Guilds(HashSet<GuildId>),
+    /// Cache the members for which the predicate returns `true`.
+    Predicate(MemberPredicate),
+}
+
+impl MemberFilter {
+    /// Returns whether a member passes the filter.
+    #[must_use]
+    pub fn allows(&self, member: &Member) -> bool {
+        match self {
+            Self::All => true,
+            Self::None => false,
+            Self::Guilds(guild_ids) => guild_ids.contains(&member.guild_id),
+            Self::Predicate(predicate) => predicate(member),
+        }
+    }
+}
+
+impl fmt::Debug for MemberFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => f.write_str("All"),
+            Self::None => f.write_str("None"),
+            Self::Guilds(guild_ids) => f.debug_tuple("Guilds").field(guild_ids).finish(),
+            Self::Predicate(_) => f.write_str("Predicate"),
+        }
+    }
+}
+
+#[cfg(feature = "typesize")]
+impl typesize::TypeSize for MemberFilter {
+    fn extra_size(&self) -> usize {
+        match self {
+            Self::Guilds(guild_ids) => guild_ids.extra_size(),
+            _ => 0,
+        }
+    }
+}
+
 /// Settings for the cache.
 ///
 /// # Examples
@@ -36,6 +122,87 @@ pub struct Settings {
     ///
     /// Defaults to true.
     pub cache_users: bool,
+    /// Which collections of guilds to cache, such as their presences or voice states.
+    ///
+    /// Defaults to every collection.
+    pub guild_fields: GuildFields,
+    /// Which members of guilds to cache, if [`GuildFields::MEMBERS`] are cached.
+    ///
+    /// Defaults to every member.
+    pub cache_members: MemberFilter,
+    /// Whether to cache the roles of members. Members are cached with no roles otherwise.
+    ///
+    /// Defaults to true.
+    pub cache_member_roles: bool,
+    /// How long a member can go unseen before being evicted from its guild, see
+    /// [`Cache::evict_members`]. A member is seen when an event about it is received, such as a
+    /// presence update or a message it sent.
+    ///
+    /// Defaults to [`None`], keeping members until they leave.
+    ///
+    /// [`Cache::evict_members`]: super::Cache::evict_members
+    pub member_ttl: Option<Duration>,
+    /// The maximum number of members to cache in a guild. Past it, the least recently seen members
+    /// are evicted, down to a tenth under the maximum.
+    ///
+    /// Defaults to [`None`], with no maximum.
+    pub max_members: Option<usize>,
+    /// Whether to remove the users of evicted members when the cache is next swept, unless another
+    /// cached member or presence refers to them. See [`Cache::evict_members`].
+    ///
+    /// Defaults to true.
+    ///
+    /// [`Cache::evict_members`]: super::Cache::evict_members
+    pub prune_users: bool,
+}
+
+impl Settings {
+    /// Returns whether a member should be cached, and strips what shouldn't be cached from it.
+    pub(crate) fn filter_member(&self, member: &mut Member) -> bool {
+        if !self.guild_fields.contains(GuildFields::MEMBERS) || !self.cache_members.allows(member) {
+            return false;
+        }
+
+        if !self.cache_member_roles {
+            member.roles = Vec::new();
+        }
+
+        true
+    }
+
+    /// Strips the fields that shouldn't be cached from a guild.
+    pub(crate) fn filter_guild(&self, guild: &mut Guild) {
+        let fields = self.guild_fields;
+        if !fields.contains(GuildFields::CHANNELS) {
+            guild.channels = HashMap::new();
+        }
+        if !fields.contains(GuildFields::ROLES) {
+            guild.roles = HashMap::new();
+        }
+        if !fields.contains(GuildFields::EMOJIS) {
+            guild.emojis = HashMap::new();
+        }
+        if !fields.contains(GuildFields::STICKERS) {
+            guild.stickers = HashMap::new();
+        }
+        if !fields.contains(GuildFields::PRESENCES) {
+            guild.presences = HashMap::new();
+        }
+        if !fields.contains(GuildFields::VOICE_STATES) {
+            guild.voice_states = HashMap::new();
+        }
+        if !fields.contains(GuildFields::THREADS) {
+            guild.threads = Vec::new();
+        }
+        if !fields.contains(GuildFields::STAGE_INSTANCES) {
+            guild.stage_instances = Vec::new();
+        }
+        if !fields.contains(GuildFields::SCHEDULED_EVENTS) {
+            guild.scheduled_events = Vec::new();
+        }
+
+        guild.members.retain(|_, member| self.filter_member(member));
+    }
 }
 
 impl Default for Settings {
@@ -46,6 +213,132 @@ impl Default for Settings {
             cache_guilds: true,
             cache_channels: true,
             cache_users: true,
+            guild_fields: GuildFields::all(),
+            cache_members: MemberFilter::All,
+            cache_member_roles: true,
+            member_ttl: None,
+            max_members: None,
+            prune_users: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test::create_guild;
+    use crate::cache::Cache;
+    use crate::model::prelude::*;
+
+    #[test]
+    fn events_honor_settings() {
+        let (guild_id, other_guild_id) = (GuildId::new(1), GuildId::new(2));
+        let settings = Settings {
+            guild_fields: GuildFields::CHANNELS | GuildFields::ROLES | GuildFields::MEMBERS,
+            cache_members: MemberFilter::Guilds(HashSet::from([guild_id])),
+            cache_member_roles: false,
+            ..Default::default()
+        };
+        let cache = Cache::new_with_settings(settings);
+
+        let emoji: Emoji =
+            crate::json::from_value(crate::json::json!({"id": "5", "name": "ferris"})).unwrap();
+        let user = User {
+            id: UserId::new(3),
+            ..Default::default()
+        };
+        for id in [guild_id, other_guild_id] {
+            create_guild(&cache, Guild {
+                id,
+                roles: HashMap::from([(RoleId::new(4), Role::default())]),
+                emojis: HashMap::from([(emoji.id, emoji.clone())]),
+                members: HashMap::from([(user.id, Member {
+                    guild_id: id,
+                    user: user.clone(),
+                    roles: vec![RoleId::new(4)],
+                    ..Default::default()
+                })]),
+                ..Default::default()
+            });
         }
+
+        let mut presence_update = PresenceUpdateEvent {
+            presence: Presence {
+                user: PresenceUser {
+                    id: user.id,
+                    ..Default::default()
+                },
+                guild_id: Some(guild_id),
+                status: OnlineStatus::Online,
+                activities: Vec::new(),
+                client_status: None,
+            },
+        };
+        cache.update(&mut presence_update);
+
+        let guild = cache.guild(guild_id).unwrap();
+        assert_eq!(guild.roles.len(), 1);
+        assert!(guild.emojis.is_empty());
+        assert!(guild.presences.is_empty());
+        assert!(guild.members[&user.id].roles.is_empty());
+        assert!(cache.guild(other_guild_id).unwrap().members.is_empty());
+
+        let mut emojis_update = GuildEmojisUpdateEvent {
+            emojis: HashMap::from([(emoji.id, emoji.clone())]),
+            guild_id,
+        };
+        cache.update(&mut emojis_update);
+        assert!(cache.guild(guild_id).unwrap().emojis.is_empty());
+    }
+
+    #[test]
+    fn member_updates_are_filtered() {
+        let settings = Settings {
+            cache_members: MemberFilter::Predicate(Arc::new(|member| member.nick.is_some())),
+            ..Default::default()
+        };
+        let cache = Cache::new_with_settings(settings);
+
+        let guild_id = GuildId::new(1);
+        create_guild(&cache, Guild {
+            id: guild_id,
+            ..Default::default()
+        });
+
+        let user = User {
+            id: UserId::new(2),
+            ..Default::default()
+        };
+        cache.update(&mut GuildMemberAddEvent {
+            member: Member {
+                guild_id,
+                user: user.clone(),
+                ..Default::default()
+            },
+        });
+        assert!(cache.guild(guild_id).unwrap().members.is_empty());
+        assert!(cache.user(user.id).is_none());
+
+        let mut member_update = GuildMemberUpdateEvent {
+            guild_id,
+            nick: Some("ferris".into()),
+            joined_at: Timestamp::now(),
+            roles: Vec::new(),
+            user: user.clone(),
+            premium_since: None,
+            pending: false,
+            deaf: false,
+            mute: false,
+            avatar: None,
+            communication_disabled_until: None,
+            unusual_dm_activity_until: None,
+        };
+        cache.update(&mut member_update);
+        assert!(cache.guild(guild_id).unwrap().members.contains_key(&user.id));
+        assert!(cache.user(user.id).is_some());
+
+        member_update.nick = None;
+        cache.update(&mut member_update);
+        assert!(cache.guild(guild_id).unwrap().members.is_empty());
     }
 }
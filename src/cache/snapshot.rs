@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::Path;
+
+use super::Cache;
+use crate::internal::prelude::*;
+use crate::model::prelude::*;
+
+/// The version of the snapshot format, bumped whenever it changes incompatibly.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// The state of a [`Cache`], as taken by [`Cache::snapshot`] and restored by [`Cache::restore`].
+///
+/// This holds the guilds, with their channels and members, the unavailable guilds, the users, the
+/// current user, the cached messages and the shard data, including the last sequence number each
+/// shard received before the snapshot was taken.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct CacheSnapshot {
+    version: u32,
+    guilds: Vec<Guild>,
+    unavailable_guilds: Vec<GuildId>,
+    users: Vec<User>,
+    current_user: CurrentUser,
+    /// The cached messages of every channel, oldest first within a channel.
+    messages: Vec<Message>,
+    shard_total: u32,
+    /// The last sequence number of every shard, by shard id.
+    shard_seqs: HashMap<u32, u64>,
+}
+
+impl CacheSnapshot {
+    /// The version of the format the snapshot was taken with.
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The last sequence number a shard received before the snapshot was taken, if any.
+    #[must_use]
+    pub fn shard_seq(&self, shard_id: ShardId) -> Option<u64> {
+        self.shard_seqs.get(&shard_id.0).copied()
+    }
+}
+
+impl Cache {
+    /// Takes a snapshot of the state of the cache, to be restored with [`Self::restore`].
+    ///
+    /// The temporary cache is not included. The sequence numbers of the shards are read before
+    /// anything else, so that resuming from the snapshot may replay events already in it, but
+    /// never skips one.
+    pub fn snapshot(&self) -> CacheSnapshot {
+        let (shard_total, shard_seqs) = {
+            let shard_data = self.shard_data.read();
+            let seqs = shard_data.seqs.iter().map(|(id, seq)| (id.0, *seq)).collect();
+            (shard_data.total, seqs)
+        };
+
+        let backend = &self.backend;
+
+        let mut messages = Vec::new();
+        for channel_id in backend.message_channel_ids() {
+            if let Some(channel_messages) = backend.channel_messages(channel_id) {
+                let mut channel_messages: Vec<_> = channel_messages.values().cloned().collect();
+                channel_messages.sort_unstable_by_key(|message| message.id);
+                messages.extend(channel_messages);
+            }
+        }
+
+        CacheSnapshot {
+            version: SNAPSHOT_VERSION,
+            guilds: backend
+                .guild_ids()
+                .into_iter()
+                .filter_map(|id| backend.guild(id))
+                .map(|guild| guild.clone())
+                .collect(),
+            unavailable_guilds: self.unavailable_guilds.iter().map(|i| *i.key()).collect(),
+            users: backend
+                .user_ids()
+                .into_iter()
+                .filter_map(|id| backend.user(id))
+                .map(|user| user.clone())
+                .collect(),
+            current_user: self.current_user().clone(),
+            messages,
+            shard_total,
+            shard_seqs,
+        }
+    }
+
+    /// Restores the state of the cache from a snapshot taken by [`Self::snapshot`].
+    ///
+    /// This is meant to be done on a new cache, before starting the shards: data already in the
+    /// cache is kept, unless the snapshot has the same guilds, users or messages. Once restored,
+    /// shards resuming their session replay the events received after the sequence number
+    /// stored in the snapshot, so that the cache misses none of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] with [`ErrorKind::InvalidData`] if the snapshot was taken with an
+    /// unsupported version of the format.
+    pub fn restore(&self, snapshot: CacheSnapshot) -> Result<()> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            let message = format!("unsupported cache snapshot version {}", snapshot.version);
+            return Err(IoError::new(ErrorKind::InvalidData, message).into());
+        }
+
+        let settings = self.settings().clone();
+        for mut guild in snapshot.guilds {
+            settings.filter_guild(&mut guild);
+            for channel_id in guild.channels.keys() {
+                self.backend.insert_channel(*channel_id, guild.id);
+            }
+
+            let member_ids: Vec<_> = guild.members.keys().copied().collect();
+            let guild_id = guild.id;
+            self.unavailable_guilds.remove(&guild_id);
+            self.backend.insert_guild(guild);
+            self.touch_members(guild_id, member_ids);
+        }
+
+        for guild_id in snapshot.unavailable_guilds {
+            self.unavailable_guilds.insert(guild_id, ());
+        }
+
+        for user in snapshot.users {
+            self.backend.insert_user(user);
+        }
+
+        let max_messages = settings.max_messages;
+        if max_messages > 0 {
+            for message in snapshot.messages {
+                self.backend.insert_message(message, max_messages);
+            }
+        }
+
+        *self.user.write() = snapshot.current_user;
+
+        let mut shard_data = self.shard_data.write();
+        shard_data.total = snapshot.shard_total;
+        shard_data.seqs =
+            snapshot.shard_seqs.into_iter().map(|(id, seq)| (ShardId(id), seq)).collect();
+
+        Ok(())
+    }
+
+    /// Writes a snapshot of the state of the cache to a file, see [`Self::snapshot`].
+    ///
+    /// The snapshot is written to a temporary file next to the given one first, so that a crash
+    /// mid-write can't corrupt a previous snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot can't be serialized or the file can't be written.
+    pub async fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = crate::json::to_vec(&self.snapshot())?;
+
+        let mut tmp = path.to_path_buf().into_os_string();
+        tmp.push(".tmp");
+
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, path).await?;
+
+        Ok(())
+    }
+
+    /// Restores the state of the cache from a file written by [`Self::save_snapshot`], see
+    /// [`Self::restore`].
+    ///
+    /// Returns whether a snapshot was restored, which isn't the case if the file doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// Keep the cache across restarts:
+    ///
+    /// ```rust,no_run
+    /// # use serenity::prelude::*;
+    /// # async fn run(mut client: Client) -> Result<(), serenity::Error> {
+    /// client.cache.load_snapshot("cache.json").await?;
+    /// client.start().await?;
+    ///
+    /// // Once the shards are shut down:
+    /// client.cache.save_snapshot("cache.json").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or deserialized, or if the snapshot was taken
+    /// with an unsupported version of the format.
+    pub async fn load_snapshot(&self, path: impl AsRef<Path>) -> Result<bool> {
+        let bytes = match tokio::fs::read(path.as_ref()).await {
+            Ok(bytes) => bytes,
+            Err(why) if why.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(why) => return Err(why.into()),
+        };
+
+        self.restore(crate::json::from_slice(&bytes)?)?;
+        Ok(true)
+    }
+
+    /// Records the last sequence number a shard received, once its events are in the cache.
+    pub(crate) fn set_shard_seq(&self, shard_id: ShardId, seq: u64) {
+        self.shard_data.write().seqs.insert(shard_id, seq);
+    }
+
+    /// Returns the last sequence number a shard received, as of the last event put in the cache.
+    ///
+    /// After [`Self::restore`], this is the sequence number stored in the snapshot until the shard
+    /// receives new events.
+    pub fn shard_seq(&self, shard_id: ShardId) -> Option<u64> {
+        self.shard_data.read().seqs.get(&shard_id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex, Weak};
+
+    use super::*;
+    use crate::cache::test::create_guild;
+    use crate::cache::{
+        CacheBackend,
+        ChannelMessagesRef,
+        GuildRef,
+        InMemoryBackend,
+        Settings,
+        UserRef,
+    };
+
+    /// A backend in which a message lands while a snapshot lists the channels with messages.
+    #[derive(Debug, Default)]
+    struct EventDuringSnapshot {
+        inner: InMemoryBackend,
+        cache: Arc<Mutex<Option<Weak<Cache>>>>,
+    }
+
+    impl CacheBackend for EventDuringSnapshot {
+        fn guild(&self, guild_id: GuildId) -> Option<GuildRef<'_>> {
+            self.inner.guild(guild_id)
+        }
+
+        fn guild_ids(&self) -> Vec<GuildId> {
+            self.inner.guild_ids()
+        }
+
+        fn guild_count(&self) -> usize {
+            self.inner.guild_count()
+        }
+
+        fn insert_guild(&self, guild: Guild) -> Option<Guild> {
+            self.inner.insert_guild(guild)
+        }
+
+        fn update_guild(&self, guild_id: GuildId, update: &mut dyn FnMut(&mut Guild)) -> bool {
+            self.inner.update_guild(guild_id, update)
+        }
+
+        fn remove_guild(&self, guild_id: GuildId) -> Option<Guild> {
+            self.inner.remove_guild(guild_id)
+        }
+
+        fn channel_guild(&self, channel_id: ChannelId) -> Option<GuildId> {
+            self.inner.channel_guild(channel_id)
+        }
+
+        fn channel_count(&self) -> usize {
+            self.inner.channel_count()
+        }
+
+        fn insert_channel(&self, channel_id: ChannelId, guild_id: GuildId) -> Option<GuildId> {
+            self.inner.insert_channel(channel_id, guild_id)
+        }
+
+        fn remove_channel(&self, channel_id: ChannelId) -> Option<GuildId> {
+            self.inner.remove_channel(channel_id)
+        }
+
+        fn user(&self, user_id: UserId) -> Option<UserRef<'_>> {
+            self.inner.user(user_id)
+        }
+
+        fn user_ids(&self) -> Vec<UserId> {
+            self.inner.user_ids()
+        }
+
+        fn user_count(&self) -> usize {
+            self.inner.user_count()
+        }
+
+        fn insert_user(&self, user: User) -> Option<User> {
+            self.inner.insert_user(user)
+        }
+
+        fn update_user(&self, user_id: UserId, update: &mut dyn FnMut(&mut User)) -> bool {
+            self.inner.update_user(user_id, update)
+        }
+
+        fn remove_user(&self, user_id: UserId) -> Option<User> {
+            self.inner.remove_user(user_id)
+        }
+
+        fn message_channel_ids(&self) -> Vec<ChannelId> {
+            let cache = self.cache.lock().unwrap().take().and_then(|cache| cache.upgrade());
+            if let Some(cache) = cache {
+                let mut message_create = MessageCreateEvent {
+                    message: Message {
+                        id: MessageId::new(2),
+                        channel_id: ChannelId::new(1),
+                        ..Default::default()
+                    },
+                };
+                cache.update(&mut message_create);
+                cache.set_shard_seq(ShardId(0), 2);
+            }
+
+            self.inner.message_channel_ids()
+        }
+
+        fn channel_messages(&self, channel_id: ChannelId) -> Option<ChannelMessagesRef<'_>> {
+            self.inner.channel_messages(channel_id)
+        }
+
+        fn insert_message(&self, message: Message, max_messages: usize) -> Option<Message> {
+            self.inner.insert_message(message, max_messages)
+        }
+
+        fn update_message(
+            &self,
+            channel_id: ChannelId,
+            message_id: MessageId,
+            update: &mut dyn FnMut(&mut Message),
+        ) -> bool {
+            self.inner.update_message(channel_id, message_id, update)
+        }
+
+        fn remove_message(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message> {
+            self.inner.remove_message(channel_id, message_id)
+        }
+
+        fn remove_channel_messages(
+            &self,
+            channel_id: ChannelId,
+        ) -> Option<HashMap<MessageId, Message>> {
+            self.inner.remove_channel_messages(channel_id)
+        }
+    }
+
+    #[test]
+    fn restores_snapshot() {
+        let settings = Settings {
+            max_messages: 2,
+            ..Default::default()
+        };
+        let cache = Cache::new_with_settings(settings.clone());
+
+        let (guild_id, channel_id) = (GuildId::new(1), ChannelId::new(2));
+        let user = User {
+            id: UserId::new(3),
+            name: "ferris".to_string(),
+            ..Default::default()
+        };
+        create_guild(&cache, Guild {
+            id: guild_id,
+            name: "crabs".to_string(),
+            channels: HashMap::from([(channel_id, GuildChannel {
+                id: channel_id,
+                guild_id,
+                ..Default::default()
+            })]),
+            members: HashMap::from([(user.id, Member {
+                guild_id,
+                user: user.clone(),
+                ..Default::default()
+            })]),
+            ..Default::default()
+        });
+        cache.unavailable_guilds.insert(GuildId::new(4), ());
+
+        for id in [5, 6, 7] {
+            let mut message_create = MessageCreateEvent {
+                message: Message {
+                    id: MessageId::new(id),
+                    channel_id,
+                    ..Default::default()
+                },
+            };
+            cache.update(&mut message_create);
+        }
+        cache.set_shard_seq(ShardId(0), 42);
+
+        let json = crate::json::to_vec(&cache.snapshot()).unwrap();
+        let snapshot: CacheSnapshot = crate::json::from_slice(&json).unwrap();
+        assert_eq!(snapshot.shard_seq(ShardId(0)), Some(42));
+
+        let restored = Cache::new_with_settings(settings);
+        restored.restore(snapshot.clone()).unwrap();
+
+        assert_eq!(restored.guild(guild_id).unwrap().name, "crabs");
+        assert!(restored.guild(guild_id).unwrap().members.contains_key(&user.id));
+        assert_eq!(restored.guild_channel_count(), 1);
+        assert_eq!(restored.user(user.id).unwrap().name, "ferris");
+        assert_eq!(restored.guilds().len(), 2);
+        assert_eq!(restored.shard_seq(ShardId(0)), Some(42));
+
+        // The oldest message was already evicted, and the queue is rebuilt in order.
+        let messages = restored.channel_messages(channel_id).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(!messages.contains_key(&MessageId::new(5)));
+        drop(messages);
+        let mut message_create = MessageCreateEvent {
+            message: Message {
+                id: MessageId::new(8),
+                channel_id,
+                ..Default::default()
+            },
+        };
+        let evicted = restored.update(&mut message_create).unwrap();
+        assert_eq!(evicted.id, MessageId::new(6));
+
+        let mut outdated = snapshot;
+        outdated.version = SNAPSHOT_VERSION + 1;
+        assert!(Cache::new().restore(outdated).is_err());
+    }
+
+    #[test]
+    fn snapshot_reads_seqs_first() {
+        let settings = Settings {
+            max_messages: 10,
+            ..Default::default()
+        };
+        let backend = EventDuringSnapshot::default();
+        let slot = Arc::clone(&backend.cache);
+        let cache = Arc::new(Cache::new_with_backend(settings, backend));
+        cache.set_shard_seq(ShardId(0), 1);
+        *slot.lock().unwrap() = Some(Arc::downgrade(&cache));
+
+        // The message of the second event is in the snapshot, but its seq isn't, so resuming
+        // replays it rather than skip it.
+        let snapshot = cache.snapshot();
+        assert_eq!(snapshot.shard_seq(ShardId(0)), Some(1));
+        assert_eq!(snapshot.messages.len(), 1);
+        assert_eq!(cache.shard_seq(ShardId(0)), Some(2));
+    }
+}
@@ -69,6 +69,10 @@ impl<K: Eq + Hash + TypeSize, V: TypeSize> TypeSize for MaybeMap<K, V> {
 /// map without allowing mutation of internal cache fields, which could cause issues.
 pub struct ReadOnlyMapRef<'a, K: Eq + Hash, V>(Option<&'a DashMap<K, V, BuildHasher>>);
 impl<'a, K: Eq + Hash, V> ReadOnlyMapRef<'a, K, V> {
+    pub(crate) fn empty() -> Self {
+        Self(None)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = RefMulti<'_, K, V, BuildHasher>> {
         self.0.into_iter().flat_map(DashMap::iter)
     }
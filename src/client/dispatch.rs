@@ -13,8 +13,6 @@ use crate::internal::tokio::spawn_named;
 use crate::model::channel::ChannelType;
 use crate::model::event::Event;
 use crate::model::guild::Member;
-#[cfg(feature = "cache")]
-use crate::model::id::GuildId;
 
 #[cfg(feature = "cache")]
 macro_rules! if_cache {
@@ -175,8 +173,7 @@ fn update_cache_with_event(
                 if cache.unavailable_guilds.len() == 0 {
                     cache.unavailable_guilds.shrink_to_fit();
 
-                    let guild_amount =
-                        cache.guilds.iter().map(|i| *i.key()).collect::<Vec<GuildId>>();
+                    let guild_amount = cache.backend().guild_ids();
 
                     extra_event = Some(FullEvent::CacheReady {
                         guilds: guild_amount,
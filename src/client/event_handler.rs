@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 
 use super::context::Context;
-use crate::gateway::ShardStageUpdateEvent;
+use crate::gateway::{ReshardEvent, ShardStageUpdateEvent};
 use crate::http::RatelimitInfo;
 use crate::model::prelude::*;
 
@@ -21,7 +21,7 @@ macro_rules! event_handler {
                 $( #[deprecated = $deprecated] )?
                 async fn $method_name(&self, $($context: Context,)? $( $arg_name: $arg_type ),*) {
                     // Suppress unused argument warnings
-                    drop(( $($context,)? $($arg_name),* ))
+                    let _ = ( $($context,)? $($arg_name),* );
                 }
             )*
         }
@@ -483,6 +483,11 @@ event_handler! {
 
     /// Dispatched when an HTTP rate limit is hit
     Ratelimit { data: RatelimitInfo } => async fn ratelimit(&self);
+
+    /// Dispatched when a [`ShardManager::reshard`] makes progress.
+    ///
+    /// [`ShardManager::reshard`]: crate::gateway::ShardManager::reshard
+    Reshard { event: ReshardEvent } => async fn reshard(&self);
 }
 
 /// This core trait for handling raw events
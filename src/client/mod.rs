@@ -43,14 +43,23 @@ use super::gateway::GatewayError;
 #[cfg(feature = "cache")]
 pub use crate::cache::Cache;
 #[cfg(feature = "cache")]
-use crate::cache::Settings as CacheSettings;
+use crate::cache::{CacheBackend, Settings as CacheSettings};
 #[cfg(feature = "framework")]
 use crate::framework::Framework;
+#[cfg(feature = "gateway")]
+use crate::gateway::replay::GatewayRecorder;
 #[cfg(feature = "voice")]
 use crate::gateway::VoiceGatewayManager;
 use crate::gateway::{ActivityData, PresenceData};
 #[cfg(feature = "gateway")]
-use crate::gateway::{ShardManager, ShardManagerOptions};
+use crate::gateway::{
+    GatewayEncoding,
+    GatewayTransport,
+    SessionStore,
+    ShardManager,
+    ShardManagerOptions,
+    TransportCompression,
+};
 use crate::http::Http;
 use crate::internal::prelude::*;
 #[cfg(feature = "gateway")]
@@ -67,6 +76,8 @@ pub struct ClientBuilder {
     intents: GatewayIntents,
     #[cfg(feature = "cache")]
     cache_settings: CacheSettings,
+    #[cfg(feature = "cache")]
+    cache_backend: Option<Box<dyn CacheBackend>>,
     #[cfg(feature = "framework")]
     framework: Option<Box<dyn Framework>>,
     #[cfg(feature = "voice")]
@@ -74,6 +85,11 @@ pub struct ClientBuilder {
     event_handlers: Vec<Arc<dyn EventHandler>>,
     raw_event_handlers: Vec<Arc<dyn RawEventHandler>>,
     presence: PresenceData,
+    compression: TransportCompression,
+    encoding: GatewayEncoding,
+    transport: Option<Arc<dyn GatewayTransport>>,
+    recorder: Option<Arc<GatewayRecorder>>,
+    session_store: Option<Arc<dyn SessionStore>>,
 }
 
 #[cfg(feature = "gateway")]
@@ -85,6 +101,8 @@ impl ClientBuilder {
             intents,
             #[cfg(feature = "cache")]
             cache_settings: CacheSettings::default(),
+            #[cfg(feature = "cache")]
+            cache_backend: None,
             #[cfg(feature = "framework")]
             framework: None,
             #[cfg(feature = "voice")]
@@ -92,6 +110,11 @@ impl ClientBuilder {
             event_handlers: vec![],
             raw_event_handlers: vec![],
             presence: PresenceData::default(),
+            compression: TransportCompression::default(),
+            encoding: GatewayEncoding::default(),
+            transport: None,
+            recorder: None,
+            session_store: None,
         }
     }
 
@@ -179,6 +202,23 @@ impl ClientBuilder {
         &self.cache_settings
     }
 
+    /// Sets the backend storing the guilds, channels, users and messages of the cache, instead of
+    /// keeping them in memory. Refer to [`CacheBackend`] for more information.
+    #[cfg(feature = "cache")]
+    pub fn cache_backend<B>(mut self, backend: B) -> Self
+    where
+        B: CacheBackend + 'static,
+    {
+        self.cache_backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Gets the cache backend, if set. See [`Self::cache_backend`] for more info.
+    #[cfg(feature = "cache")]
+    pub fn get_cache_backend(&self) -> Option<&dyn CacheBackend> {
+        self.cache_backend.as_deref()
+    }
+
     /// Sets the command framework to be used. It will receive messages sent over the gateway and
     /// then consider - based on its settings - whether to dispatch a command.
     ///
@@ -320,6 +360,91 @@ impl ClientBuilder {
     pub fn get_presence(&self) -> &PresenceData {
         &self.presence
     }
+
+    /// Sets the transport compression used for the gateway connections of all shards.
+    ///
+    /// Defaults to [`TransportCompression::None`], in which case only large payloads are
+    /// compressed.
+    pub fn transport_compression(mut self, compression: TransportCompression) -> Self {
+        self.compression = compression;
+
+        self
+    }
+
+    /// Gets the transport compression. See [`Self::transport_compression`] for more info.
+    pub fn get_transport_compression(&self) -> TransportCompression {
+        self.compression
+    }
+
+    /// Sets the encoding of the payloads sent over the gateway connections of all shards.
+    ///
+    /// Defaults to [`GatewayEncoding::Json`].
+    pub fn gateway_encoding(mut self, encoding: GatewayEncoding) -> Self {
+        self.encoding = encoding;
+
+        self
+    }
+
+    /// Gets the gateway encoding. See [`Self::gateway_encoding`] for more info.
+    pub fn get_gateway_encoding(&self) -> GatewayEncoding {
+        self.encoding
+    }
+
+    /// Sets the transport that shards open their gateway connections with, instead of connecting
+    /// over a [`WebSocketTransport`].
+    ///
+    /// This allows routing shards through a gateway proxy, or feeding them payloads from memory.
+    ///
+    /// [`WebSocketTransport`]: crate::gateway::WebSocketTransport
+    pub fn gateway_transport<T>(mut self, transport: T) -> Self
+    where
+        T: GatewayTransport + 'static,
+    {
+        self.transport = Some(Arc::new(transport));
+
+        self
+    }
+
+    /// Gets the gateway transport, if set. See [`Self::gateway_transport`] for more info.
+    pub fn get_gateway_transport(&self) -> Option<Arc<dyn GatewayTransport>> {
+        self.transport.clone()
+    }
+
+    /// Sets a recorder that every payload received by the shards is written to.
+    ///
+    /// Recordings can be replayed with a [`ReplayTransport`], see the [`replay`] module.
+    ///
+    /// [`ReplayTransport`]: crate::gateway::replay::ReplayTransport
+    /// [`replay`]: crate::gateway::replay
+    pub fn gateway_recorder(mut self, recorder: GatewayRecorder) -> Self {
+        self.recorder = Some(Arc::new(recorder));
+
+        self
+    }
+
+    /// Gets the gateway recorder, if set. See [`Self::gateway_recorder`] for more info.
+    pub fn get_gateway_recorder(&self) -> Option<Arc<GatewayRecorder>> {
+        self.recorder.clone()
+    }
+
+    /// Sets the store that shard sessions are saved to by [`ShardManager::shutdown_all`].
+    ///
+    /// Shards with a saved session resume it when started, instead of identifying anew. This
+    /// avoids replaying the initial `GUILD_CREATE`s and spending the IDENTIFY ratelimit when the
+    /// process is restarted, as long as it comes back before Discord expires the sessions.
+    pub fn session_store<S>(mut self, session_store: S) -> Self
+    where
+        S: SessionStore + 'static,
+    {
+        self.session_store = Some(Arc::new(session_store));
+
+        self
+    }
+
+    /// Gets the session store, if set. See [`Self::session_store`] for more info.
+    pub fn get_session_store(&self) -> Option<Arc<dyn SessionStore>> {
+        self.session_store.clone()
+    }
 }
 
 #[cfg(feature = "gateway")]
@@ -337,6 +462,11 @@ impl IntoFuture for ClientBuilder {
         let raw_event_handlers = self.raw_event_handlers;
         let intents = self.intents;
         let presence = self.presence;
+        let compression = self.compression;
+        let encoding = self.encoding;
+        let transport = self.transport;
+        let recorder = self.recorder;
+        let session_store = self.session_store;
 
         let mut http = self.http;
 
@@ -356,9 +486,15 @@ impl IntoFuture for ClientBuilder {
         let voice_manager = self.voice_manager;
 
         #[cfg(feature = "cache")]
-        let cache = Arc::new(Cache::new_with_settings(self.cache_settings));
+        let cache = Arc::new(match self.cache_backend {
+            Some(backend) => Cache::new_with_boxed_backend(self.cache_settings, backend),
+            None => Cache::new_with_settings(self.cache_settings),
+        });
 
         Box::pin(async move {
+            #[cfg(feature = "cache")]
+            crate::cache::spawn_sweeper(&cache);
+
             let ws_url = Arc::new(Mutex::new(match http.get_gateway().await {
                 Ok(response) => response.url,
                 Err(err) => {
@@ -386,6 +522,12 @@ impl IntoFuture for ClientBuilder {
                 http: Arc::clone(&http),
                 intents,
                 presence: Some(presence),
+                compression,
+                encoding,
+                transport,
+                recorder,
+                session_store,
+                identify_limiter: None,
             });
 
             let client = Client {
@@ -18,3 +18,43 @@ pub struct ShardStageUpdateEvent {
     /// The ID of the shard that had its connection stage change.
     pub shard_id: ShardId,
 }
+
+/// An event denoting the progress of a [`ShardManager::reshard`].
+///
+/// [`ShardManager::reshard`]: super::ShardManager::reshard
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum ReshardEvent {
+    /// The new set of shards is being started, alongside the current one.
+    Started {
+        /// The total number of shards before resharding.
+        old_total: u32,
+        /// The total number of shards after resharding.
+        new_total: u32,
+    },
+    /// Another shard of the new set became ready.
+    ShardReady {
+        /// The ID of the shard, out of the new total.
+        shard_id: ShardId,
+        /// The number of shards of the new set that are ready.
+        ready: u32,
+        /// The number of shards in the new set.
+        count: u32,
+    },
+    /// Events are now dispatched from the new set of shards, and the old set is shut down.
+    Switched {
+        /// The total number of shards after resharding.
+        new_total: u32,
+    },
+    /// The old set of shards has been shut down, completing the resharding.
+    Finished {
+        /// The total number of shards after resharding.
+        new_total: u32,
+    },
+    /// The new set of shards did not become ready in time and was shut down. The old set keeps
+    /// running.
+    Failed {
+        /// The total number of shards the resharding was attempted with.
+        new_total: u32,
+    },
+}
@@ -52,10 +52,12 @@ mod voice;
 use std::fmt;
 use std::time::Duration as StdDuration;
 
-pub use self::event::ShardStageUpdateEvent;
+pub use self::event::{ReshardEvent, ShardStageUpdateEvent};
 pub use self::shard_manager::{ShardManager, ShardManagerOptions};
 pub use self::shard_messenger::ShardMessenger;
-pub use self::shard_queuer::ShardQueuer;
+#[cfg(feature = "cluster")]
+pub(crate) use self::shard_queuer::WAIT_BETWEEN_BOOTS_IN_SECONDS;
+pub use self::shard_queuer::{IdentifyLimiter, ShardQueuer};
 pub use self::shard_runner::{ShardRunner, ShardRunnerOptions};
 pub use self::shard_runner_message::ShardRunnerMessage;
 #[cfg(feature = "voice")]
@@ -75,6 +77,11 @@ pub enum ShardQueuerMessage {
     Shutdown,
     /// Message to dequeue/shutdown a shard.
     ShutdownShard(ShardId, u16),
+    /// Message to start a shard of the new set brought up by [`ShardManager::reshard`], where the
+    /// 0-index element is the ID of the Shard to start and the 1-index element is the new total.
+    ///
+    /// The shard's events are not dispatched until the manager switches over to the new set.
+    StartStaged(ShardId, ShardId),
 }
 
 /// Information about a [`ShardRunner`].
@@ -1,26 +1,45 @@
 use std::collections::{HashMap, VecDeque};
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 #[cfg(feature = "framework")]
 use std::sync::OnceLock;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::{fmt, mem};
 
 use futures::channel::mpsc::{self, UnboundedReceiver as Receiver, UnboundedSender as Sender};
 use futures::{SinkExt, StreamExt};
 use tokio::sync::{Mutex, RwLock};
-use tokio::time::timeout;
-use tracing::{info, instrument, warn};
+use tokio::time::{sleep, timeout};
+use tracing::{debug, info, instrument, warn};
 use typemap_rev::TypeMap;
 
 #[cfg(feature = "voice")]
 use super::VoiceGatewayManager;
-use super::{ShardId, ShardQueuer, ShardQueuerMessage, ShardRunnerInfo};
+use super::{
+    IdentifyLimiter,
+    ReshardEvent,
+    ShardId,
+    ShardQueuer,
+    ShardQueuerMessage,
+    ShardRunnerInfo,
+    ShardRunnerMessage,
+};
 #[cfg(feature = "cache")]
 use crate::cache::Cache;
 use crate::client::{EventHandler, RawEventHandler};
 #[cfg(feature = "framework")]
 use crate::framework::Framework;
-use crate::gateway::{ConnectionStage, GatewayError, PresenceData};
+use crate::gateway::replay::GatewayRecorder;
+use crate::gateway::{
+    ConnectionStage,
+    GatewayEncoding,
+    GatewayError,
+    GatewayTransport,
+    PresenceData,
+    SessionStore,
+    TransportCompression,
+    WebSocketTransport,
+};
 use crate::http::Http;
 use crate::internal::prelude::*;
 use crate::internal::tokio::spawn_named;
@@ -53,7 +72,12 @@ use crate::model::gateway::GatewayIntents;
 ///
 /// use serenity::client::{EventHandler, RawEventHandler};
 /// use serenity::framework::{Framework, StandardFramework};
-/// use serenity::gateway::{ShardManager, ShardManagerOptions};
+/// use serenity::gateway::{
+///     GatewayEncoding,
+///     ShardManager,
+///     ShardManagerOptions,
+///     TransportCompression,
+/// };
 /// use serenity::http::Http;
 /// use serenity::model::gateway::GatewayIntents;
 /// use serenity::prelude::*;
@@ -89,13 +113,18 @@ use crate::model::gateway::GatewayIntents;
 ///     # http,
 ///     intents: GatewayIntents::non_privileged(),
 ///     presence: None,
+///     compression: TransportCompression::Zlib,
+///     encoding: GatewayEncoding::Json,
+///     transport: None,
+///     recorder: None,
+///     session_store: None,
+///     identify_limiter: None,
 /// });
 /// # Ok(())
 /// # }
 /// ```
 ///
 /// [`Client`]: crate::Client
-#[derive(Debug)]
 pub struct ShardManager {
     return_value_tx: Mutex<Sender<Result<(), GatewayError>>>,
     /// The shard runners currently managed.
@@ -116,6 +145,34 @@ pub struct ShardManager {
     shard_shutdown: Mutex<Receiver<ShardId>>,
     shard_shutdown_send: Sender<ShardId>,
     gateway_intents: GatewayIntents,
+    persist_sessions: bool,
+    event_handlers: Vec<Arc<dyn EventHandler>>,
+    /// The shard set whose events are dispatched. Runners of other sets stay silent.
+    generation: AtomicU64,
+    /// The shard set being brought up by [`Self::reshard`], if any.
+    pub(super) staged: Mutex<Option<StagedShards>>,
+    resharding: Mutex<()>,
+}
+
+/// The runners of a shard set that is not dispatching events yet.
+#[derive(Debug)]
+pub(super) struct StagedShards {
+    pub(super) generation: u64,
+    pub(super) runners: HashMap<ShardId, ShardRunnerInfo>,
+}
+
+impl fmt::Debug for ShardManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardManager")
+            .field("runners", &self.runners)
+            .field("shard_index", &self.shard_index)
+            .field("shard_init", &self.shard_init)
+            .field("shard_total", &self.shard_total)
+            .field("gateway_intents", &self.gateway_intents)
+            .field("generation", &self.generation)
+            .field("staged", &self.staged)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ShardManager {
@@ -139,6 +196,11 @@ impl ShardManager {
             shard_shutdown_send: shutdown_send,
             runners: Arc::clone(&runners),
             gateway_intents: opt.intents,
+            persist_sessions: opt.session_store.is_some(),
+            event_handlers: opt.event_handlers.clone(),
+            generation: AtomicU64::new(0),
+            staged: Mutex::new(None),
+            resharding: Mutex::new(()),
         });
 
         let mut shard_queuer = ShardQueuer {
@@ -148,6 +210,9 @@ impl ShardManager {
             #[cfg(feature = "framework")]
             framework: opt.framework,
             last_start: None,
+            bucket_starts: HashMap::new(),
+            session_start_limit: None,
+            session_start_limit_reset: None,
             manager: Arc::clone(&manager),
             queue: VecDeque::new(),
             runners,
@@ -160,6 +225,12 @@ impl ShardManager {
             http: opt.http,
             intents: opt.intents,
             presence: opt.presence,
+            compression: opt.compression,
+            encoding: opt.encoding,
+            transport: opt.transport.unwrap_or_else(|| Arc::new(WebSocketTransport)),
+            recorder: opt.recorder,
+            session_store: opt.session_store,
+            identify_limiter: opt.identify_limiter,
         };
 
         spawn_named("shard_queuer::run", async move {
@@ -210,6 +281,185 @@ impl ShardManager {
         self.shard_total.store(total, Ordering::Relaxed);
     }
 
+    /// Reshards to a new total number of shards, without dropping events.
+    ///
+    /// The new set of shards is started alongside the current one, without dispatching its events.
+    /// Once every new shard is ready, event dispatch switches over to the new set and the old set
+    /// is shut down. If the manager only runs a slice of the shards, it runs the same proportion of
+    /// the new total afterwards.
+    ///
+    /// Progress is reported to [`EventHandler::reshard`] as [`ReshardEvent`]s.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GatewayError::ReshardTimedOut`] if the new shards were not all ready within
+    /// `ready_timeout`. The new shards are then shut down, and the old ones keep running.
+    #[instrument(skip(self))]
+    pub async fn reshard(&self, shard_total: u32, ready_timeout: Duration) -> Result<()> {
+        let _resharding = self.resharding.lock().await;
+
+        let old_index = self.shard_index.load(Ordering::Relaxed);
+        let old_init = self.shard_init.load(Ordering::Relaxed);
+        let old_total = self.shard_total.load(Ordering::Relaxed);
+
+        // Never larger than `shard_total`, as `id` is at most `old_total`.
+        let scale = |id: u32| {
+            let scaled = u64::from(id) * u64::from(shard_total) / u64::from(old_total.max(1));
+            u32::try_from(scaled).unwrap_or(shard_total)
+        };
+        let index = scale(old_index);
+        let init = scale(old_index + old_init) - index;
+
+        info!("Resharding from {} to {} shards", old_total, shard_total);
+        self.dispatch_reshard(&ReshardEvent::Started {
+            old_total,
+            new_total: shard_total,
+        });
+
+        let generation = self.generation.load(Ordering::SeqCst) + 1;
+        *self.staged.lock().await = Some(StagedShards {
+            generation,
+            runners: HashMap::new(),
+        });
+
+        for shard_id in index..index + init {
+            self.boot_staged(generation, ShardId(shard_id), shard_total).await;
+        }
+
+        let deadline = Instant::now() + ready_timeout;
+        let mut ready: Vec<ShardId> = Vec::new();
+        while ready.len() < init as usize {
+            if Instant::now() >= deadline {
+                warn!("Timed out waiting for the new shards, aborting resharding");
+
+                let staged = self.staged.lock().await.take();
+                if let Some(staged) = staged {
+                    self.shutdown_runners(staged.runners).await;
+                }
+
+                self.dispatch_reshard(&ReshardEvent::Failed {
+                    new_total: shard_total,
+                });
+                return Err(Error::Gateway(GatewayError::ReshardTimedOut));
+            }
+
+            sleep(Duration::from_millis(100)).await;
+
+            let newly_ready = self.staged.lock().await.as_ref().map_or_else(Vec::new, |staged| {
+                staged
+                    .runners
+                    .iter()
+                    .filter(|(id, info)| {
+                        info.stage == ConnectionStage::Connected && !ready.contains(*id)
+                    })
+                    .map(|(id, _)| *id)
+                    .collect()
+            });
+
+            for shard_id in newly_ready {
+                ready.push(shard_id);
+                self.dispatch_reshard(&ReshardEvent::ShardReady {
+                    shard_id,
+                    ready: u32::try_from(ready.len()).unwrap_or(init),
+                    count: init,
+                });
+            }
+        }
+
+        let old_runners = {
+            let mut runners = self.runners.lock().await;
+            let staged = self.staged.lock().await.take().map(|s| s.runners).unwrap_or_default();
+
+            self.shard_index.store(index, Ordering::Relaxed);
+            self.shard_init.store(init, Ordering::Relaxed);
+            self.shard_total.store(shard_total, Ordering::Relaxed);
+            self.generation.store(generation, Ordering::SeqCst);
+
+            mem::replace(&mut *runners, staged)
+        };
+
+        info!("Switched to {} shards, shutting down the old ones", shard_total);
+        self.dispatch_reshard(&ReshardEvent::Switched {
+            new_total: shard_total,
+        });
+
+        self.shutdown_runners(old_runners).await;
+
+        self.dispatch_reshard(&ReshardEvent::Finished {
+            new_total: shard_total,
+        });
+        Ok(())
+    }
+
+    /// Reshards to the number of shards recommended by Discord, if it differs from the current
+    /// total. See [`Self::reshard`] for more info.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::Http`] if fetching the recommended number of shards failed, or
+    /// [`GatewayError::ReshardTimedOut`] if the new shards were not ready in time.
+    pub async fn reshard_to_recommended(&self, http: &Http, ready_timeout: Duration) -> Result<()> {
+        let shard_total = http.get_bot_gateway().await?.shards;
+
+        if shard_total == self.shard_total.load(Ordering::Relaxed) {
+            debug!("Already running the recommended {} shards", shard_total);
+            return Ok(());
+        }
+
+        self.reshard(shard_total, ready_timeout).await
+    }
+
+    /// Shuts down runners that are no longer in [`Self::runners`], waiting for each to finish.
+    async fn shutdown_runners(&self, runners: HashMap<ShardId, ShardRunnerInfo>) {
+        const TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+
+        let mut shard_shutdown = self.shard_shutdown.lock().await;
+
+        let mut pending = 0;
+        for (shard_id, runner) in runners {
+            let msg = ShardRunnerMessage::Shutdown(shard_id, 1000);
+            if runner.runner_tx.tx.unbounded_send(msg).is_ok() {
+                pending += 1;
+            }
+        }
+
+        for _ in 0..pending {
+            if timeout(TIMEOUT, shard_shutdown.next()).await.is_err() {
+                warn!("Failed to cleanly shutdown old shards, reached timeout");
+                break;
+            }
+        }
+    }
+
+    /// Whether runners of the given shard set should dispatch events.
+    pub(super) fn is_dispatching(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+    }
+
+    /// The shard set that is currently dispatching events.
+    pub(super) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Queues the start of a shard of the staged shard set, if it is still being brought up.
+    pub(super) async fn boot_staged(&self, generation: u64, shard_id: ShardId, shard_total: u32) {
+        if self.staged.lock().await.as_ref().map(|s| s.generation) != Some(generation) {
+            return;
+        }
+
+        let msg = ShardQueuerMessage::StartStaged(shard_id, ShardId(shard_total));
+        drop(self.shard_queuer.unbounded_send(msg));
+    }
+
+    fn dispatch_reshard(&self, event: &ReshardEvent) {
+        for event_handler in self.event_handlers.iter().map(Arc::clone) {
+            let event = event.clone();
+            spawn_named("dispatch::event_handler::reshard", async move {
+                event_handler.reshard(event).await;
+            });
+        }
+    }
+
     /// Restarts a shard runner.
     ///
     /// This sends a shutdown signal to a shard's associated [`ShardRunner`], and then queues a
@@ -299,6 +549,10 @@ impl ShardManager {
     ///
     /// If you only need to shutdown a select number of shards, prefer looping over the
     /// [`Self::shutdown`] method.
+    ///
+    /// If a [`SessionStore`] was given in the [`ShardManagerOptions`], the shards are closed
+    /// without invalidating their sessions, which are saved to the store so they can be resumed
+    /// the next time the shards are started.
     #[instrument(skip(self))]
     pub async fn shutdown_all(&self) {
         let keys = {
@@ -313,8 +567,12 @@ impl ShardManager {
 
         info!("Shutting down all shards");
 
+        // Closing with 1000 invalidates the session, so a non-normal code is used to keep it
+        // resumable.
+        let code = if self.persist_sessions { 4000 } else { 1000 };
+
         for shard_id in keys {
-            self.shutdown(shard_id, 1000).await;
+            self.shutdown(shard_id, code).await;
         }
 
         drop(self.shard_queuer.unbounded_send(ShardQueuerMessage::Shutdown));
@@ -369,6 +627,31 @@ impl ShardManager {
             runner.stage = stage;
         }
     }
+
+    /// Updates the latency and stage of a runner of the given shard set.
+    pub(super) async fn update_runner(
+        &self,
+        generation: u64,
+        id: ShardId,
+        latency: Option<Duration>,
+        stage: ConnectionStage,
+    ) {
+        if self.is_dispatching(generation) {
+            self.update_shard_latency_and_stage(id, latency, stage).await;
+            return;
+        }
+
+        let mut staged = self.staged.lock().await;
+        let runner = staged
+            .as_mut()
+            .filter(|s| s.generation == generation)
+            .and_then(|s| s.runners.get_mut(&id));
+
+        if let Some(runner) = runner {
+            runner.latency = latency;
+            runner.stage = stage;
+        }
+    }
 }
 
 impl Drop for ShardManager {
@@ -400,4 +683,302 @@ pub struct ShardManagerOptions {
     pub http: Arc<Http>,
     pub intents: GatewayIntents,
     pub presence: Option<PresenceData>,
+    /// The transport compression used by every shard's gateway connection.
+    pub compression: TransportCompression,
+    /// The payload encoding used by every shard's gateway connection.
+    pub encoding: GatewayEncoding,
+    /// The transport every shard opens its gateway connections with. If `None`, shards connect
+    /// over a [`WebSocketTransport`].
+    pub transport: Option<Arc<dyn GatewayTransport>>,
+    /// Where the payloads received by shards are recorded. If `None`, nothing is recorded.
+    pub recorder: Option<Arc<GatewayRecorder>>,
+    /// Where shard sessions are saved by [`ShardManager::shutdown_all`], and resumed from when
+    /// shards are started. If `None`, shards always identify anew.
+    pub session_store: Option<Arc<dyn SessionStore>>,
+    /// Decides when shards may IDENTIFY. If `None`, shards are started 5 seconds apart.
+    pub identify_limiter: Option<Arc<dyn IdentifyLimiter>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::sync::Mutex as StdMutex;
+    use std::task::{Context as TaskContext, Poll};
+
+    use async_trait::async_trait;
+    use futures::{Sink, Stream};
+    use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+    use url::Url;
+
+    use super::*;
+    use crate::client::Context;
+    use crate::gateway::{GatewayConnection, InMemorySessionStore, ShardSession};
+    use crate::json::{from_str, Value};
+    use crate::model::event::TypingStartEvent;
+    use crate::model::gateway::ShardInfo;
+
+    type Frames = Sender<StdResult<Message, WsError>>;
+
+    /// A gateway answering every IDENTIFY with a READY, keeping the connections to send events.
+    /// Every RESUME is rejected, as though the session had expired.
+    #[derive(Default)]
+    struct FakeGateway {
+        connections: Arc<StdMutex<Vec<(ShardInfo, Frames)>>>,
+        resumes: Arc<StdMutex<Vec<ShardInfo>>>,
+    }
+
+    impl FakeGateway {
+        /// Sends a typing event from a user with the shard total as ID through every connection
+        /// still open.
+        fn send_typing(&self) {
+            for (shard, tx) in &*self.connections.lock().unwrap() {
+                let typing = format!(
+                    r#"{{"op":0,"s":2,"t":"TYPING_START","d":{{"channel_id":"1","user_id":"{}","timestamp":1700000000}}}}"#,
+                    shard.total
+                );
+                drop(tx.unbounded_send(Ok(Message::Text(typing))));
+            }
+        }
+    }
+
+    #[async_trait]
+    impl GatewayTransport for FakeGateway {
+        async fn connect(&self, _: Url) -> Result<Box<dyn GatewayConnection>> {
+            let (tx, rx) = mpsc::unbounded();
+            let hello = r#"{"op":10,"d":{"heartbeat_interval":41250}}"#;
+            drop(tx.unbounded_send(Ok(Message::Text(hello.into()))));
+
+            Ok(Box::new(FakeConnection {
+                rx,
+                tx,
+                connections: Arc::clone(&self.connections),
+                resumes: Arc::clone(&self.resumes),
+            }))
+        }
+    }
+
+    struct FakeConnection {
+        rx: Receiver<StdResult<Message, WsError>>,
+        tx: Frames,
+        connections: Arc<StdMutex<Vec<(ShardInfo, Frames)>>>,
+        resumes: Arc<StdMutex<Vec<ShardInfo>>>,
+    }
+
+    impl Stream for FakeConnection {
+        type Item = StdResult<Message, WsError>;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            cx: &mut TaskContext<'_>,
+        ) -> Poll<Option<Self::Item>> {
+            self.rx.poll_next_unpin(cx)
+        }
+    }
+
+    impl Sink<Message> for FakeConnection {
+        type Error = WsError;
+
+        fn poll_ready(
+            self: Pin<&mut Self>,
+            _: &mut TaskContext<'_>,
+        ) -> Poll<StdResult<(), WsError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: Message) -> StdResult<(), WsError> {
+            let text = match item {
+                Message::Text(text) => text,
+                Message::Close(frame) => {
+                    return self
+                        .tx
+                        .unbounded_send(Ok(Message::Close(frame)))
+                        .map_err(|_| WsError::ConnectionClosed);
+                },
+                _ => return Ok(()),
+            };
+            let payload: Value = from_str(text).unwrap();
+            if payload["op"] == 6 {
+                let session_id = payload["d"]["session_id"].as_str().unwrap();
+                let (id, total) = session_id.split_once('/').unwrap();
+                let shard = ShardInfo::new(ShardId(id.parse().unwrap()), total.parse().unwrap());
+                self.resumes.lock().unwrap().push(shard);
+
+                let invalid_session = r#"{"op":9,"d":false}"#;
+                self.tx.unbounded_send(Ok(Message::Text(invalid_session.into()))).unwrap();
+                return Ok(());
+            }
+            if payload["op"] != 2 {
+                return Ok(());
+            }
+
+            let shard = &payload["d"]["shard"];
+            let ready = format!(
+                r#"{{"op":0,"s":1,"t":"READY","d":{{"v":10,"user":{{"id":"1","username":"bot","discriminator":"0000","avatar":null,"bot":true}},"guilds":[],"session_id":"session","resume_gateway_url":"wss://gateway.example","shard":{shard},"application":{{"id":"1","flags":0}}}}}}"#
+            );
+            self.tx.unbounded_send(Ok(Message::Text(ready))).unwrap();
+
+            let shard = ShardInfo::new(
+                ShardId(shard[0].as_u64().unwrap() as u32),
+                shard[1].as_u64().unwrap() as u32,
+            );
+            self.connections.lock().unwrap().push((shard, self.tx.clone()));
+            Ok(())
+        }
+
+        fn poll_flush(
+            self: Pin<&mut Self>,
+            _: &mut TaskContext<'_>,
+        ) -> Poll<StdResult<(), WsError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(
+            self: Pin<&mut Self>,
+            _: &mut TaskContext<'_>,
+        ) -> Poll<StdResult<(), WsError>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Lets every shard IDENTIFY at once, recording which did.
+    #[derive(Default)]
+    struct Unlimited {
+        acquired: StdMutex<Vec<ShardInfo>>,
+    }
+
+    #[async_trait]
+    impl IdentifyLimiter for Unlimited {
+        async fn acquire(&self, shard: ShardInfo) -> Result<()> {
+            self.acquired.lock().unwrap().push(shard);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Handler {
+        reshard_events: StdMutex<Vec<ReshardEvent>>,
+        typing_users: StdMutex<Vec<u64>>,
+    }
+
+    #[async_trait]
+    impl EventHandler for Handler {
+        async fn typing_start(&self, _: Context, event: TypingStartEvent) {
+            self.typing_users.lock().unwrap().push(event.user_id.get());
+        }
+
+        async fn reshard(&self, event: ReshardEvent) {
+            self.reshard_events.lock().unwrap().push(event);
+        }
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        timeout(Duration::from_secs(10), async {
+            while !condition() {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reshard_hands_dispatch_over_to_new_shards() {
+        let gateway = Arc::new(FakeGateway::default());
+        let handler = Arc::new(Handler::default());
+        let (manager, _rx) = ShardManager::new(ShardManagerOptions {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            event_handlers: vec![Arc::clone(&handler) as Arc<dyn EventHandler>],
+            raw_event_handlers: vec![],
+            #[cfg(feature = "framework")]
+            framework: Arc::new(OnceLock::new()),
+            shard_index: 0,
+            shard_init: 1,
+            shard_total: 1,
+            #[cfg(feature = "voice")]
+            voice_manager: None,
+            ws_url: Arc::new(Mutex::new("wss://gateway.example".into())),
+            #[cfg(feature = "cache")]
+            cache: Arc::new(Cache::new()),
+            http: Arc::new(Http::new("")),
+            intents: GatewayIntents::non_privileged(),
+            presence: None,
+            compression: TransportCompression::None,
+            encoding: GatewayEncoding::Json,
+            transport: Some(Arc::clone(&gateway) as Arc<dyn GatewayTransport>),
+            recorder: None,
+            session_store: None,
+            identify_limiter: Some(Arc::new(Unlimited::default())),
+        });
+        manager.initialize().unwrap();
+        wait_until(|| gateway.connections.lock().unwrap().len() == 1).await;
+
+        manager.reshard(2, Duration::from_secs(5)).await.unwrap();
+
+        let mut shards = manager.shards_instantiated().await;
+        shards.sort_unstable();
+        assert_eq!(shards, [ShardId(0), ShardId(1)]);
+        wait_until(|| {
+            let connections = gateway.connections.lock().unwrap();
+            connections.iter().all(|(shard, tx)| shard.total == 2 || tx.is_closed())
+        })
+        .await;
+
+        // Only the new shards dispatch events from now on.
+        gateway.send_typing();
+        wait_until(|| handler.typing_users.lock().unwrap().len() == 2).await;
+        assert_eq!(*handler.typing_users.lock().unwrap(), [2, 2]);
+
+        wait_until(|| handler.reshard_events.lock().unwrap().len() == 5).await;
+        let events = handler.reshard_events.lock().unwrap();
+        let ready = events.iter().filter(|e| matches!(e, ReshardEvent::ShardReady { .. }));
+        assert_eq!(ready.count(), 2);
+        assert!(events.iter().any(|e| matches!(e, ReshardEvent::Switched {
+            new_total: 2
+        })));
+        assert!(events.iter().any(|e| matches!(e, ReshardEvent::Finished {
+            new_total: 2
+        })));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rejected_session_identifies_through_queuer() {
+        let gateway = Arc::new(FakeGateway::default());
+        let limiter = Arc::new(Unlimited::default());
+        let store = Arc::new(InMemorySessionStore::new());
+        let shard = ShardInfo::new(ShardId(0), 1);
+        store.save(ShardSession::new(shard, "0/1".into(), 5, None)).await.unwrap();
+
+        let (manager, _rx) = ShardManager::new(ShardManagerOptions {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            event_handlers: vec![],
+            raw_event_handlers: vec![],
+            #[cfg(feature = "framework")]
+            framework: Arc::new(OnceLock::new()),
+            shard_index: 0,
+            shard_init: 1,
+            shard_total: 1,
+            #[cfg(feature = "voice")]
+            voice_manager: None,
+            ws_url: Arc::new(Mutex::new("wss://gateway.example".into())),
+            #[cfg(feature = "cache")]
+            cache: Arc::new(Cache::new()),
+            http: Arc::new(Http::new("")),
+            intents: GatewayIntents::non_privileged(),
+            presence: None,
+            compression: TransportCompression::None,
+            encoding: GatewayEncoding::Json,
+            transport: Some(Arc::clone(&gateway) as Arc<dyn GatewayTransport>),
+            recorder: None,
+            session_store: Some(Arc::clone(&store) as Arc<dyn SessionStore>),
+            identify_limiter: Some(Arc::clone(&limiter) as Arc<dyn IdentifyLimiter>),
+        });
+        manager.initialize().unwrap();
+
+        // The saved session is resumed without asking the limiter, and once it is rejected the
+        // shard is started anew by the queuer, which does.
+        wait_until(|| gateway.connections.lock().unwrap().len() == 1).await;
+        assert_eq!(gateway.resumes.lock().unwrap().len(), 1);
+        assert_eq!(limiter.acquired.lock().unwrap().len(), 1);
+        assert!(store.load(ShardId(0)).await.unwrap().is_none());
+    }
 }
@@ -1,13 +1,15 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 #[cfg(feature = "framework")]
 use std::sync::OnceLock;
 
+use async_trait::async_trait;
 use futures::channel::mpsc::UnboundedReceiver as Receiver;
+use futures::future::join_all;
 use futures::StreamExt;
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::{sleep, timeout, Duration, Instant};
-use tracing::{debug, info, instrument, warn};
+use tracing::{debug, error, info, instrument, warn};
 use typemap_rev::TypeMap;
 
 #[cfg(feature = "voice")]
@@ -26,13 +28,41 @@ use crate::cache::Cache;
 use crate::client::{EventHandler, RawEventHandler};
 #[cfg(feature = "framework")]
 use crate::framework::Framework;
-use crate::gateway::{ConnectionStage, PresenceData, Shard, ShardRunnerMessage};
+use crate::gateway::replay::GatewayRecorder;
+use crate::gateway::{
+    ConnectionStage,
+    GatewayEncoding,
+    GatewayError,
+    GatewayTransport,
+    PresenceData,
+    SessionStore,
+    Shard,
+    ShardOptions,
+    ShardRunnerMessage,
+    ShardSession,
+    TransportCompression,
+};
 use crate::http::Http;
 use crate::internal::prelude::*;
 use crate::internal::tokio::spawn_named;
-use crate::model::gateway::{GatewayIntents, ShardInfo};
+use crate::model::gateway::{GatewayIntents, SessionStartLimit, ShardInfo};
 
-const WAIT_BETWEEN_BOOTS_IN_SECONDS: u64 = 5;
+pub(crate) const WAIT_BETWEEN_BOOTS_IN_SECONDS: u64 = 5;
+
+/// Decides when the [`ShardQueuer`] may start a shard that needs to IDENTIFY.
+///
+/// By default, the queuer waits 5 seconds between shard starts on its own. A limiter replaces that
+/// wait, for example to share the IDENTIFY ratelimit between several processes.
+#[async_trait]
+pub trait IdentifyLimiter: Send + Sync {
+    /// Waits until the given shard may IDENTIFY.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if permission could not be obtained, in which case the shard start is
+    /// queued again.
+    async fn acquire(&self, shard: ShardInfo) -> Result<()>;
+}
 
 /// The shard queuer is a simple loop that runs indefinitely to manage the startup of shards.
 ///
@@ -55,9 +85,17 @@ pub struct ShardQueuer {
     #[cfg(feature = "framework")]
     pub framework: Arc<OnceLock<Arc<dyn Framework>>>,
     /// The instant that a shard was last started.
+    pub last_start: Option<Instant>,
+    /// The instant that a shard was last started in each identify bucket, where the bucket of a
+    /// shard is its ID modulo [`SessionStartLimit::max_concurrency`].
     ///
     /// This is used to determine how long to wait between shard IDENTIFYs.
-    pub last_start: Option<Instant>,
+    pub bucket_starts: HashMap<u64, Instant>,
+    /// The session start limit, fetched when the first shard needs to IDENTIFY and counted down
+    /// with every IDENTIFY since.
+    pub session_start_limit: Option<SessionStartLimit>,
+    /// When [`Self::session_start_limit`] resets, after which it is fetched again.
+    pub session_start_limit_reset: Option<Instant>,
     /// A copy of the [`ShardManager`] to communicate with it.
     pub manager: Arc<ShardManager>,
     /// The shards that are queued for booting.
@@ -78,6 +116,19 @@ pub struct ShardQueuer {
     pub http: Arc<Http>,
     pub intents: GatewayIntents,
     pub presence: Option<PresenceData>,
+    /// The transport compression to start shards with.
+    pub compression: TransportCompression,
+    /// The payload encoding to start shards with.
+    pub encoding: GatewayEncoding,
+    /// The transport shards open their gateway connections with.
+    pub transport: Arc<dyn GatewayTransport>,
+    /// Where the payloads received by shards are recorded, if anywhere.
+    pub recorder: Option<Arc<GatewayRecorder>>,
+    /// Where shard sessions are saved on shutdown, and loaded from to resume shards on start.
+    pub session_store: Option<Arc<dyn SessionStore>>,
+    /// Decides when shards may IDENTIFY, instead of [`Self::bucket_starts`] and
+    /// [`Self::session_start_limit`].
+    pub identify_limiter: Option<Arc<dyn IdentifyLimiter>>,
 }
 
 impl ShardQueuer {
@@ -86,12 +137,15 @@ impl ShardQueuer {
     /// This will loop over the internal [`Self::rx`] for [`ShardQueuerMessage`]s, blocking for
     /// messages on what to do.
     ///
-    /// If a [`ShardQueuerMessage::Start`] is received, this will:
+    /// If a [`ShardQueuerMessage::Start`] is received, the shards waiting to be started along with
+    /// it are started in rounds of one shard per identify bucket. For each round, this will:
     ///
-    /// 1. Check how much time has passed since the last shard was started
-    /// 2. If the amount of time is less than the ratelimit, it will sleep until that time has
+    /// 1. Check that the session start limit allows another IDENTIFY, returning an error from the
+    ///    [`ShardManager`] otherwise
+    /// 2. Check how much time has passed since the last shard of each identify bucket was started
+    /// 3. If the amount of time is less than the ratelimit, it will sleep until that time has
     ///    passed
-    /// 3. Start the shard by ID
+    /// 4. Start the shards of the round at once
     ///
     /// If a [`ShardQueuerMessage::Shutdown`] is received, this will return and the loop will be
     /// over.
@@ -104,8 +158,16 @@ impl ShardQueuer {
         // queue.
         const TIMEOUT: Duration = Duration::from_secs(WAIT_BETWEEN_BOOTS_IN_SECONDS);
 
+        // A message received while taking the shards to start at once, to be handled next.
+        let mut next = None;
+
         loop {
-            match timeout(TIMEOUT, self.rx.next()).await {
+            let message = match next.take() {
+                Some(message) => Ok(Some(message)),
+                None => timeout(TIMEOUT, self.rx.next()).await,
+            };
+
+            match message {
                 Ok(Some(ShardQueuerMessage::Shutdown)) => {
                     debug!("[Shard Queuer] Received to shutdown.");
                     self.shutdown_runners().await;
@@ -118,12 +180,32 @@ impl ShardQueuer {
                 },
                 Ok(Some(ShardQueuerMessage::Start(id, total))) => {
                     debug!("[Shard Queuer] Received to start shard {} of {}.", id.0, total.0);
-                    self.checked_start(id, total.0).await;
+
+                    let mut shards = vec![ShardInfo::new(id, total.0)];
+                    while let Ok(message) = self.rx.try_recv() {
+                        if let ShardQueuerMessage::Start(id, total) = message {
+                            debug!(
+                                "[Shard Queuer] Received to start shard {} of {}.",
+                                id.0, total.0
+                            );
+                            shards.push(ShardInfo::new(id, total.0));
+                        } else {
+                            next = Some(message);
+                            break;
+                        }
+                    }
+
+                    self.checked_start(shards).await;
+                },
+                Ok(Some(ShardQueuerMessage::StartStaged(id, total))) => {
+                    debug!("[Shard Queuer] Received to stage shard {} of {}.", id.0, total.0);
+                    self.checked_start_staged(id, total.0).await;
                 },
                 Ok(None) => break,
                 Err(_) => {
-                    if let Some(shard) = self.queue.pop_front() {
-                        self.checked_start(shard.id, shard.total).await;
+                    if !self.queue.is_empty() {
+                        let shards = self.queue.drain(..).collect();
+                        self.checked_start(shards).await;
                     }
                 },
             }
@@ -131,10 +213,10 @@ impl ShardQueuer {
     }
 
     #[instrument(skip(self))]
-    async fn check_last_start(&mut self) {
-        let Some(instant) = self.last_start else { return };
+    async fn check_last_start(&mut self, id: ShardId) {
+        let Some(&instant) = self.bucket_starts.get(&self.bucket(id)) else { return };
 
-        // We must wait 5 seconds between IDENTIFYs to avoid session invalidations.
+        // We must wait 5 seconds between IDENTIFYs in a bucket to avoid session invalidations.
         let duration = Duration::from_secs(WAIT_BETWEEN_BOOTS_IN_SECONDS);
         let elapsed = instant.elapsed();
 
@@ -147,38 +229,226 @@ impl ShardQueuer {
         sleep(to_sleep).await;
     }
 
+    /// Starts shards in rounds, each starting at most one shard per identify bucket at once.
     #[instrument(skip(self))]
-    async fn checked_start(&mut self, id: ShardId, total: u32) {
-        debug!("[Shard Queuer] Checked start for shard {} out of {}", id, total);
-        self.check_last_start().await;
+    async fn checked_start(&mut self, mut shards: Vec<ShardInfo>) {
+        // The buckets are only known once the session start limit is.
+        if self.identify_limiter.is_none() {
+            self.fetch_session_start_limit().await;
+        }
+
+        while !shards.is_empty() {
+            let mut buckets = HashSet::new();
+            let (round, rest) =
+                shards.into_iter().partition(|shard| buckets.insert(self.bucket(shard.id)));
+            shards = rest;
+
+            let mut starts = Vec::new();
+            for shard in round {
+                let (id, total) = (shard.id, shard.total);
+                debug!("[Shard Queuer] Checked start for shard {} out of {}", id, total);
+
+                // Resuming a saved session does not count towards the IDENTIFY ratelimit.
+                let session = self.load_session(id, total).await;
+                if session.is_none() {
+                    if let Err(why) = self.wait_for_identify(id, total).await {
+                        if let Error::Gateway(why @ GatewayError::SessionStartLimitReached(_)) = why
+                        {
+                            error!("[Shard Queuer] Not starting shard {}: {}", id, why);
+                            self.manager.return_with_value(Err(why)).await;
+                            return;
+                        }
+
+                        warn!("[Shard Queuer] Err acquiring identify for shard {}: {:?}", id, why);
+                        info!("[Shard Queuer] Re-queueing start of shard {}", id);
+
+                        self.queue.push_back(shard);
+                        continue;
+                    }
+                }
 
-        if let Err(why) = self.start(id, total).await {
+                starts.push((shard, session));
+            }
+
+            let this = &*self;
+            let results = join_all(starts.into_iter().map(|(shard, session)| async move {
+                let resuming = session.is_some();
+                (shard, resuming, this.start(shard.id, shard.total, session, None).await)
+            }))
+            .await;
+
+            let now = Instant::now();
+            for (shard, resuming, result) in results {
+                if let Err(why) = result {
+                    warn!("[Shard Queuer] Err starting shard {}: {:?}", shard.id, why);
+                    info!("[Shard Queuer] Re-queueing start of shard {}", shard.id);
+
+                    self.queue.push_back(shard);
+                }
+
+                if !resuming {
+                    self.last_start = Some(now);
+                    self.bucket_starts.insert(self.bucket(shard.id), now);
+                }
+            }
+        }
+    }
+
+    /// Starts a shard of the shard set being brought up by [`ShardManager::reshard`], if it is
+    /// still being brought up.
+    #[instrument(skip(self))]
+    async fn checked_start_staged(&mut self, id: ShardId, total: u32) {
+        let Some(generation) = self.manager.staged.lock().await.as_ref().map(|s| s.generation)
+        else {
+            debug!("[Shard Queuer] Resharding is over, not starting shard {}", id);
+            return;
+        };
+
+        if let Err(why) = self.wait_for_identify(id, total).await {
+            warn!("[Shard Queuer] Err acquiring identify for shard {}: {:?}", id, why);
+            if !matches!(why, Error::Gateway(GatewayError::SessionStartLimitReached(_))) {
+                self.manager.boot_staged(generation, id, total).await;
+            }
+            return;
+        }
+
+        if let Err(why) = self.start(id, total, None, Some(generation)).await {
             warn!("[Shard Queuer] Err starting shard {}: {:?}", id, why);
             info!("[Shard Queuer] Re-queueing start of shard {}", id);
 
-            self.queue.push_back(ShardInfo::new(id, total));
+            self.manager.boot_staged(generation, id, total).await;
+        }
+
+        let now = Instant::now();
+        self.last_start = Some(now);
+        self.bucket_starts.insert(self.bucket(id), now);
+    }
+
+    /// Waits until the shard may IDENTIFY, using the [`Self::identify_limiter`] if there is one.
+    async fn wait_for_identify(&mut self, id: ShardId, total: u32) -> Result<()> {
+        if let Some(limiter) = &self.identify_limiter {
+            return limiter.acquire(ShardInfo::new(id, total)).await;
+        }
+
+        self.check_session_start_limit().await?;
+        self.check_last_start(id).await;
+        Ok(())
+    }
+
+    /// Takes an IDENTIFY out of the [`Self::session_start_limit`], fetching it first if it is
+    /// unknown or has reset.
+    ///
+    /// If the limit can't be fetched, the IDENTIFY is allowed.
+    #[instrument(skip(self))]
+    async fn check_session_start_limit(&mut self) -> Result<()> {
+        self.fetch_session_start_limit().await;
+
+        let Some(limit) = &mut self.session_start_limit else { return Ok(()) };
+        if limit.remaining == 0 {
+            let reset_after = self
+                .session_start_limit_reset
+                .map_or(Duration::ZERO, |reset| reset.saturating_duration_since(Instant::now()));
+
+            return Err(Error::Gateway(GatewayError::SessionStartLimitReached(reset_after)));
+        }
+
+        limit.remaining -= 1;
+        Ok(())
+    }
+
+    /// Fetches the [`Self::session_start_limit`] if it is unknown or has reset.
+    async fn fetch_session_start_limit(&mut self) {
+        let now = Instant::now();
+        if self.session_start_limit_reset.is_some_and(|reset| reset > now) {
+            return;
+        }
+
+        self.session_start_limit = None;
+
+        match self.http.get_bot_gateway().await {
+            Ok(gateway) => {
+                let limit = gateway.session_start_limit;
+                debug!("[Shard Queuer] Fetched session start limit: {:?}", limit);
+
+                self.session_start_limit_reset =
+                    Some(now + Duration::from_millis(limit.reset_after));
+                self.session_start_limit = Some(limit);
+            },
+            Err(why) => warn!("[Shard Queuer] Err fetching session start limit: {:?}", why),
+        }
+    }
+
+    /// Returns the identify bucket of a shard.
+    fn bucket(&self, id: ShardId) -> u64 {
+        let max_concurrency = self.session_start_limit.as_ref().map_or(1, |l| l.max_concurrency);
+        u64::from(id.0) % max_concurrency.max(1)
+    }
+
+    /// Takes the saved session of a shard out of the [`Self::session_store`], if any.
+    ///
+    /// The session is removed from the store so that a failed resume is not retried, and is
+    /// discarded if it was saved with a different shard total.
+    #[instrument(skip(self))]
+    async fn load_session(&self, id: ShardId, total: u32) -> Option<ShardSession> {
+        let store = self.session_store.as_ref()?;
+
+        let session = match store.load(id).await {
+            Ok(session) => session?,
+            Err(why) => {
+                warn!("[Shard Queuer] Err loading session of shard {}: {:?}", id, why);
+                return None;
+            },
+        };
+
+        if let Err(why) = store.remove(id).await {
+            warn!("[Shard Queuer] Err removing session of shard {}: {:?}", id, why);
+        }
+
+        let mut session = (session.shard.total == total).then_some(session)?;
+
+        // Resume from where a restored cache left off, so that it misses none of the events.
+        #[cfg(feature = "cache")]
+        if let Some(seq) = self.cache.shard_seq(id) {
+            session.seq = session.seq.min(seq);
         }
 
-        self.last_start = Some(Instant::now());
+        Some(session)
     }
 
     #[instrument(skip(self))]
-    async fn start(&mut self, id: ShardId, total: u32) -> Result<()> {
+    async fn start(
+        &self,
+        id: ShardId,
+        total: u32,
+        session: Option<ShardSession>,
+        staged_generation: Option<u64>,
+    ) -> Result<()> {
         let shard_info = ShardInfo::new(id, total);
 
-        let mut shard = Shard::new(
-            Arc::clone(&self.ws_url),
-            self.http.token(),
-            shard_info,
-            self.intents,
-            self.presence.clone(),
-        )
-        .await?;
+        let ws_url = Arc::clone(&self.ws_url);
+        let token = self.http.token();
+        let presence = self.presence.clone();
+        let options = ShardOptions::new()
+            .compression(self.compression)
+            .encoding(self.encoding)
+            .transport(Arc::clone(&self.transport));
+        let mut shard = if let Some(session) = session {
+            info!("[Shard Queuer] Resuming saved session of shard {}", id);
+
+            Shard::from_session(ws_url, token, shard_info, self.intents, presence, options, session)
+                .await?
+        } else {
+            Shard::with_options(ws_url, token, shard_info, self.intents, presence, options).await?
+        };
+
+        if let Some(recorder) = &self.recorder {
+            shard.set_recorder(Arc::clone(recorder));
+        }
 
         let cloned_http = Arc::clone(&self.http);
         shard.set_application_id_callback(move |id| cloned_http.set_application_id(id));
 
-        let mut runner = ShardRunner::new(ShardRunnerOptions {
+        let runner = ShardRunner::new(ShardRunnerOptions {
             data: Arc::clone(&self.data),
             event_handlers: self.event_handlers.clone(),
             raw_event_handlers: self.raw_event_handlers.clone(),
@@ -187,6 +457,8 @@ impl ShardQueuer {
             manager: Arc::clone(&self.manager),
             #[cfg(feature = "voice")]
             voice_manager: self.voice_manager.clone(),
+            session_store: self.session_store.clone(),
+            generation: staged_generation.unwrap_or_else(|| self.manager.generation()),
             shard,
             #[cfg(feature = "cache")]
             cache: Arc::clone(&self.cache),
@@ -199,12 +471,21 @@ impl ShardQueuer {
             stage: ConnectionStage::Disconnected,
         };
 
-        spawn_named("shard_queuer::stop", async move {
-            drop(runner.run().await);
-            debug!("[ShardRunner {:?}] Stopping", runner.shard.shard_info());
-        });
+        let Some(generation) = staged_generation else {
+            spawn_runner(runner);
+            self.runners.lock().await.insert(id, runner_info);
 
-        self.runners.lock().await.insert(id, runner_info);
+            return Ok(());
+        };
+
+        // The staged runner is only kept if resharding wasn't aborted in the meantime.
+        let mut staged = self.manager.staged.lock().await;
+        if let Some(staged) = staged.as_mut().filter(|s| s.generation == generation) {
+            spawn_runner(runner);
+            staged.runners.insert(id, runner_info);
+        } else {
+            debug!("[Shard Queuer] Resharding is over, dropping shard {}", id);
+        }
 
         Ok(())
     }
@@ -250,3 +531,134 @@ impl ShardQueuer {
         }
     }
 }
+
+fn spawn_runner(mut runner: ShardRunner) {
+    spawn_named("shard_queuer::stop", async move {
+        drop(runner.run().await);
+        debug!("[ShardRunner {:?}] Stopping", runner.shard.shard_info());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "framework")]
+    use std::sync::OnceLock;
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use url::Url;
+
+    use super::*;
+    use crate::gateway::replay::ReplayTransport;
+    use crate::gateway::{GatewayConnection, ShardManagerOptions};
+
+    /// How long connecting takes with a [`TimedTransport`].
+    const CONNECT_TIME: Duration = Duration::from_millis(500);
+
+    /// Records when shards connect, connecting slowly enough for concurrent starts to show.
+    #[derive(Default)]
+    struct TimedTransport {
+        connects: StdMutex<Vec<Instant>>,
+    }
+
+    #[async_trait]
+    impl GatewayTransport for TimedTransport {
+        async fn connect(&self, url: Url) -> Result<Box<dyn GatewayConnection>> {
+            self.connects.lock().unwrap().push(Instant::now());
+            sleep(CONNECT_TIME).await;
+            ReplayTransport::new([]).connect(url).await
+        }
+    }
+
+    fn options(transport: &Arc<TimedTransport>) -> ShardManagerOptions {
+        ShardManagerOptions {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            event_handlers: vec![],
+            raw_event_handlers: vec![],
+            #[cfg(feature = "framework")]
+            framework: Arc::new(OnceLock::new()),
+            shard_index: 0,
+            shard_init: 0,
+            shard_total: 4,
+            #[cfg(feature = "voice")]
+            voice_manager: None,
+            ws_url: Arc::new(Mutex::new("wss://gateway.example".into())),
+            #[cfg(feature = "cache")]
+            cache: Arc::new(Cache::new()),
+            http: Arc::new(Http::new("")),
+            intents: GatewayIntents::non_privileged(),
+            presence: None,
+            compression: TransportCompression::None,
+            encoding: GatewayEncoding::Json,
+            transport: Some(Arc::clone(transport) as Arc<dyn GatewayTransport>),
+            recorder: None,
+            session_store: None,
+            identify_limiter: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn starts_identify_buckets_together() {
+        let transport = Arc::new(TimedTransport::default());
+        let opt = options(&transport);
+        let (manager, _rx) = ShardManager::new(options(&transport));
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+
+        let mut queuer = ShardQueuer {
+            data: opt.data,
+            event_handlers: opt.event_handlers,
+            raw_event_handlers: opt.raw_event_handlers,
+            #[cfg(feature = "framework")]
+            framework: opt.framework,
+            last_start: None,
+            bucket_starts: HashMap::new(),
+            session_start_limit: Some(SessionStartLimit {
+                remaining: 1000,
+                reset_after: 60_000,
+                total: 1000,
+                max_concurrency: 2,
+            }),
+            session_start_limit_reset: Some(Instant::now() + Duration::from_secs(60)),
+            manager,
+            queue: VecDeque::new(),
+            runners: Arc::new(Mutex::new(HashMap::new())),
+            rx,
+            #[cfg(feature = "voice")]
+            voice_manager: None,
+            ws_url: opt.ws_url,
+            #[cfg(feature = "cache")]
+            cache: opt.cache,
+            http: opt.http,
+            intents: opt.intents,
+            presence: None,
+            compression: opt.compression,
+            encoding: opt.encoding,
+            transport: Arc::clone(&transport) as Arc<dyn GatewayTransport>,
+            recorder: None,
+            session_store: None,
+            identify_limiter: None,
+        };
+
+        for id in 0..4 {
+            tx.unbounded_send(ShardQueuerMessage::Start(ShardId(id), ShardId(4))).unwrap();
+        }
+        let started = Instant::now();
+        tokio::spawn(async move { queuer.run().await });
+
+        timeout(Duration::from_secs(15), async {
+            while transport.connects.lock().unwrap().len() < 4 {
+                sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Both buckets start at once, then wait out the IDENTIFY ratelimit together from when the
+        // first round was started.
+        let connects = transport.connects.lock().unwrap().clone();
+        let wait = Duration::from_secs(WAIT_BETWEEN_BOOTS_IN_SECONDS);
+        assert_eq!(connects[..2], [started, started]);
+        let next_round = started + CONNECT_TIME + wait;
+        assert_eq!(connects[2..], [next_round, next_round]);
+        tx.unbounded_send(ShardQueuerMessage::Shutdown).unwrap();
+    }
+}
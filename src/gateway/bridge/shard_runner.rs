@@ -21,7 +21,7 @@ use crate::client::dispatch::dispatch_model;
 use crate::client::{Context, EventHandler, RawEventHandler};
 #[cfg(feature = "framework")]
 use crate::framework::Framework;
-use crate::gateway::{GatewayError, ReconnectType, Shard, ShardAction};
+use crate::gateway::{GatewayError, ReconnectType, SessionStore, Shard, ShardAction};
 use crate::http::Http;
 use crate::internal::prelude::*;
 use crate::internal::tokio::spawn_named;
@@ -42,6 +42,8 @@ pub struct ShardRunner {
     pub(crate) shard: Shard,
     #[cfg(feature = "voice")]
     voice_manager: Option<Arc<dyn VoiceGatewayManager + 'static>>,
+    session_store: Option<Arc<dyn SessionStore>>,
+    generation: u64,
     #[cfg(feature = "cache")]
     pub cache: Arc<Cache>,
     pub http: Arc<Http>,
@@ -51,6 +53,7 @@ pub struct ShardRunner {
 
 impl ShardRunner {
     /// Creates a new runner for a Shard.
+    #[must_use]
     pub fn new(opt: ShardRunnerOptions) -> Self {
         let (tx, rx) = mpsc::unbounded();
 
@@ -66,6 +69,8 @@ impl ShardRunner {
             shard: opt.shard,
             #[cfg(feature = "voice")]
             voice_manager: opt.voice_manager,
+            session_store: opt.session_store,
+            generation: opt.generation,
             #[cfg(feature = "cache")]
             cache: opt.cache,
             http: opt.http,
@@ -115,19 +120,23 @@ impl ShardRunner {
             let (event, action, successful) = self.recv_event().await?;
             let post = self.shard.stage();
 
+            let dispatching = self.manager.is_dispatching(self.generation);
+
             if post != pre {
                 self.update_manager().await;
 
-                for event_handler in self.event_handlers.clone() {
-                    let context = self.make_context();
-                    let event = ShardStageUpdateEvent {
-                        new: post,
-                        old: pre,
-                        shard_id: self.shard.shard_info().id,
-                    };
-                    spawn_named("dispatch::event_handler::shard_stage_update", async move {
-                        event_handler.shard_stage_update(context, event).await;
-                    });
+                if dispatching {
+                    for event_handler in self.event_handlers.clone() {
+                        let context = self.make_context();
+                        let event = ShardStageUpdateEvent {
+                            new: post,
+                            old: pre,
+                            shard_id: self.shard.shard_info().id,
+                        };
+                        spawn_named("dispatch::event_handler::shard_stage_update", async move {
+                            event_handler.shard_stage_update(context, event).await;
+                        });
+                    }
                 }
             }
 
@@ -162,7 +171,7 @@ impl ShardRunner {
                 None => {},
             }
 
-            if let Some(event) = event {
+            if let Some(event) = event.filter(|_| dispatching) {
                 #[cfg(feature = "collector")]
                 self.collectors.lock().expect("poison").retain_mut(|callback| (callback.0)(&event));
 
@@ -174,6 +183,9 @@ impl ShardRunner {
                     self.event_handlers.clone(),
                     self.raw_event_handlers.clone(),
                 );
+
+                #[cfg(feature = "cache")]
+                self.cache.set_shard_seq(self.shard.shard_info().id, self.shard.seq());
             }
 
             if !successful && !self.shard.stage().is_connecting() {
@@ -247,11 +259,29 @@ impl ShardRunner {
             }
         }
 
+        // Close codes 1000 and 1001 invalidate the session, any other code leaves it resumable.
+        if !matches!(close_code, 1000 | 1001) {
+            self.save_session().await;
+        }
+
         // Inform the manager that shutdown for this shard has finished.
         self.manager.shutdown_finished(id);
         false
     }
 
+    #[instrument(skip(self))]
+    async fn save_session(&self) {
+        let (Some(store), Some(session)) = (&self.session_store, self.shard.session()) else {
+            return;
+        };
+
+        debug!("[ShardRunner {:?}] Saving session", self.shard.shard_info());
+
+        if let Err(why) = store.save(session).await {
+            warn!("[ShardRunner {:?}] Failed to save session: {:?}", self.shard.shard_info(), why);
+        }
+    }
+
     fn make_context(&self) -> Context {
         Context::new(
             Arc::clone(&self.data),
@@ -428,7 +458,7 @@ impl ShardRunner {
         }
 
         #[cfg(feature = "voice")]
-        {
+        if self.manager.is_dispatching(self.generation) {
             if let Ok(GatewayEvent::Dispatch(_, ref event)) = event {
                 self.handle_voice_event(event).await;
             }
@@ -449,6 +479,15 @@ impl ShardRunner {
         self.update_manager().await;
 
         let shard_id = self.shard.shard_info().id;
+        if !self.manager.is_dispatching(self.generation) {
+            // A shard of a set being brought up by resharding is started again within that set,
+            // and one of a set being shut down is not restarted at all.
+            let total = self.shard.shard_info().total;
+            self.manager.boot_staged(self.generation, shard_id, total).await;
+
+            return Ok(());
+        }
+
         self.manager.restart_shard(shard_id).await;
 
         #[cfg(feature = "voice")]
@@ -462,7 +501,8 @@ impl ShardRunner {
     #[instrument(skip(self))]
     async fn update_manager(&self) {
         self.manager
-            .update_shard_latency_and_stage(
+            .update_runner(
+                self.generation,
                 self.shard.shard_info().id,
                 self.shard.latency(),
                 self.shard.stage(),
@@ -482,6 +522,11 @@ pub struct ShardRunnerOptions {
     pub shard: Shard,
     #[cfg(feature = "voice")]
     pub voice_manager: Option<Arc<dyn VoiceGatewayManager>>,
+    /// Where to save the shard's session when it is shut down with a resumable close code.
+    pub session_store: Option<Arc<dyn SessionStore>>,
+    /// The shard set the runner belongs to. Events are only dispatched while the manager's
+    /// current shard set is this one, see [`ShardManager::reshard`].
+    pub generation: u64,
     #[cfg(feature = "cache")]
     pub cache: Arc<Cache>,
     pub http: Arc<Http>,
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration as StdDuration;
+
+use futures::channel::mpsc::{self, UnboundedSender as Sender};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::net::TcpListener;
+use tokio::time::{sleep_until, Instant};
+use tracing::{debug, info, instrument, warn};
+
+use super::{write_messages, CoordinatorMessage, ShardAssignment, ShardStatus, WorkerMessage};
+use crate::gateway::bridge::WAIT_BETWEEN_BOOTS_IN_SECONDS;
+use crate::gateway::ConnectionStage;
+use crate::internal::prelude::*;
+use crate::internal::tokio::spawn_named;
+use crate::json::from_str;
+use crate::model::id::ShardId;
+
+/// Options to be passed to [`ClusterCoordinator::new`].
+#[derive(Clone, Debug)]
+pub struct CoordinatorOptions {
+    /// The total number of shards in the cluster.
+    pub shard_total: u32,
+    /// The number of shards assigned to each worker. The last worker may be assigned fewer.
+    pub shards_per_worker: u32,
+    /// The number of shards that may IDENTIFY at once, as given by the `max_concurrency` of
+    /// [`SessionStartLimit`].
+    ///
+    /// [`SessionStartLimit`]: crate::model::gateway::SessionStartLimit
+    pub max_concurrency: u32,
+    /// How long to wait between two IDENTIFYs in the same bucket. Defaults to 5 seconds.
+    pub identify_interval: StdDuration,
+}
+
+impl CoordinatorOptions {
+    #[must_use]
+    pub fn new(shard_total: u32, shards_per_worker: u32, max_concurrency: u32) -> Self {
+        Self {
+            shard_total,
+            shards_per_worker,
+            max_concurrency,
+            identify_interval: StdDuration::from_secs(WAIT_BETWEEN_BOOTS_IN_SECONDS),
+        }
+    }
+}
+
+/// Information about a shard running on a [`ClusterWorker`], as last reported by the worker.
+///
+/// [`ClusterWorker`]: super::ClusterWorker
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ClusterShardInfo {
+    /// The ID of the worker running the shard.
+    pub worker_id: u32,
+    /// The latency between when a heartbeat was sent and when the acknowledgement was received.
+    pub latency: Option<StdDuration>,
+    /// The current connection stage of the shard.
+    pub stage: ConnectionStage,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_worker_id: u32,
+    /// The worker each shard range is assigned to, if any.
+    ranges: Vec<Option<ShardAssignment>>,
+    /// When the last IDENTIFY of each `max_concurrency` bucket was granted for.
+    buckets: Vec<Option<Instant>>,
+    shards: HashMap<ShardId, ClusterShardInfo>,
+}
+
+/// Hands out shard ranges to [`ClusterWorker`]s and coordinates their IDENTIFYs.
+///
+/// Refer to the [module-level documentation] for an overview.
+///
+/// [`ClusterWorker`]: super::ClusterWorker
+/// [module-level documentation]: super
+#[derive(Debug)]
+pub struct ClusterCoordinator {
+    options: CoordinatorOptions,
+    state: Mutex<State>,
+}
+
+impl ClusterCoordinator {
+    /// Creates a new coordinator. Call [`Self::serve`] to start accepting workers.
+    #[must_use]
+    pub fn new(options: CoordinatorOptions) -> Arc<Self> {
+        let shards_per_worker = options.shards_per_worker.max(1);
+        let range_count = options.shard_total.div_ceil(shards_per_worker);
+        let bucket_count = options.max_concurrency.max(1);
+
+        let state = State {
+            ranges: vec![None; range_count as usize],
+            buckets: vec![None; bucket_count as usize],
+            ..State::default()
+        };
+
+        Arc::new(Self {
+            options,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Accepts workers over a TCP socket until accepting fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if a connection could not be accepted.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            debug!("[Cluster Coordinator] Accepted connection from {}", addr);
+
+            spawn_named("cluster::coordinator::connection", Arc::clone(&self).handle(stream));
+        }
+    }
+
+    /// Accepts workers over a Unix socket until accepting fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if a connection could not be accepted.
+    #[cfg(unix)]
+    pub async fn serve_unix(self: Arc<Self>, listener: tokio::net::UnixListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            debug!("[Cluster Coordinator] Accepted connection");
+
+            spawn_named("cluster::coordinator::connection", Arc::clone(&self).handle(stream));
+        }
+    }
+
+    /// Returns the shard ranges currently assigned to connected workers.
+    pub fn assignments(&self) -> Vec<ShardAssignment> {
+        self.state().ranges.iter().flatten().copied().collect()
+    }
+
+    /// Returns the last reported information of every shard running in the cluster.
+    pub fn shards(&self) -> HashMap<ShardId, ClusterShardInfo> {
+        self.state().shards.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("poisoned coordinator state")
+    }
+
+    #[instrument(skip(self, stream))]
+    async fn handle<S>(self: Arc<Self>, stream: S)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (read, write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+
+        let (tx, rx) = mpsc::unbounded();
+        spawn_named("cluster::coordinator::write", write_messages(write, rx));
+
+        let mut worker_id = None;
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(why) => {
+                    warn!("[Cluster Coordinator] Err reading from worker: {:?}", why);
+                    break;
+                },
+            };
+
+            let message = match from_str(line) {
+                Ok(message) => message,
+                Err(why) => {
+                    warn!("[Cluster Coordinator] Err decoding message: {:?}", why);
+                    continue;
+                },
+            };
+
+            match (message, worker_id) {
+                (WorkerMessage::Hello, None) => {
+                    let Some(assignment) = self.assign() else {
+                        let reason = "no shard range left to assign".to_string();
+                        drop(tx.unbounded_send(CoordinatorMessage::Reject {
+                            reason,
+                        }));
+                        break;
+                    };
+
+                    info!("[Cluster Coordinator] Assigned {:?}", assignment);
+
+                    worker_id = Some(assignment.worker_id);
+                    drop(tx.unbounded_send(CoordinatorMessage::Assign(assignment)));
+                },
+                (
+                    WorkerMessage::RequestIdentify {
+                        shard_id,
+                    },
+                    Some(_),
+                ) => self.grant_identify(shard_id, tx.clone()),
+                (
+                    WorkerMessage::Status {
+                        shards,
+                    },
+                    Some(worker_id),
+                ) => self.update_shards(worker_id, shards),
+                (message, _) => warn!("[Cluster Coordinator] Unexpected message: {:?}", message),
+            }
+        }
+
+        if let Some(worker_id) = worker_id {
+            info!("[Cluster Coordinator] Worker {} disconnected", worker_id);
+            self.release(worker_id);
+        }
+    }
+
+    fn assign(&self) -> Option<ShardAssignment> {
+        let mut state = self.state();
+        let per_worker = self.options.shards_per_worker.max(1);
+
+        let worker_id = state.next_worker_id;
+        let (index, slot) = state.ranges.iter_mut().enumerate().find(|(_, r)| r.is_none())?;
+        let shard_index = index as u32 * per_worker;
+
+        let assignment = ShardAssignment {
+            worker_id,
+            shard_index,
+            shard_init: per_worker.min(self.options.shard_total - shard_index),
+            shard_total: self.options.shard_total,
+        };
+        *slot = Some(assignment);
+        state.next_worker_id += 1;
+
+        Some(assignment)
+    }
+
+    fn release(&self, worker_id: u32) {
+        let mut state = self.state();
+
+        for range in &mut state.ranges {
+            if range.is_some_and(|a| a.worker_id == worker_id) {
+                *range = None;
+            }
+        }
+        state.shards.retain(|_, info| info.worker_id != worker_id);
+    }
+
+    /// Reserves the next IDENTIFY slot of the shard's bucket, and grants it once it is due.
+    fn grant_identify(&self, shard_id: u32, tx: Sender<CoordinatorMessage>) {
+        let due = {
+            let mut state = self.state();
+            let bucket = shard_id as usize % state.buckets.len();
+            let now = Instant::now();
+
+            let due = state.buckets[bucket]
+                .map_or(now, |last| (last + self.options.identify_interval).max(now));
+            state.buckets[bucket] = Some(due);
+            due
+        };
+
+        spawn_named("cluster::coordinator::grant_identify", async move {
+            sleep_until(due).await;
+
+            debug!("[Cluster Coordinator] Granting identify to shard {}", shard_id);
+            drop(tx.unbounded_send(CoordinatorMessage::IdentifyGranted {
+                shard_id,
+            }));
+        });
+    }
+
+    fn update_shards(&self, worker_id: u32, shards: Vec<ShardStatus>) {
+        let mut state = self.state();
+
+        state.shards.retain(|_, info| info.worker_id != worker_id);
+        for status in shards {
+            state.shards.insert(ShardId(status.shard_id), ClusterShardInfo {
+                worker_id,
+                latency: status.latency(),
+                stage: status.stage,
+            });
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Coordination of shards that are spread over several processes.
+//!
+//! A single [`ClusterCoordinator`] hands out shard ranges to [`ClusterWorker`]s, grants them
+//! permission to IDENTIFY so that every `max_concurrency` bucket is respected across the whole
+//! cluster, and gathers the latency and connection stage of every shard.
+//!
+//! Workers talk to the coordinator over a TCP or Unix socket, using newline-delimited JSON
+//! messages. Each worker runs a regular [`ShardManager`] for the range it was assigned.
+//!
+//! # Examples
+//!
+//! Running the coordinator, typically in its own process:
+//!
+//! ```rust,no_run
+//! use serenity::gateway::cluster::{ClusterCoordinator, CoordinatorOptions};
+//! use tokio::net::TcpListener;
+//!
+//! # async fn run() -> serenity::Result<()> {
+//! // 16 shards, 4 per worker, with a `max_concurrency` of 1.
+//! let coordinator = ClusterCoordinator::new(CoordinatorOptions::new(16, 4, 1));
+//! coordinator.serve(TcpListener::bind("127.0.0.1:7878").await?).await
+//! # }
+//! ```
+//!
+//! Running a worker:
+//!
+//! ```rust,no_run
+//! use serenity::gateway::cluster::ClusterWorker;
+//! use serenity::gateway::ShardManagerOptions;
+//!
+//! # async fn run(options: ShardManagerOptions) -> serenity::Result<()> {
+//! let worker = ClusterWorker::connect("127.0.0.1:7878").await?;
+//! let (manager, mut manager_rx) = worker.start(options)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ShardManager`]: super::ShardManager
+
+mod coordinator;
+mod worker;
+
+use std::time::Duration as StdDuration;
+
+use futures::channel::mpsc::UnboundedReceiver as Receiver;
+use futures::StreamExt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::warn;
+
+pub use self::coordinator::{ClusterCoordinator, ClusterShardInfo, CoordinatorOptions};
+pub use self::worker::ClusterWorker;
+use super::ConnectionStage;
+use crate::internal::prelude::*;
+use crate::json::to_vec;
+
+/// The range of shards a [`ClusterWorker`] is responsible for.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ShardAssignment {
+    /// The ID the coordinator knows the worker by.
+    pub worker_id: u32,
+    /// The ID of the first shard of the range.
+    pub shard_index: u32,
+    /// The number of shards in the range.
+    pub shard_init: u32,
+    /// The total number of shards in the cluster.
+    pub shard_total: u32,
+}
+
+/// A message sent by a worker to the coordinator.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "op", content = "d", rename_all = "snake_case")]
+enum WorkerMessage {
+    /// Asks for a shard range. Must be the first message on a connection.
+    Hello,
+    /// Asks for permission to IDENTIFY a shard.
+    RequestIdentify { shard_id: u32 },
+    /// Reports the status of every running shard of the worker.
+    Status { shards: Vec<ShardStatus> },
+}
+
+/// A message sent by the coordinator to a worker.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "op", content = "d", rename_all = "snake_case")]
+enum CoordinatorMessage {
+    /// The shard range given in response to a [`WorkerMessage::Hello`].
+    Assign(ShardAssignment),
+    /// Sent instead of [`Self::Assign`] if the worker can't be given a shard range.
+    Reject { reason: String },
+    /// Permission to IDENTIFY a shard, in response to a [`WorkerMessage::RequestIdentify`].
+    IdentifyGranted { shard_id: u32 },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ShardStatus {
+    shard_id: u32,
+    latency_ms: Option<u64>,
+    stage: ConnectionStage,
+}
+
+impl ShardStatus {
+    fn latency(&self) -> Option<StdDuration> {
+        self.latency_ms.map(StdDuration::from_millis)
+    }
+}
+
+async fn write_message<W, T>(writer: &mut W, message: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: serde::Serialize,
+{
+    let mut bytes = to_vec(message)?;
+    bytes.push(b'\n');
+
+    writer.write_all(&bytes).await?;
+    Ok(())
+}
+
+/// Writes every message received over the channel, then closes the connection.
+async fn write_messages<W, T>(mut write: W, mut rx: Receiver<T>)
+where
+    W: AsyncWrite + Unpin,
+    T: serde::Serialize,
+{
+    while let Some(message) = rx.next().await {
+        if let Err(why) = write_message(&mut write, &message).await {
+            warn!("[Cluster] Err writing message: {:?}", why);
+            break;
+        }
+    }
+
+    drop(write.shutdown().await);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::model::gateway::ShardInfo;
+    use crate::model::id::ShardId;
+
+    async fn coordinator(
+        options: CoordinatorOptions,
+    ) -> (std::sync::Arc<ClusterCoordinator>, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let coordinator = ClusterCoordinator::new(options);
+        let serving = std::sync::Arc::clone(&coordinator);
+        tokio::spawn(async move { serving.serve(listener).await });
+
+        (coordinator, addr)
+    }
+
+    #[tokio::test]
+    async fn assigns_shard_ranges() {
+        let (coordinator, addr) = coordinator(CoordinatorOptions::new(5, 3, 1)).await;
+
+        let first = ClusterWorker::connect(&*addr).await.unwrap();
+        let second = ClusterWorker::connect(&*addr).await.unwrap();
+        assert_eq!((first.assignment().shard_index, first.assignment().shard_init), (0, 3));
+        assert_eq!((second.assignment().shard_index, second.assignment().shard_init), (3, 2));
+        assert_eq!(second.assignment().shard_total, 5);
+
+        assert!(ClusterWorker::connect(&*addr).await.is_err());
+
+        // The range of a worker that goes away is handed out again.
+        drop(first);
+        tokio::time::sleep(StdDuration::from_millis(100)).await;
+        assert_eq!(coordinator.assignments().len(), 1);
+
+        let third = ClusterWorker::connect(&*addr).await.unwrap();
+        assert_eq!(third.assignment().shard_index, 0);
+    }
+
+    #[tokio::test]
+    async fn grants_identify_per_bucket() {
+        let mut options = CoordinatorOptions::new(4, 4, 2);
+        options.identify_interval = StdDuration::from_millis(300);
+        let (_coordinator, addr) = coordinator(options).await;
+
+        let worker = ClusterWorker::connect(&*addr).await.unwrap();
+        let start = Instant::now();
+
+        // Shards 0 and 1 are in different buckets, shard 2 shares a bucket with shard 0.
+        worker.request_identify(ShardInfo::new(ShardId(0), 4)).await.unwrap();
+        worker.request_identify(ShardInfo::new(ShardId(1), 4)).await.unwrap();
+        assert!(start.elapsed() < StdDuration::from_millis(300));
+
+        worker.request_identify(ShardInfo::new(ShardId(2), 4)).await.unwrap();
+        assert!(start.elapsed() >= StdDuration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn gathers_shard_status() {
+        let (coordinator, addr) = coordinator(CoordinatorOptions::new(2, 2, 1)).await;
+
+        let worker = ClusterWorker::connect(&*addr).await.unwrap();
+        worker.send_status(vec![ShardStatus {
+            shard_id: 1,
+            latency_ms: Some(42),
+            stage: ConnectionStage::Connected,
+        }]);
+        tokio::time::sleep(StdDuration::from_millis(100)).await;
+
+        let shards = coordinator.shards();
+        let info = &shards[&ShardId(1)];
+        assert_eq!(info.worker_id, worker.assignment().worker_id);
+        assert_eq!(info.latency, Some(StdDuration::from_millis(42)));
+        assert_eq!(info.stage, ConnectionStage::Connected);
+
+        drop(worker);
+        tokio::time::sleep(StdDuration::from_millis(100)).await;
+        assert!(coordinator.shards().is_empty());
+    }
+}
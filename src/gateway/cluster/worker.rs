@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration as StdDuration;
+
+use async_trait::async_trait;
+use futures::channel::mpsc::{self, UnboundedReceiver as Receiver, UnboundedSender as Sender};
+use futures::channel::oneshot;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tracing::{debug, instrument, warn};
+
+use super::{
+    write_message,
+    write_messages,
+    CoordinatorMessage,
+    ShardAssignment,
+    ShardStatus,
+    WorkerMessage,
+};
+use crate::gateway::{GatewayError, IdentifyLimiter, ShardManager, ShardManagerOptions};
+use crate::internal::prelude::*;
+use crate::internal::tokio::spawn_named;
+use crate::json::from_str;
+use crate::model::gateway::ShardInfo;
+
+/// How often a worker reports the status of its shards to the coordinator.
+const STATUS_INTERVAL: StdDuration = StdDuration::from_secs(5);
+
+/// A started [`ShardManager`], along with the receiver for its return value.
+type ManagerHandle = (Arc<ShardManager>, Receiver<Result<(), GatewayError>>);
+
+/// Senders waiting for an IDENTIFY grant, by shard ID, or `None` once the coordinator is gone.
+type Waiters = Mutex<Option<HashMap<u32, oneshot::Sender<()>>>>;
+
+/// A connection to a [`ClusterCoordinator`], running the shards it was assigned.
+///
+/// The worker is kept connected for as long as it, or a [`ShardManager`] started through
+/// [`Self::start`], is alive. Once it disconnects, its shard range is handed out to the next
+/// worker that connects.
+///
+/// [`ClusterCoordinator`]: super::ClusterCoordinator
+#[derive(Debug)]
+pub struct ClusterWorker {
+    assignment: ShardAssignment,
+    tx: Sender<WorkerMessage>,
+    waiters: Arc<Waiters>,
+}
+
+impl ClusterWorker {
+    /// Connects to a coordinator listening on a TCP socket, and waits to be assigned a shard
+    /// range.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the connection failed, or [`GatewayError::Cluster`] if the
+    /// coordinator has no shard range left to assign.
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Arc<Self>> {
+        Self::handshake(TcpStream::connect(addr).await?).await
+    }
+
+    /// Connects to a coordinator listening on a Unix socket, and waits to be assigned a shard
+    /// range.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the connection failed, or [`GatewayError::Cluster`] if the
+    /// coordinator has no shard range left to assign.
+    #[cfg(unix)]
+    pub async fn connect_unix(path: impl AsRef<Path>) -> Result<Arc<Self>> {
+        Self::handshake(tokio::net::UnixStream::connect(path).await?).await
+    }
+
+    async fn handshake<S>(stream: S) -> Result<Arc<Self>>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+
+        write_message(&mut write, &WorkerMessage::Hello).await?;
+        let assignment = match lines.next_line().await? {
+            Some(line) => match from_str(line)? {
+                CoordinatorMessage::Assign(assignment) => assignment,
+                CoordinatorMessage::Reject {
+                    reason,
+                } => return Err(Error::Gateway(GatewayError::Cluster(reason))),
+                other @ CoordinatorMessage::IdentifyGranted {
+                    ..
+                } => {
+                    let why = format!("unexpected message during handshake: {other:?}");
+                    return Err(Error::Gateway(GatewayError::Cluster(why)));
+                },
+            },
+            None => return Err(disconnected()),
+        };
+
+        debug!("[Cluster Worker] Assigned {:?}", assignment);
+
+        let (tx, rx) = mpsc::unbounded();
+        spawn_named("cluster::worker::write", write_messages(write, rx));
+
+        let waiters: Arc<Waiters> = Arc::new(Mutex::new(Some(HashMap::new())));
+        let read_waiters = Arc::clone(&waiters);
+        spawn_named("cluster::worker::read", async move {
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(why) => {
+                        warn!("[Cluster Worker] Err reading from coordinator: {:?}", why);
+                        break;
+                    },
+                };
+
+                match from_str(line) {
+                    Ok(CoordinatorMessage::IdentifyGranted {
+                        shard_id,
+                    }) => {
+                        let mut waiters = read_waiters.lock().expect("poisoned waiters");
+                        if let Some(tx) = waiters.as_mut().and_then(|w| w.remove(&shard_id)) {
+                            tx.send(()).ok();
+                        }
+                    },
+                    Ok(other) => warn!("[Cluster Worker] Unexpected message: {:?}", other),
+                    Err(why) => warn!("[Cluster Worker] Err decoding message: {:?}", why),
+                }
+            }
+
+            // Dropping the senders wakes up anyone still waiting for a grant.
+            debug!("[Cluster Worker] Disconnected from coordinator");
+            *read_waiters.lock().expect("poisoned waiters") = None;
+        });
+
+        Ok(Arc::new(Self {
+            assignment,
+            tx,
+            waiters,
+        }))
+    }
+
+    /// Returns the shard range the coordinator assigned to this worker.
+    #[must_use]
+    pub fn assignment(&self) -> ShardAssignment {
+        self.assignment
+    }
+
+    /// Starts a [`ShardManager`] for the shards assigned to this worker.
+    ///
+    /// The shard range of the given options is replaced with the assigned one, and every shard
+    /// waits for the coordinator's permission before it IDENTIFYs. The status of the shards is
+    /// reported to the coordinator until the manager is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`ShardManager`] failed to initialize.
+    pub fn start(self: &Arc<Self>, mut opt: ShardManagerOptions) -> Result<ManagerHandle> {
+        opt.shard_index = self.assignment.shard_index;
+        opt.shard_init = self.assignment.shard_init;
+        opt.shard_total = self.assignment.shard_total;
+        opt.identify_limiter = Some(Arc::clone(self) as Arc<dyn IdentifyLimiter>);
+
+        let (manager, manager_rx) = ShardManager::new(opt);
+        manager.initialize()?;
+
+        let worker = Arc::clone(self);
+        let manager_ref = Arc::downgrade(&manager);
+        spawn_named("cluster::worker::report", async move {
+            let mut interval = tokio::time::interval(STATUS_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let Some(manager) = manager_ref.upgrade() else { break };
+                let shards = manager
+                    .runners
+                    .lock()
+                    .await
+                    .iter()
+                    .map(|(id, info)| ShardStatus {
+                        shard_id: id.0,
+                        latency_ms: info.latency.and_then(|l| u64::try_from(l.as_millis()).ok()),
+                        stage: info.stage,
+                    })
+                    .collect();
+
+                if !worker.send_status(shards) {
+                    break;
+                }
+            }
+        });
+
+        Ok((manager, manager_rx))
+    }
+
+    /// Waits for the coordinator's permission to IDENTIFY the given shard.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GatewayError::Cluster`] if the connection to the coordinator was lost.
+    #[instrument(skip(self))]
+    pub async fn request_identify(&self, shard: ShardInfo) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        match self.waiters.lock().expect("poisoned waiters").as_mut() {
+            Some(waiters) => waiters.insert(shard.id.0, tx),
+            None => return Err(disconnected()),
+        };
+
+        self.tx
+            .unbounded_send(WorkerMessage::RequestIdentify {
+                shard_id: shard.id.0,
+            })
+            .map_err(|_| disconnected())?;
+
+        rx.await.map_err(|_| disconnected())
+    }
+
+    /// Sends the status of the worker's shards, returning whether the connection is still open.
+    pub(super) fn send_status(&self, shards: Vec<ShardStatus>) -> bool {
+        self.tx
+            .unbounded_send(WorkerMessage::Status {
+                shards,
+            })
+            .is_ok()
+    }
+}
+
+#[async_trait]
+impl IdentifyLimiter for ClusterWorker {
+    async fn acquire(&self, shard: ShardInfo) -> Result<()> {
+        self.request_identify(shard).await
+    }
+}
+
+fn disconnected() -> Error {
+    Error::Gateway(GatewayError::Cluster("disconnected from the coordinator".into()))
+}
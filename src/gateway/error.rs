@@ -1,5 +1,6 @@
 use std::error::Error as StdError;
 use std::fmt;
+use std::time::Duration as StdDuration;
 
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
@@ -50,6 +51,19 @@ pub enum Error {
     /// If an connection has been established but privileged gateway intents were provided without
     /// enabling them prior.
     DisallowedGatewayIntents,
+    /// No more shards may IDENTIFY until the session start limit resets, after the given
+    /// duration.
+    SessionStartLimitReached(StdDuration),
+    /// The new shards of a [`ShardManager::reshard`] did not become ready in time.
+    ///
+    /// [`ShardManager::reshard`]: super::ShardManager::reshard
+    ReshardTimedOut,
+    /// A payload could not be encoded or decoded as ETF.
+    #[cfg(feature = "etf")]
+    Etf(String),
+    /// The cluster coordinator rejected a worker or went away, with the reason why.
+    #[cfg(feature = "cluster")]
+    Cluster(String),
 }
 
 impl fmt::Display for Error {
@@ -70,6 +84,16 @@ impl fmt::Display for Error {
             Self::DisallowedGatewayIntents => {
                 f.write_str("Disallowed gateway intents were provided")
             },
+            Self::SessionStartLimitReached(reset_after) => {
+                write!(f, "Session start limit reached, resets in {reset_after:?}")
+            },
+            Self::ReshardTimedOut => {
+                f.write_str("Timed out waiting for the new shards to be ready")
+            },
+            #[cfg(feature = "etf")]
+            Self::Etf(why) => write!(f, "Invalid ETF payload: {why}"),
+            #[cfg(feature = "cluster")]
+            Self::Cluster(why) => write!(f, "Cluster error: {why}"),
         }
     }
 }
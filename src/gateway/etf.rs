@@ -0,0 +1,903 @@
+//! A minimal serde implementation of Erlang's [External Term Format], as spoken by Discord's
+//! gateway when connecting with `encoding=etf`.
+//!
+//! Only the subset of terms Discord sends and accepts is supported: atoms, integers (including
+//! bignums up to 64 bits), floats, binaries, lists, tuples and maps. Atoms `nil`, `true` and
+//! `false` are mapped to the unit value and booleans respectively.
+//!
+//! [External Term Format]: https://www.erlang.org/doc/apps/erts/erl_ext_dist.html
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use super::GatewayError;
+
+const VERSION: u8 = 131;
+
+const NEW_FLOAT_EXT: u8 = 70;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const FLOAT_EXT: u8 = 99;
+const ATOM_EXT: u8 = 100;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const STRING_EXT: u8 = 107;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const SMALL_ATOM_EXT: u8 = 115;
+const MAP_EXT: u8 = 116;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+/// An error encountered while encoding or decoding an ETF payload.
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StdError for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl From<Error> for crate::Error {
+    fn from(e: Error) -> Self {
+        crate::Error::Gateway(GatewayError::Etf(e.0))
+    }
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Deserialize an instance of type `T` from an ETF encoded payload.
+pub(crate) fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let mut deserializer = Deserializer {
+        input: bytes,
+    };
+
+    let version = deserializer.read_u8()?;
+    if version != VERSION {
+        return Err(Error(format!("unsupported ETF version {version}")));
+    }
+
+    let value = T::deserialize(&mut deserializer)?;
+    if !deserializer.input.is_empty() {
+        return Err(Error(format!("{} trailing bytes after term", deserializer.input.len())));
+    }
+
+    Ok(value)
+}
+
+/// Serialize the given data structure as an ETF encoded payload.
+pub(crate) fn to_vec<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut serializer = Serializer {
+        output: vec![VERSION],
+    };
+
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    fn take(&mut self, len: usize) -> Result<&'de [u8]> {
+        if self.input.len() < len {
+            return Err(Error("unexpected end of ETF payload".into()));
+        }
+
+        let (head, tail) = self.input.split_at(len);
+        self.input = tail;
+        Ok(head)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take_array::<1>()?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take_array()?))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take_array()?))
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    fn read_atom(&mut self, tag: u8) -> Result<&'de str> {
+        let len = match tag {
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => self.read_u8()? as usize,
+            _ => self.read_u16()? as usize,
+        };
+
+        std::str::from_utf8(self.take(len)?).map_err(|why| Error(format!("invalid atom: {why}")))
+    }
+
+    /// Reads the term following the cursor if it is the `nil` atom, leaving the cursor untouched
+    /// otherwise.
+    fn read_nil(&mut self) -> Result<bool> {
+        let mut probe = Deserializer {
+            input: self.input,
+        };
+
+        let tag = probe.read_u8()?;
+        if is_atom(tag) && probe.read_atom(tag)? == "nil" {
+            self.input = probe.input;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn visit_big<V: Visitor<'de>>(&mut self, digits: usize, visitor: V) -> Result<V::Value> {
+        let negative = self.read_u8()? != 0;
+        let bytes = self.take(digits)?;
+
+        // Digits are stored little-endian; anything past 8 significant bytes doesn't fit a u64.
+        let significant = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        if significant > 8 {
+            return Err(Error("ETF integer does not fit in 64 bits".into()));
+        }
+
+        let mut le = [0; 8];
+        le[..significant].copy_from_slice(&bytes[..significant]);
+        let magnitude = u64::from_le_bytes(le);
+
+        if !negative {
+            visitor.visit_u64(magnitude)
+        } else if magnitude <= i64::MAX as u64 + 1 {
+            visitor.visit_i64((magnitude as i64).wrapping_neg())
+        } else {
+            Err(Error("ETF integer does not fit in 64 bits".into()))
+        }
+    }
+}
+
+fn is_atom(tag: u8) -> bool {
+    matches!(tag, ATOM_EXT | SMALL_ATOM_EXT | ATOM_UTF8_EXT | SMALL_ATOM_UTF8_EXT)
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_u8()? {
+            SMALL_INTEGER_EXT => visitor.visit_u8(self.read_u8()?),
+            INTEGER_EXT => visitor.visit_i32(i32::from_be_bytes(self.take_array()?)),
+            NEW_FLOAT_EXT => visitor.visit_f64(f64::from_be_bytes(self.take_array()?)),
+            FLOAT_EXT => {
+                // A NUL padded string, as produced by `io_lib:format("~.20e", [Float])`.
+                let bytes = self.take(31)?;
+                let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                let float = std::str::from_utf8(&bytes[..end])
+                    .ok()
+                    .and_then(|s| s.trim().parse().ok())
+                    .ok_or_else(|| Error("invalid ETF float".into()))?;
+
+                visitor.visit_f64(float)
+            },
+            tag @ (ATOM_EXT | SMALL_ATOM_EXT | ATOM_UTF8_EXT | SMALL_ATOM_UTF8_EXT) => {
+                match self.read_atom(tag)? {
+                    "nil" => visitor.visit_unit(),
+                    "true" => visitor.visit_bool(true),
+                    "false" => visitor.visit_bool(false),
+                    atom => visitor.visit_borrowed_str(atom),
+                }
+            },
+            SMALL_TUPLE_EXT => {
+                let len = self.read_u8()? as usize;
+                self.visit_elements(len, visitor)
+            },
+            LARGE_TUPLE_EXT => {
+                let len = self.read_len()?;
+                self.visit_elements(len, visitor)
+            },
+            NIL_EXT => self.visit_elements(0, visitor),
+            STRING_EXT => {
+                // Erlang encodes lists of small integers as strings.
+                let len = self.read_u16()? as usize;
+                let bytes = self.take(len)?;
+                visitor.visit_seq(de::value::SeqDeserializer::new(bytes.iter().copied()))
+            },
+            LIST_EXT => {
+                let len = self.read_len()?;
+                let value = self.visit_elements(len, visitor)?;
+
+                if self.read_u8()? != NIL_EXT {
+                    return Err(Error("improper ETF lists are not supported".into()));
+                }
+
+                Ok(value)
+            },
+            BINARY_EXT => {
+                let len = self.read_len()?;
+                let bytes = self.take(len)?;
+
+                match std::str::from_utf8(bytes) {
+                    Ok(s) => visitor.visit_borrowed_str(s),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            },
+            SMALL_BIG_EXT => {
+                let digits = self.read_u8()? as usize;
+                self.visit_big(digits, visitor)
+            },
+            LARGE_BIG_EXT => {
+                let digits = self.read_len()?;
+                self.visit_big(digits, visitor)
+            },
+            MAP_EXT => {
+                let len = self.read_len()?;
+                let mut access = Access {
+                    de: self,
+                    remaining: len,
+                };
+
+                let value = visitor.visit_map(&mut access)?;
+                access.finish(value)
+            },
+            tag => Err(Error(format!("unsupported ETF tag {tag}"))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.read_nil()? {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let mut probe = Deserializer {
+            input: self.input,
+        };
+
+        if probe.read_u8()? == MAP_EXT {
+            if probe.read_u32()? != 1 {
+                return Err(Error("expected a map with a single entry for an enum".into()));
+            }
+
+            self.input = probe.input;
+            return visitor.visit_enum(Enum {
+                de: self,
+            });
+        }
+
+        let variant: &str = de::Deserialize::deserialize(&mut *self)?;
+        visitor.visit_enum(variant.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    fn visit_elements<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value> {
+        let mut access = Access {
+            de: self,
+            remaining: len,
+        };
+
+        let value = visitor.visit_seq(&mut access)?;
+        access.finish(value)
+    }
+}
+
+/// Gives sequential access to the elements of a list, tuple or map.
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+impl Access<'_, '_> {
+    /// A visitor that stops reading early would leave the cursor in the middle of a term, so
+    /// that is treated as an error.
+    fn finish<T>(self, value: T) -> Result<T> {
+        if self.remaining != 0 {
+            return Err(Error(format!("{} unread ETF elements", self.remaining)));
+        }
+
+        Ok(value)
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+/// An externally tagged enum, encoded as a map with a single entry.
+struct Enum<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for Enum<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Enum<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    fn write_atom(&mut self, atom: &str) {
+        if let Ok(len) = u8::try_from(atom.len()) {
+            self.output.push(SMALL_ATOM_UTF8_EXT);
+            self.output.push(len);
+        } else {
+            self.output.push(ATOM_UTF8_EXT);
+            self.output.extend_from_slice(&(atom.len() as u16).to_be_bytes());
+        }
+
+        self.output.extend_from_slice(atom.as_bytes());
+    }
+
+    fn write_binary(&mut self, bytes: &[u8]) -> Result<()> {
+        let len = u32::try_from(bytes.len()).map_err(|_| Error("binary too large".into()))?;
+
+        self.output.push(BINARY_EXT);
+        self.output.extend_from_slice(&len.to_be_bytes());
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_integer(&mut self, negative: bool, magnitude: u64) {
+        if let (false, Ok(small)) = (negative, u8::try_from(magnitude)) {
+            self.output.push(SMALL_INTEGER_EXT);
+            self.output.push(small);
+        } else if let Ok(value) = i32::try_from(magnitude) {
+            let value = if negative { -value } else { value };
+
+            self.output.push(INTEGER_EXT);
+            self.output.extend_from_slice(&value.to_be_bytes());
+        } else {
+            let digits = magnitude.to_le_bytes();
+            let len = digits.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+
+            self.output.push(SMALL_BIG_EXT);
+            self.output.push(len as u8);
+            self.output.push(u8::from(negative));
+            self.output.extend_from_slice(&digits[..len]);
+        }
+    }
+
+    /// Writes the header of a list or map whose length is patched in by [`Compound::finish`].
+    fn start_compound(&mut self, tag: u8) -> Compound<'_> {
+        let start = self.output.len();
+        self.output.push(tag);
+        self.output.extend_from_slice(&[0; 4]);
+
+        Compound {
+            ser: self,
+            start,
+            len: 0,
+        }
+    }
+
+    /// Writes `{variant => ...}`, the representation of a non-unit enum variant.
+    fn start_variant(&mut self, variant: &str) -> Result<()> {
+        self.output.push(MAP_EXT);
+        self.output.extend_from_slice(&1_u32.to_be_bytes());
+        self.write_binary(variant.as_bytes())
+    }
+}
+
+/// A list or map being serialized.
+struct Compound<'a> {
+    ser: &'a mut Serializer,
+    start: usize,
+    len: u32,
+}
+
+impl Compound<'_> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.len += 1;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(self) {
+        let tag = self.ser.output[self.start];
+
+        if tag == LIST_EXT {
+            if self.len == 0 {
+                // The empty list is written as a lone NIL_EXT rather than a list header.
+                self.ser.output.truncate(self.start);
+            }
+
+            self.ser.output.push(NIL_EXT);
+        }
+
+        if self.len != 0 || tag == MAP_EXT {
+            self.ser.output[self.start + 1..self.start + 5]
+                .copy_from_slice(&self.len.to_be_bytes());
+        }
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.write_atom(if v { "true" } else { "false" });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_integer(v < 0, v.unsigned_abs());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.write_integer(false, v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.output.push(NEW_FLOAT_EXT);
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write_binary(v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_binary(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_binary(v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.write_atom("nil");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.write_binary(variant.as_bytes())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.start_variant(variant)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.start_compound(LIST_EXT))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.start_compound(LIST_EXT))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.start_compound(LIST_EXT))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>> {
+        self.start_variant(variant)?;
+        Ok(self.start_compound(LIST_EXT))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.start_compound(MAP_EXT))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.start_compound(MAP_EXT))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>> {
+        self.start_variant(variant)?;
+        Ok(self.start_compound(MAP_EXT))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.element(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.len += 1;
+        self.ser.write_atom(key);
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{json, Value};
+    use crate::model::event::GatewayEvent;
+
+    fn atom(name: &str) -> Vec<u8> {
+        let mut bytes = vec![SMALL_ATOM_UTF8_EXT, name.len() as u8];
+        bytes.extend_from_slice(name.as_bytes());
+        bytes
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = vec![MAP_EXT];
+        bytes.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for (key, value) in entries {
+            bytes.extend(atom(key));
+            bytes.extend_from_slice(value);
+        }
+        bytes
+    }
+
+    fn term(body: Vec<u8>) -> Vec<u8> {
+        let mut bytes = vec![VERSION];
+        bytes.extend(body);
+        bytes
+    }
+
+    #[test]
+    fn deserialize_hello() {
+        let payload = term(map(&[
+            ("t", atom("nil")),
+            ("s", atom("nil")),
+            ("op", vec![SMALL_INTEGER_EXT, 10]),
+            ("d", map(&[("heartbeat_interval", vec![INTEGER_EXT, 0, 0, 0xa1, 0x22])])),
+        ]));
+
+        let event: GatewayEvent = from_slice(&payload).unwrap();
+        assert!(matches!(event, GatewayEvent::Hello(41250)));
+    }
+
+    #[test]
+    fn deserialize_terms() {
+        let snowflake = 81384788765712384_u64;
+        let mut big = vec![SMALL_BIG_EXT, 8, 0];
+        big.extend_from_slice(&snowflake.to_le_bytes());
+        let mut binary = vec![BINARY_EXT, 0, 0, 0, 5];
+        binary.extend_from_slice(b"hello");
+
+        let payload = term(map(&[
+            ("id", big),
+            ("name", binary),
+            ("nsfw", atom("false")),
+            ("topic", atom("nil")),
+            ("bytes", vec![STRING_EXT, 0, 3, 1, 2, 3]),
+            ("empty", vec![NIL_EXT]),
+            ("negative", vec![INTEGER_EXT, 0xff, 0xff, 0xff, 0xfe]),
+        ]));
+
+        let value: Value = from_slice(&payload).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "id": snowflake,
+                "name": "hello",
+                "nsfw": false,
+                "topic": null,
+                "bytes": [1, 2, 3],
+                "empty": [],
+                "negative": -2,
+            })
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let value = json!({
+            "op": 2,
+            "d": {
+                "token": "abc",
+                "shard": [0, 1],
+                "intents": 3276799,
+                "large": 81384788765712384_u64,
+                "negative": -5_000_000_000_i64,
+                "ratio": 0.5,
+                "compress": false,
+                "activities": [],
+                "since": null,
+            },
+        });
+
+        let encoded = to_vec(&value).unwrap();
+        assert_eq!(from_slice::<Value>(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn reject_trailing_bytes() {
+        let mut payload = term(vec![SMALL_INTEGER_EXT, 1]);
+        payload.push(0);
+
+        assert!(from_slice::<Value>(&payload).is_err());
+    }
+}
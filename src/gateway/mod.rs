@@ -40,8 +40,15 @@
 //! [docs]: https://discordapp.com/developers/docs/topics/gateway#sharding
 
 mod bridge;
+#[cfg(feature = "cluster")]
+pub mod cluster;
 mod error;
+#[cfg(feature = "etf")]
+mod etf;
+pub mod replay;
+mod session;
 mod shard;
+mod transport;
 mod ws;
 
 use std::fmt;
@@ -52,7 +59,9 @@ use reqwest::Url;
 
 pub use self::bridge::*;
 pub use self::error::Error as GatewayError;
-pub use self::shard::Shard;
+pub use self::session::{FileSessionStore, InMemorySessionStore, SessionStore, ShardSession};
+pub use self::shard::{Shard, ShardOptions};
+pub use self::transport::{GatewayConnection, GatewayTransport, WebSocketTransport};
 pub use self::ws::WsClient;
 #[cfg(feature = "http")]
 use crate::internal::prelude::*;
@@ -171,7 +180,7 @@ impl From<Activity> for ActivityData {
 /// Indicates the current connection stage of a [`Shard`].
 ///
 /// This can be useful for knowing which shards are currently "down"/"up".
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 #[non_exhaustive]
 pub enum ConnectionStage {
     /// Indicator that the [`Shard`] is normally connected and is not in, e.g., a resume phase.
@@ -236,6 +245,64 @@ impl fmt::Display for ConnectionStage {
     }
 }
 
+/// The compression method used for the whole gateway connection of a [`Shard`].
+///
+/// Transport compression keeps a single compression context alive for the lifetime of the
+/// connection, which shrinks large dispatches such as `READY` and `GUILD_CREATE` much further than
+/// compressing each payload on its own.
+///
+/// [Discord docs](https://discord.com/developers/docs/topics/gateway#transport-compression).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum TransportCompression {
+    /// No transport compression. Large payloads are still compressed individually by Discord.
+    #[default]
+    None,
+    /// The whole connection is compressed as a single zlib stream.
+    Zlib,
+    /// The whole connection is compressed as a single zstd stream.
+    #[cfg(feature = "transport_compression_zstd")]
+    Zstd,
+}
+
+impl TransportCompression {
+    /// The value of the `compress` query parameter of the gateway URL, if any.
+    pub(crate) fn query_param(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Zlib => Some("zlib-stream"),
+            #[cfg(feature = "transport_compression_zstd")]
+            Self::Zstd => Some("zstd-stream"),
+        }
+    }
+}
+
+/// The encoding of the payloads sent and received over the gateway connection of a [`Shard`].
+///
+/// [Discord docs](https://discord.com/developers/docs/topics/gateway#encoding-and-compression).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum GatewayEncoding {
+    /// Payloads are encoded as JSON text.
+    #[default]
+    Json,
+    /// Payloads are encoded in Erlang's binary External Term Format, which is cheaper to decode
+    /// than JSON and always transmits snowflakes as integers.
+    #[cfg(feature = "etf")]
+    Etf,
+}
+
+impl GatewayEncoding {
+    /// The value of the `encoding` query parameter of the gateway URL.
+    pub(crate) fn query_param(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            #[cfg(feature = "etf")]
+            Self::Etf => "etf",
+        }
+    }
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum ShardAction {
@@ -0,0 +1,501 @@
+//! Recording of the payloads a shard receives, and replaying them without a connection to
+//! Discord.
+//!
+//! A [`GatewayRecorder`] writes every payload a shard receives to a newline-delimited JSON file,
+//! one [`RecordedFrame`] per line. A [`ReplayTransport`] feeds such a file back to a shard as if it
+//! came from the gateway, so that event handlers, the framework, the cache and collectors all see
+//! the events as they were received live. This is useful to reproduce bugs locally, without a
+//! connection to the gateway.
+//!
+//! # Examples
+//!
+//! Recording the payloads received by a bot:
+//!
+//! ```rust,no_run
+//! use serenity::gateway::replay::GatewayRecorder;
+//! use serenity::prelude::*;
+//!
+//! # async fn run() -> serenity::Result<()> {
+//! let recorder = GatewayRecorder::create("gateway.ndjson")?;
+//! let mut client = Client::builder("token", GatewayIntents::non_privileged())
+//!     .gateway_recorder(recorder)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Replaying them later on. The [`Client`] still fetches the gateway URL and the session start
+//! limit over HTTP as it starts. Without a valid token or a network these requests fail, which is
+//! only logged: the shards then start as if no limit was known. To replay with no HTTP requests at
+//! all, start a [`ShardManager`] with an [`IdentifyLimiter`] instead.
+//!
+//! ```rust,no_run
+//! use serenity::gateway::replay::ReplayTransport;
+//! use serenity::gateway::{GatewayEncoding, TransportCompression};
+//! use serenity::prelude::*;
+//!
+//! # async fn run() -> serenity::Result<()> {
+//! let mut client = Client::builder("", GatewayIntents::non_privileged())
+//!     .gateway_transport(ReplayTransport::open("gateway.ndjson")?)
+//!     .gateway_encoding(GatewayEncoding::Json)
+//!     .transport_compression(TransportCompression::None)
+//!     .await?;
+//! client.start().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Client`]: crate::Client
+//! [`ShardManager`]: super::ShardManager
+//! [`IdentifyLimiter`]: super::IdentifyLimiter
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::mpsc::{self as std_mpsc, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration as StdDuration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures::channel::mpsc::{self, UnboundedReceiver as Receiver, UnboundedSender as Sender};
+use futures::{Sink, Stream, StreamExt};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tracing::{debug, warn};
+use url::Url;
+
+use super::{GatewayConnection, GatewayTransport};
+use crate::constants::Opcode;
+use crate::internal::prelude::*;
+use crate::internal::tokio::spawn_named;
+use crate::json::{from_str, to_string};
+use crate::model::id::ShardId;
+
+/// A single payload received by a shard, as written by a [`GatewayRecorder`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct RecordedFrame {
+    /// The ID of the shard that received the payload.
+    pub shard_id: u32,
+    /// When the payload was received, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The decompressed payload, as JSON.
+    pub payload: String,
+}
+
+impl RecordedFrame {
+    #[must_use]
+    pub fn new(shard_id: u32, timestamp: u64, payload: String) -> Self {
+        Self {
+            shard_id,
+            timestamp,
+            payload,
+        }
+    }
+}
+
+/// Writes every payload received by the shards to a newline-delimited JSON file.
+///
+/// Payloads are written after decompression. Payloads received with the ETF encoding are
+/// converted to JSON, so that a recording can always be replayed by a [`ReplayTransport`].
+///
+/// Frames are written on a thread of their own, so that a slow writer never holds up the shards.
+/// The writer is flushed whenever no more frames are queued, rather than after every frame.
+pub struct GatewayRecorder {
+    tx: std_mpsc::Sender<RecordedFrame>,
+}
+
+impl GatewayRecorder {
+    /// Creates a recorder writing to the given writer, from a thread spawned for it.
+    ///
+    /// The thread stops once the recorder is dropped, after writing the frames left.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        let (tx, rx) = std_mpsc::channel();
+        thread::spawn(move || write_frames(writer, &rx));
+
+        Self {
+            tx,
+        }
+    }
+
+    /// Creates a recorder writing to the file at the given path, truncating it if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file could not be created.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Records a payload received by a shard. Failures are logged, as they must not stop the
+    /// shard.
+    pub(crate) fn record(&self, shard_id: ShardId, payload: String) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX));
+
+        if self.tx.send(RecordedFrame::new(shard_id.0, timestamp, payload)).is_err() {
+            warn!("[Shard {:?}] Err recording payload: the recorder stopped", shard_id);
+        }
+    }
+}
+
+/// Writes the frames received over `rx` until every [`GatewayRecorder`] sending them is dropped.
+fn write_frames(mut writer: impl Write, rx: &std_mpsc::Receiver<RecordedFrame>) {
+    let mut next = rx.recv().ok();
+    while let Some(frame) = next {
+        let shard_id = frame.shard_id;
+        let result = to_string(&frame).and_then(|line| Ok(writeln!(writer, "{line}")?));
+        if let Err(why) = result {
+            warn!("[Shard {}] Err recording payload: {:?}", shard_id, why);
+        }
+
+        next = match rx.try_recv() {
+            Ok(frame) => Some(frame),
+            Err(TryRecvError::Empty) => {
+                if let Err(why) = writer.flush() {
+                    warn!("Err flushing recorded payloads: {:?}", why);
+                }
+                rx.recv().ok()
+            },
+            Err(TryRecvError::Disconnected) => None,
+        };
+    }
+
+    if let Err(why) = writer.flush() {
+        warn!("Err flushing recorded payloads: {:?}", why);
+    }
+}
+
+impl std::fmt::Debug for GatewayRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GatewayRecorder").finish_non_exhaustive()
+    }
+}
+
+/// The frames left to replay, shared by every connection of a [`ReplayTransport`].
+#[derive(Debug)]
+struct ReplayState {
+    frames: Mutex<VecDeque<RecordedFrame>>,
+    finished: watch::Sender<bool>,
+}
+
+/// A [`GatewayTransport`] replaying frames written by a [`GatewayRecorder`] instead of connecting
+/// to Discord.
+///
+/// Payloads sent by the shard are dropped, except for heartbeats, which are acknowledged, and close
+/// frames, which are echoed back. A connection opened after the shard reconnected continues where
+/// the previous one stopped. Once every frame has been replayed, the connection stays open and
+/// idle.
+///
+/// The shard must use [`GatewayEncoding::Json`] and [`TransportCompression::None`], as recorded
+/// payloads are decompressed JSON.
+///
+/// [`GatewayEncoding::Json`]: super::GatewayEncoding::Json
+/// [`TransportCompression::None`]: super::TransportCompression::None
+#[derive(Debug)]
+pub struct ReplayTransport {
+    state: Arc<ReplayState>,
+    realtime: bool,
+}
+
+impl ReplayTransport {
+    /// Creates a transport replaying the given frames, in order.
+    #[must_use]
+    pub fn new(frames: impl IntoIterator<Item = RecordedFrame>) -> Self {
+        Self {
+            state: Arc::new(ReplayState {
+                frames: Mutex::new(frames.into_iter().collect()),
+                finished: watch::channel(false).0,
+            }),
+            realtime: false,
+        }
+    }
+
+    /// Creates a transport replaying the frames of a file written by a [`GatewayRecorder`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file could not be read, or [`Error::Json`] if a line is not a
+    /// valid [`RecordedFrame`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut frames = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                frames.push(from_str(line)?);
+            }
+        }
+
+        Ok(Self::new(frames))
+    }
+
+    /// Keeps only the frames received by the given shard.
+    ///
+    /// A recording of several shards should be filtered this way before being replayed by a
+    /// single shard.
+    #[must_use]
+    pub fn only_shard(self, shard_id: ShardId) -> Self {
+        self.state.frames.lock().expect("poisoned frames").retain(|f| f.shard_id == shard_id.0);
+        self
+    }
+
+    /// Whether to wait between frames as long as was waited when they were recorded. By default,
+    /// frames are replayed as fast as the shard reads them.
+    #[must_use]
+    pub fn realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// Waits until every frame has been handed to the shard.
+    ///
+    /// Event handlers may still be running for the last events when this returns.
+    pub async fn finished(&self) {
+        let mut finished = self.state.finished.subscribe();
+        drop(finished.wait_for(|finished| *finished).await);
+    }
+}
+
+#[async_trait]
+impl GatewayTransport for ReplayTransport {
+    async fn connect(&self, url: Url) -> Result<Box<dyn GatewayConnection>> {
+        debug!("[Replay] Opening connection for {}", url);
+
+        let (tx, rx) = mpsc::unbounded();
+        let feeder =
+            spawn_named("replay::feed", feed(Arc::clone(&self.state), self.realtime, tx.clone()));
+
+        Ok(Box::new(ReplayConnection {
+            rx,
+            tx,
+            feeder,
+        }))
+    }
+}
+
+/// Hands the remaining frames over to a connection, until there are none left or the connection
+/// is dropped.
+async fn feed(state: Arc<ReplayState>, realtime: bool, tx: Sender<StdResult<Message, WsError>>) {
+    let mut last_timestamp = None;
+
+    loop {
+        let Some(frame) = state.frames.lock().expect("poisoned frames").pop_front() else {
+            state.finished.send_replace(true);
+            break;
+        };
+
+        if realtime {
+            if let Some(last) = last_timestamp {
+                let delay = frame.timestamp.saturating_sub(last);
+                tokio::time::sleep(StdDuration::from_millis(delay)).await;
+            }
+            last_timestamp = Some(frame.timestamp);
+        }
+
+        if let Err(why) = tx.unbounded_send(Ok(Message::Text(frame.payload.clone()))) {
+            // The connection is gone, keep the frame for the next one.
+            debug!("[Replay] Connection closed: {:?}", why);
+            state.frames.lock().expect("poisoned frames").push_front(frame);
+            break;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SentPayload {
+    op: Opcode,
+}
+
+struct ReplayConnection {
+    rx: Receiver<StdResult<Message, WsError>>,
+    /// Used to answer the payloads sent by the shard.
+    tx: Sender<StdResult<Message, WsError>>,
+    feeder: JoinHandle<()>,
+}
+
+impl Drop for ReplayConnection {
+    fn drop(&mut self) {
+        self.feeder.abort();
+    }
+}
+
+impl Stream for ReplayConnection {
+    type Item = StdResult<Message, WsError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_next_unpin(cx)
+    }
+}
+
+impl Sink<Message> for ReplayConnection {
+    type Error = WsError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<StdResult<(), WsError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> StdResult<(), WsError> {
+        let reply = match item {
+            Message::Text(text) => match from_str::<SentPayload>(text) {
+                Ok(SentPayload {
+                    op: Opcode::Heartbeat,
+                }) => Some(Message::Text(r#"{"op":11}"#.into())),
+                _ => None,
+            },
+            Message::Close(frame) => Some(Message::Close(frame)),
+            _ => None,
+        };
+
+        if let Some(reply) = reply {
+            self.tx.unbounded_send(Ok(reply)).map_err(|_| WsError::ConnectionClosed)?;
+        }
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<StdResult<(), WsError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<StdResult<(), WsError>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[cfg(feature = "framework")]
+    use std::sync::OnceLock;
+
+    use tokio::sync::RwLock;
+    use typemap_rev::TypeMap;
+
+    use super::*;
+    use crate::client::{Context, EventHandler};
+    use crate::gateway::{
+        GatewayEncoding,
+        IdentifyLimiter,
+        ShardManager,
+        ShardManagerOptions,
+        TransportCompression,
+    };
+    use crate::http::Http;
+    use crate::model::event::TypingStartEvent;
+    use crate::model::gateway::{GatewayIntents, ShardInfo};
+
+    #[derive(Default)]
+    struct Handler {
+        typing: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EventHandler for Handler {
+        async fn typing_start(&self, _: Context, event: TypingStartEvent) {
+            assert_eq!(event.user_id.get(), 2);
+            self.typing.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Lets every shard IDENTIFY immediately, as nothing is sent to Discord.
+    struct Unlimited;
+
+    #[async_trait]
+    impl IdentifyLimiter for Unlimited {
+        async fn acquire(&self, _: ShardInfo) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A writer that can be read back while the recorder still owns it.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn frames() -> Vec<RecordedFrame> {
+        let typing = r#"{"op":0,"s":2,"t":"TYPING_START","d":{"channel_id":"1","user_id":"2","timestamp":1700000000}}"#;
+
+        vec![
+            RecordedFrame::new(0, 1000, r#"{"op":10,"d":{"heartbeat_interval":41250}}"#.into()),
+            RecordedFrame::new(1, 1001, typing.replace("\"s\":2", "\"s\":1")),
+            RecordedFrame::new(0, 1002, typing.into()),
+            RecordedFrame::new(0, 1003, typing.replace("\"s\":2", "\"s\":3")),
+        ]
+    }
+
+    #[tokio::test]
+    async fn replays_through_event_handlers() {
+        let handler = Arc::new(Handler::default());
+        let transport = Arc::new(ReplayTransport::new(frames()).only_shard(ShardId(0)));
+        let recording = SharedBuffer::default();
+
+        let (manager, _rx) = ShardManager::new(ShardManagerOptions {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            event_handlers: vec![Arc::clone(&handler) as Arc<dyn EventHandler>],
+            raw_event_handlers: vec![],
+            #[cfg(feature = "framework")]
+            framework: Arc::new(OnceLock::new()),
+            shard_index: 0,
+            shard_init: 1,
+            shard_total: 1,
+            #[cfg(feature = "voice")]
+            voice_manager: None,
+            ws_url: Arc::new(tokio::sync::Mutex::new("wss://gateway.example".into())),
+            #[cfg(feature = "cache")]
+            cache: Arc::new(crate::cache::Cache::new()),
+            http: Arc::new(Http::new("")),
+            intents: GatewayIntents::non_privileged(),
+            presence: None,
+            compression: TransportCompression::None,
+            encoding: GatewayEncoding::Json,
+            transport: Some(Arc::clone(&transport) as Arc<dyn GatewayTransport>),
+            recorder: Some(Arc::new(GatewayRecorder::new(recording.clone()))),
+            session_store: None,
+            identify_limiter: Some(Arc::new(Unlimited)),
+        });
+        manager.initialize().unwrap();
+
+        tokio::time::timeout(StdDuration::from_secs(5), transport.finished()).await.unwrap();
+        tokio::time::timeout(StdDuration::from_secs(5), async {
+            while handler.typing.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(StdDuration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // The replayed shard records the frames of shard 0 as it received them.
+        let expected: Vec<_> =
+            frames().into_iter().filter(|f| f.shard_id == 0).map(|f| f.payload).collect();
+        let recorded = || String::from_utf8(recording.0.lock().unwrap().clone()).unwrap();
+        tokio::time::timeout(StdDuration::from_secs(5), async {
+            while recorded().lines().count() < expected.len() {
+                tokio::time::sleep(StdDuration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let payloads: Vec<_> = recorded()
+            .lines()
+            .map(|line| from_str::<RecordedFrame>(line).unwrap().payload)
+            .collect();
+        assert_eq!(payloads, expected);
+
+        manager.shutdown_all().await;
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::internal::prelude::*;
+use crate::model::gateway::ShardInfo;
+use crate::model::id::ShardId;
+
+/// The state needed for a [`Shard`] to resume its gateway session instead of identifying anew.
+///
+/// [`Shard`]: super::Shard
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct ShardSession {
+    /// The shard the session belongs to.
+    ///
+    /// A session is only resumed if the shard total still matches, as Discord rejects a resume
+    /// after resharding.
+    pub shard: ShardInfo,
+    /// The ID of the session, as received in the `READY` event.
+    pub session_id: String,
+    /// The last sequence number received over the session.
+    pub seq: u64,
+    /// The gateway URL to connect to when resuming, as received in the `READY` event.
+    pub resume_ws_url: Option<String>,
+}
+
+impl ShardSession {
+    #[must_use]
+    pub fn new(
+        shard: ShardInfo,
+        session_id: String,
+        seq: u64,
+        resume_ws_url: Option<String>,
+    ) -> Self {
+        Self {
+            shard,
+            session_id,
+            seq,
+            resume_ws_url,
+        }
+    }
+}
+
+/// Storage for [`ShardSession`]s, allowing shards to resume their sessions after the process is
+/// restarted.
+///
+/// Sessions are saved when shards are shut down through [`ShardManager::shutdown_all`], and loaded
+/// by the [`ShardQueuer`] when starting a shard. A loaded session is removed from the store, as it
+/// can only be resumed once.
+///
+/// [`ShardManager::shutdown_all`]: super::ShardManager::shutdown_all
+/// [`ShardQueuer`]: super::ShardQueuer
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Loads the saved session of a shard, if any.
+    async fn load(&self, shard_id: ShardId) -> Result<Option<ShardSession>>;
+
+    /// Saves the session of a shard, replacing any previously saved session.
+    async fn save(&self, session: ShardSession) -> Result<()>;
+
+    /// Removes the saved session of a shard, if any.
+    async fn remove(&self, shard_id: ShardId) -> Result<()>;
+}
+
+/// A [`SessionStore`] keeping sessions in memory.
+///
+/// Sessions do not outlive the process, but survive the [`ShardManager`] being recreated.
+///
+/// [`ShardManager`]: super::ShardManager
+#[derive(Debug, Default)]
+pub struct InMemorySessionStore {
+    sessions: Mutex<HashMap<ShardId, ShardSession>>,
+}
+
+impl InMemorySessionStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for InMemorySessionStore {
+    async fn load(&self, shard_id: ShardId) -> Result<Option<ShardSession>> {
+        Ok(self.sessions.lock().expect("poisoned sessions").get(&shard_id).cloned())
+    }
+
+    async fn save(&self, session: ShardSession) -> Result<()> {
+        self.sessions.lock().expect("poisoned sessions").insert(session.shard.id, session);
+        Ok(())
+    }
+
+    async fn remove(&self, shard_id: ShardId) -> Result<()> {
+        self.sessions.lock().expect("poisoned sessions").remove(&shard_id);
+        Ok(())
+    }
+}
+
+/// A [`SessionStore`] keeping sessions in a JSON file, so that they survive process restarts.
+///
+/// The file is created on the first save. It should not be shared between processes running
+/// concurrently.
+#[derive(Debug)]
+pub struct FileSessionStore {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl FileSessionStore {
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Returns the path of the file sessions are kept in.
+    #[must_use]
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    async fn read(&self) -> Result<Vec<ShardSession>> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => crate::json::from_slice(&bytes),
+            Err(why) if why.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    async fn write(&self, sessions: &[ShardSession]) -> Result<()> {
+        // Write to a temporary file first, so that a crash mid-write can't corrupt the store.
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        tokio::fs::write(&tmp, crate::json::to_vec(&sessions)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn load(&self, shard_id: ShardId) -> Result<Option<ShardSession>> {
+        let _guard = self.lock.lock().await;
+        let sessions = self.read().await?;
+
+        Ok(sessions.into_iter().find(|s| s.shard.id == shard_id))
+    }
+
+    async fn save(&self, session: ShardSession) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut sessions = self.read().await?;
+
+        sessions.retain(|s| s.shard.id != session.shard.id);
+        sessions.push(session);
+
+        self.write(&sessions).await
+    }
+
+    async fn remove(&self, shard_id: ShardId) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut sessions = self.read().await?;
+
+        let len = sessions.len();
+        sessions.retain(|s| s.shard.id != shard_id);
+        if sessions.len() == len {
+            return Ok(());
+        }
+
+        self.write(&sessions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: u32, seq: u64) -> ShardSession {
+        ShardSession::new(
+            ShardInfo::new(ShardId(id), 2),
+            format!("session-{id}"),
+            seq,
+            Some("wss://gateway-us-east1-b.discord.gg".into()),
+        )
+    }
+
+    async fn roundtrip(store: &dyn SessionStore) {
+        assert!(store.load(ShardId(0)).await.unwrap().is_none());
+
+        store.save(session(0, 10)).await.unwrap();
+        store.save(session(1, 20)).await.unwrap();
+        store.save(session(0, 30)).await.unwrap();
+
+        let loaded = store.load(ShardId(0)).await.unwrap().unwrap();
+        assert_eq!(loaded.session_id, "session-0");
+        assert_eq!(loaded.seq, 30);
+        assert_eq!(loaded.shard.total, 2);
+
+        store.remove(ShardId(0)).await.unwrap();
+        assert!(store.load(ShardId(0)).await.unwrap().is_none());
+        assert_eq!(store.load(ShardId(1)).await.unwrap().unwrap().seq, 20);
+    }
+
+    #[tokio::test]
+    async fn in_memory_store() {
+        roundtrip(&InMemorySessionStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn file_store() {
+        let path =
+            std::env::temp_dir().join(format!("serenity-sessions-{}.json", std::process::id()));
+        let store = FileSessionStore::new(&path);
+
+        roundtrip(&store).await;
+
+        // Sessions are read back from disk by a fresh store.
+        let reopened = FileSessionStore::new(&path);
+        assert_eq!(reopened.load(ShardId(1)).await.unwrap().unwrap().seq, 20);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -7,14 +7,20 @@ use tokio_tungstenite::tungstenite::protocol::frame::CloseFrame;
 use tracing::{debug, error, info, instrument, trace, warn};
 use url::Url;
 
+use super::replay::GatewayRecorder;
 use super::{
     ActivityData,
     ChunkGuildFilter,
     ConnectionStage,
+    GatewayEncoding,
     GatewayError,
+    GatewayTransport,
     PresenceData,
     ReconnectType,
     ShardAction,
+    ShardSession,
+    TransportCompression,
+    WebSocketTransport,
     WsClient,
 };
 use crate::constants::{self, close_codes};
@@ -66,6 +72,7 @@ pub struct Shard {
     last_heartbeat_acknowledged: bool,
     seq: u64,
     session_id: Option<String>,
+    resume_ws_url: Option<String>,
     shard_info: ShardInfo,
     stage: ConnectionStage,
     /// Instant of when the shard was started.
@@ -75,6 +82,10 @@ pub struct Shard {
     pub token: String,
     ws_url: Arc<Mutex<String>>,
     pub intents: GatewayIntents,
+    compression: TransportCompression,
+    encoding: GatewayEncoding,
+    transport: Arc<dyn GatewayTransport>,
+    recorder: Option<Arc<GatewayRecorder>>,
 }
 
 impl Shard {
@@ -114,6 +125,9 @@ impl Shard {
     /// # }
     /// ```
     ///
+    /// The shard connects over a [`WebSocketTransport`], without transport compression and with
+    /// the JSON encoding. Use [`Self::with_options`] to change these.
+    ///
     /// # Errors
     ///
     /// On Error, will return either [`Error::Gateway`], [`Error::Tungstenite`] or a Rustls/native
@@ -125,17 +139,85 @@ impl Shard {
         intents: GatewayIntents,
         presence: Option<PresenceData>,
     ) -> Result<Shard> {
-        let url = ws_url.lock().await.clone();
-        let client = connect(&url).await?;
+        Self::with_options(ws_url, token, shard_info, intents, presence, ShardOptions::new()).await
+    }
+
+    /// Instantiates a new shard like [`Self::new`], connecting with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::new`].
+    pub async fn with_options(
+        ws_url: Arc<Mutex<String>>,
+        token: &str,
+        shard_info: ShardInfo,
+        intents: GatewayIntents,
+        presence: Option<PresenceData>,
+        options: ShardOptions,
+    ) -> Result<Shard> {
+        Self::open(ws_url, token, shard_info, intents, presence, options, None).await
+    }
+
+    /// Creates a new shard resuming a session saved with [`Self::session`], instead of
+    /// identifying.
+    ///
+    /// The connection is made to the resume gateway URL of the session, falling back to `ws_url`
+    /// if there is none, and a RESUME is sent right away. The arguments are otherwise the same as
+    /// for [`Self::with_options`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::new`], or an error if the RESUME could not be sent.
+    pub async fn from_session(
+        ws_url: Arc<Mutex<String>>,
+        token: &str,
+        shard_info: ShardInfo,
+        intents: GatewayIntents,
+        presence: Option<PresenceData>,
+        options: ShardOptions,
+        session: ShardSession,
+    ) -> Result<Shard> {
+        let session_id = session.session_id.clone();
+        let mut shard =
+            Self::open(ws_url, token, shard_info, intents, presence, options, Some(session))
+                .await?;
+
+        shard.client.send_resume(&shard.shard_info, &session_id, shard.seq, &shard.token).await?;
+
+        Ok(shard)
+    }
+
+    async fn open(
+        ws_url: Arc<Mutex<String>>,
+        token: &str,
+        shard_info: ShardInfo,
+        intents: GatewayIntents,
+        presence: Option<PresenceData>,
+        options: ShardOptions,
+        session: Option<ShardSession>,
+    ) -> Result<Shard> {
+        let ShardOptions {
+            compression,
+            encoding,
+            transport,
+        } = options;
+        let transport = transport.unwrap_or_else(|| Arc::new(WebSocketTransport));
+        let resume_ws_url = session.as_ref().and_then(|s| s.resume_ws_url.clone());
+        let url = match &resume_ws_url {
+            Some(url) => url.clone(),
+            None => ws_url.lock().await.clone(),
+        };
+        let client = connect(&*transport, &url, compression, encoding).await?;
 
         let presence = presence.unwrap_or_default();
         let last_heartbeat_sent = None;
         let last_heartbeat_ack = None;
         let heartbeat_interval = None;
         let last_heartbeat_acknowledged = true;
-        let seq = 0;
-        let stage = ConnectionStage::Handshake;
-        let session_id = None;
+        let seq = session.as_ref().map_or(0, |s| s.seq);
+        let stage =
+            if session.is_some() { ConnectionStage::Resuming } else { ConnectionStage::Handshake };
+        let session_id = session.map(|s| s.session_id);
 
         Ok(Shard {
             client,
@@ -150,9 +232,14 @@ impl Shard {
             started: Instant::now(),
             token: token.to_string(),
             session_id,
+            resume_ws_url,
             shard_info,
             ws_url,
             intents,
+            compression,
+            encoding,
+            transport,
+            recorder: None,
         })
     }
 
@@ -166,20 +253,30 @@ impl Shard {
         self.application_id_callback = Some(Box::new(callback));
     }
 
+    /// Sets a recorder that every payload received by the shard is written to, starting with the
+    /// current connection.
+    pub fn set_recorder(&mut self, recorder: Arc<GatewayRecorder>) {
+        self.client.set_recorder(Arc::clone(&recorder), self.shard_info.id);
+        self.recorder = Some(recorder);
+    }
+
     /// Retrieves the current presence of the shard.
     #[inline]
+    #[must_use]
     pub fn presence(&self) -> &PresenceData {
         &self.presence
     }
 
     /// Retrieves the value of when the last heartbeat was sent.
     #[inline]
+    #[must_use]
     pub fn last_heartbeat_sent(&self) -> Option<Instant> {
         self.last_heartbeat_sent
     }
 
     /// Retrieves the value of when the last heartbeat ack was received.
     #[inline]
+    #[must_use]
     pub fn last_heartbeat_ack(&self) -> Option<Instant> {
         self.last_heartbeat_ack
     }
@@ -220,25 +317,47 @@ impl Shard {
 
     /// Returns the heartbeat interval dictated by Discord, if the Hello packet has been received.
     #[inline]
+    #[must_use]
     pub fn heartbeat_interval(&self) -> Option<std::time::Duration> {
         self.heartbeat_interval
     }
 
     #[inline]
+    #[must_use]
     pub fn last_heartbeat_acknowledged(&self) -> bool {
         self.last_heartbeat_acknowledged
     }
 
     #[inline]
+    #[must_use]
     pub fn seq(&self) -> u64 {
         self.seq
     }
 
     #[inline]
+    #[must_use]
     pub fn session_id(&self) -> Option<&String> {
         self.session_id.as_ref()
     }
 
+    /// Returns the state needed to resume the shard's current session, if it has one.
+    ///
+    /// This can be stored in a [`SessionStore`] and passed to [`Self::from_session`] after a
+    /// restart, allowing the shard to resume instead of identifying.
+    ///
+    /// [`SessionStore`]: super::SessionStore
+    #[must_use]
+    pub fn session(&self) -> Option<ShardSession> {
+        self.session_id.as_ref().map(|session_id| {
+            ShardSession::new(
+                self.shard_info,
+                session_id.clone(),
+                self.seq,
+                self.resume_ws_url.clone(),
+            )
+        })
+    }
+
     #[inline]
     #[instrument(skip(self))]
     pub fn set_activity(&mut self, activity: Option<ActivityData>) {
@@ -266,15 +385,29 @@ impl Shard {
     ///
     /// For example, if using 3 shards in total, and if this is shard 1, then it can be read as
     /// "the second of three shards".
+    #[must_use]
     pub fn shard_info(&self) -> ShardInfo {
         self.shard_info
     }
 
     /// Returns the current connection stage of the shard.
+    #[must_use]
     pub fn stage(&self) -> ConnectionStage {
         self.stage
     }
 
+    /// Returns the transport compression used for the shard's connection.
+    #[must_use]
+    pub fn compression(&self) -> TransportCompression {
+        self.compression
+    }
+
+    /// Returns the payload encoding used for the shard's connection.
+    #[must_use]
+    pub fn encoding(&self) -> GatewayEncoding {
+        self.encoding
+    }
+
     #[instrument(skip(self))]
     fn handle_gateway_dispatch(&mut self, seq: u64, event: &Event) -> Option<ShardAction> {
         if seq > self.seq + 1 {
@@ -286,6 +419,7 @@ impl Shard {
                 debug!("[{:?}] Received Ready", self.shard_info);
 
                 self.session_id = Some(ready.ready.session_id.clone());
+                self.resume_ws_url = Some(ready.ready.resume_gateway_url.clone());
                 self.stage = ConnectionStage::Connected;
 
                 if let Some(callback) = self.application_id_callback.take() {
@@ -385,6 +519,7 @@ impl Shard {
                 info!("[{:?}] Invalid session.", self.shard_info);
 
                 self.session_id = None;
+                self.resume_ws_url = None;
             },
             Some(close_codes::INVALID_GATEWAY_INTENTS) => {
                 error!("[{:?}] Invalid gateway intents have been provided.", self.shard_info);
@@ -453,12 +588,12 @@ impl Shard {
             &Ok(GatewayEvent::Hello(interval)) => {
                 debug!("[{:?}] Received a Hello; interval: {}", self.shard_info, interval);
 
+                self.heartbeat_interval = Some(std::time::Duration::from_millis(interval));
+
                 if self.stage == ConnectionStage::Resuming {
                     return Ok(None);
                 }
 
-                self.heartbeat_interval = Some(std::time::Duration::from_millis(interval));
-
                 Ok(Some(if self.stage == ConnectionStage::Handshake {
                     ShardAction::Identify
                 } else {
@@ -470,11 +605,16 @@ impl Shard {
             &Ok(GatewayEvent::InvalidateSession(resumable)) => {
                 info!("[{:?}] Received session invalidation", self.shard_info);
 
-                Ok(Some(if resumable {
-                    ShardAction::Reconnect(ReconnectType::Resume)
-                } else {
-                    ShardAction::Reconnect(ReconnectType::Reidentify)
-                }))
+                if resumable {
+                    return Ok(Some(ShardAction::Reconnect(ReconnectType::Resume)));
+                }
+
+                // The session is gone, so it must not be saved to be resumed again. The shard is
+                // restarted by the queuer, which accounts for the new IDENTIFY.
+                self.session_id = None;
+                self.resume_ws_url = None;
+
+                Ok(Some(ShardAction::Reconnect(ReconnectType::Reidentify)))
             },
             Ok(GatewayEvent::Reconnect) => Ok(Some(ShardAction::Reconnect(ReconnectType::Resume))),
             Err(Error::Gateway(GatewayError::Closed(data))) => {
@@ -569,6 +709,7 @@ impl Shard {
         Some(self.reconnection_type())
     }
 
+    #[must_use]
     pub fn reconnection_type(&self) -> ReconnectType {
         if self.session_id().is_some() {
             ReconnectType::Resume
@@ -688,7 +829,8 @@ impl Shard {
 
     /// Initializes a new WebSocket client.
     ///
-    /// This will set the stage of the shard before and after instantiation of the client.
+    /// This will set the stage of the shard before and after instantiation of the client. The new
+    /// client starts with a fresh decompression context, as required by transport compression.
     #[instrument(skip(self))]
     pub async fn initialize(&mut self) -> Result<WsClient> {
         debug!("[{:?}] Initializing.", self.shard_info);
@@ -700,10 +842,17 @@ impl Shard {
         //
         // This is used to accurately assess whether the state of the shard is accurate when a
         // Hello is received.
+        let url = self.ws_url.lock().await.clone();
+        self.connect_to(&url).await
+    }
+
+    async fn connect_to(&mut self, url: &str) -> Result<WsClient> {
         self.stage = ConnectionStage::Connecting;
         self.started = Instant::now();
-        let url = &self.ws_url.lock().await.clone();
-        let client = connect(url).await?;
+        let mut client = connect(&*self.transport, url, self.compression, self.encoding).await?;
+        if let Some(recorder) = &self.recorder {
+            client.set_recorder(Arc::clone(recorder), self.shard_info.id);
+        }
         self.stage = ConnectionStage::Handshake;
 
         Ok(client)
@@ -716,15 +865,23 @@ impl Shard {
         self.heartbeat_interval = None;
         self.last_heartbeat_acknowledged = true;
         self.session_id = None;
+        self.resume_ws_url = None;
         self.stage = ConnectionStage::Disconnected;
         self.seq = 0;
     }
 
+    /// Opens a new connection and sends a RESUME for the current session.
+    ///
+    /// The connection is made to the resume gateway URL given in the `READY` event, falling back
+    /// to the regular gateway URL if there is none.
     #[instrument(skip(self))]
     pub async fn resume(&mut self) -> Result<()> {
         debug!("[{:?}] Attempting to resume", self.shard_info);
 
-        self.client = self.initialize().await?;
+        self.client = match self.resume_ws_url.clone() {
+            Some(url) => self.connect_to(&url).await?,
+            None => self.initialize().await?,
+        };
         self.stage = ConnectionStage::Resuming;
 
         match &self.session_id {
@@ -751,13 +908,68 @@ impl Shard {
     }
 }
 
-async fn connect(base_url: &str) -> Result<WsClient> {
-    let url =
+/// How a [`Shard`] connects to the gateway, beyond what is given to [`Shard::new`].
+#[derive(Clone, Default)]
+#[must_use]
+pub struct ShardOptions {
+    compression: TransportCompression,
+    encoding: GatewayEncoding,
+    transport: Option<Arc<dyn GatewayTransport>>,
+}
+
+impl ShardOptions {
+    /// Creates options connecting over a [`WebSocketTransport`], without transport compression and
+    /// with the JSON encoding.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the transport compression of the connection.
+    pub fn compression(mut self, compression: TransportCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets the encoding of the payloads sent and received over the connection.
+    pub fn encoding(mut self, encoding: GatewayEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the transport the connection is opened with, instead of a [`WebSocketTransport`].
+    pub fn transport(mut self, transport: Arc<dyn GatewayTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+}
+
+impl std::fmt::Debug for ShardOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShardOptions")
+            .field("compression", &self.compression)
+            .field("encoding", &self.encoding)
+            .field("transport", &self.transport.as_ref().map(|_| "GatewayTransport"))
+            .finish()
+    }
+}
+
+async fn connect(
+    transport: &dyn GatewayTransport,
+    base_url: &str,
+    compression: TransportCompression,
+    encoding: GatewayEncoding,
+) -> Result<WsClient> {
+    let mut url =
         Url::parse(&format!("{base_url}?v={}", constants::GATEWAY_VERSION)).map_err(|why| {
             warn!("Error building gateway URL with base `{}`: {:?}", base_url, why);
 
             Error::Gateway(GatewayError::BuildingUrl)
         })?;
 
-    WsClient::connect(url).await
+    url.query_pairs_mut().append_pair("encoding", encoding.query_param());
+    if let Some(compress) = compression.query_param() {
+        url.query_pairs_mut().append_pair("compress", compress);
+    }
+
+    WsClient::connect(transport, url, compression, encoding).await
 }
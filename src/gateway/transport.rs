@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{Sink, Stream};
+use tokio_tungstenite::connect_async_with_config;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use url::Url;
+
+use crate::internal::prelude::*;
+
+/// A single open connection to the gateway, as returned by [`GatewayTransport::connect`].
+///
+/// This is implemented for every stream of websocket [`Message`]s that is also a sink for them,
+/// such as tokio-tungstenite's `WebSocketStream`. Compression and encoding of the payloads are
+/// handled by the [`Shard`], so a connection only has to move the messages themselves.
+///
+/// [`Shard`]: super::Shard
+pub trait GatewayConnection:
+    Stream<Item = StdResult<Message, WsError>> + Sink<Message, Error = WsError> + Send + Sync + Unpin
+{
+}
+
+impl<T> GatewayConnection for T where
+    T: Stream<Item = StdResult<Message, WsError>>
+        + Sink<Message, Error = WsError>
+        + Send
+        + Sync
+        + Unpin
+{
+}
+
+/// Opens the connections a [`Shard`] uses to talk to the gateway.
+///
+/// The [`WebSocketTransport`] is used by default. Another transport can be set with
+/// [`ClientBuilder::gateway_transport`], for example to route shards through a gateway proxy, or to
+/// feed recorded payloads to a shard in tests without a network.
+///
+/// [`Shard`]: super::Shard
+/// [`ClientBuilder::gateway_transport`]: crate::client::ClientBuilder::gateway_transport
+#[async_trait]
+pub trait GatewayTransport: Send + Sync {
+    /// Opens a new connection to the given gateway URL.
+    ///
+    /// The URL already carries the gateway version, encoding and compression as query parameters.
+    /// A new connection is opened every time the shard reconnects or resumes.
+    async fn connect(&self, url: Url) -> Result<Box<dyn GatewayConnection>>;
+}
+
+#[async_trait]
+impl<T: GatewayTransport + ?Sized> GatewayTransport for Arc<T> {
+    async fn connect(&self, url: Url) -> Result<Box<dyn GatewayConnection>> {
+        (**self).connect(url).await
+    }
+}
+
+/// The default [`GatewayTransport`], connecting to the gateway over a websocket.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebSocketTransport;
+
+#[async_trait]
+impl GatewayTransport for WebSocketTransport {
+    async fn connect(&self, url: Url) -> Result<Box<dyn GatewayConnection>> {
+        let config = WebSocketConfig {
+            max_message_size: None,
+            max_frame_size: None,
+            ..Default::default()
+        };
+        let (stream, _) = connect_async_with_config(url, Some(config), false).await?;
+
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use std::pin::Pin;
+    use std::sync::Mutex;
+    use std::task::{Context, Poll};
+
+    use futures::channel::mpsc::{self, UnboundedReceiver as Receiver, UnboundedSender as Sender};
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::gateway::{Shard, ShardOptions};
+    use crate::model::event::GatewayEvent;
+    use crate::model::gateway::{GatewayIntents, ShardInfo};
+    use crate::model::id::ShardId;
+
+    /// A connection whose messages are exchanged with the test over channels.
+    struct ChannelConnection {
+        rx: Receiver<Message>,
+        tx: Sender<Message>,
+    }
+
+    impl Stream for ChannelConnection {
+        type Item = StdResult<Message, WsError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.rx.poll_next_unpin(cx).map(|message| message.map(Ok))
+        }
+    }
+
+    impl Sink<Message> for ChannelConnection {
+        type Error = WsError;
+
+        fn poll_ready(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<StdResult<(), WsError>> {
+            Pin::new(&mut self.tx).poll_ready(cx).map_err(|_| WsError::ConnectionClosed)
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, item: Message) -> StdResult<(), WsError> {
+            Pin::new(&mut self.tx).start_send(item).map_err(|_| WsError::ConnectionClosed)
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<StdResult<(), WsError>> {
+            Pin::new(&mut self.tx).poll_flush(cx).map_err(|_| WsError::ConnectionClosed)
+        }
+
+        fn poll_close(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<StdResult<(), WsError>> {
+            Pin::new(&mut self.tx).poll_close(cx).map_err(|_| WsError::ConnectionClosed)
+        }
+    }
+
+    /// Hands out a single [`ChannelConnection`], remembering the URL it was asked for.
+    #[derive(Default)]
+    struct ChannelTransport {
+        connection: Mutex<Option<ChannelConnection>>,
+        url: Mutex<Option<Url>>,
+    }
+
+    #[async_trait]
+    impl GatewayTransport for ChannelTransport {
+        async fn connect(&self, url: Url) -> Result<Box<dyn GatewayConnection>> {
+            *self.url.lock().unwrap() = Some(url);
+
+            let connection = self.connection.lock().unwrap().take();
+            Ok(Box::new(connection.ok_or(WsError::ConnectionClosed)?))
+        }
+    }
+
+    #[tokio::test]
+    async fn shard_uses_custom_transport() {
+        let (server_tx, rx) = mpsc::unbounded();
+        let (tx, mut server_rx) = mpsc::unbounded();
+        let transport = Arc::new(ChannelTransport {
+            connection: Mutex::new(Some(ChannelConnection {
+                rx,
+                tx,
+            })),
+            ..ChannelTransport::default()
+        });
+
+        server_tx
+            .unbounded_send(Message::Text(r#"{"op":10,"d":{"heartbeat_interval":41250}}"#.into()))
+            .unwrap();
+
+        let mut shard = Shard::with_options(
+            Arc::new(tokio::sync::Mutex::new("wss://gateway.example".into())),
+            "token",
+            ShardInfo::new(ShardId(0), 1),
+            GatewayIntents::non_privileged(),
+            None,
+            ShardOptions::new().transport(Arc::clone(&transport) as Arc<dyn GatewayTransport>),
+        )
+        .await
+        .unwrap();
+
+        let url = transport.url.lock().unwrap().clone().unwrap();
+        assert_eq!(url.host_str(), Some("gateway.example"));
+        assert!(url.query_pairs().any(|(key, value)| key == "encoding" && value == "json"));
+
+        let event = shard.client.recv_json().await.unwrap();
+        assert!(matches!(event, Some(GatewayEvent::Hello(41250))));
+
+        shard.identify().await.unwrap();
+        let Some(Message::Text(identify)) = server_rx.next().await else {
+            panic!("expected an IDENTIFY text message");
+        };
+        assert!(identify.contains(r#""op":2"#));
+        assert!(identify.contains(r#""token":"token""#));
+    }
+}
@@ -1,29 +1,45 @@
 use std::env::consts;
 #[cfg(feature = "client")]
-use std::io::Read;
+use std::io::{self, Read};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 #[cfg(feature = "client")]
 use flate2::read::ZlibDecoder;
+use flate2::Decompress;
+#[cfg(feature = "client")]
+use flate2::{FlushDecompress, Status};
 use futures::SinkExt;
 #[cfg(feature = "client")]
 use futures::StreamExt;
-use tokio::net::TcpStream;
 #[cfg(feature = "client")]
 use tokio::time::{timeout, Duration};
 #[cfg(feature = "client")]
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
-use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 #[cfg(feature = "client")]
 use tokio_tungstenite::tungstenite::Error as WsError;
 use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{connect_async_with_config, MaybeTlsStream, WebSocketStream};
 #[cfg(feature = "client")]
 use tracing::warn;
 use tracing::{debug, instrument, trace};
 use url::Url;
-
-use super::{ActivityData, ChunkGuildFilter, PresenceData};
+#[cfg(feature = "transport_compression_zstd")]
+use zstd_safe::DCtx;
+#[cfg(all(feature = "client", feature = "transport_compression_zstd"))]
+use zstd_safe::{InBuffer, OutBuffer};
+
+#[cfg(all(feature = "client", feature = "etf"))]
+use super::etf;
+use super::replay::GatewayRecorder;
+use super::{
+    ActivityData,
+    ChunkGuildFilter,
+    GatewayConnection,
+    GatewayEncoding,
+    GatewayTransport,
+    PresenceData,
+    TransportCompression,
+};
 use crate::constants::{self, Opcode};
 #[cfg(feature = "client")]
 use crate::gateway::GatewayError;
@@ -33,7 +49,7 @@ use crate::json::to_string;
 #[cfg(feature = "client")]
 use crate::model::event::GatewayEvent;
 use crate::model::gateway::{GatewayIntents, ShardInfo};
-use crate::model::id::{GuildId, UserId};
+use crate::model::id::{GuildId, ShardId, UserId};
 #[cfg(feature = "client")]
 use crate::Error;
 use crate::Result;
@@ -93,28 +109,180 @@ struct WebSocketMessage<'a> {
     d: WebSocketMessageData<'a>,
 }
 
-pub struct WsClient(WebSocketStream<MaybeTlsStream<TcpStream>>);
+pub struct WsClient {
+    stream: Box<dyn GatewayConnection>,
+    compression: Compression,
+    encoding: GatewayEncoding,
+    recorder: Option<(Arc<GatewayRecorder>, ShardId)>,
+}
 
 #[cfg(feature = "client")]
 const TIMEOUT: Duration = Duration::from_millis(500);
 #[cfg(feature = "client")]
 const DECOMPRESSION_MULTIPLIER: usize = 3;
+/// Every complete message of a zlib-stream connection ends with a Z_SYNC_FLUSH marker.
+#[cfg(feature = "client")]
+const ZLIB_SUFFIX: [u8; 4] = [0x00, 0x00, 0xFF, 0xFF];
+
+/// The decompression state of a single gateway connection.
+///
+/// The buffers are kept around between messages, so that they only have to grow until they fit
+/// the largest payload received so far.
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+enum Compression {
+    /// Binary messages are not compressed.
+    None,
+    /// Each binary message is compressed on its own.
+    Payload { decompressed: Vec<u8> },
+    /// The whole connection is one zlib stream, so the inflate context must outlive each message.
+    Zlib { inflater: Decompress, compressed: Vec<u8>, decompressed: Vec<u8> },
+    /// The whole connection is one zstd stream, and every message is flushed as a whole.
+    #[cfg(feature = "transport_compression_zstd")]
+    Zstd { decompressor: DCtx<'static>, decompressed: Vec<u8> },
+}
 
-impl WsClient {
-    pub(crate) async fn connect(url: Url) -> Result<Self> {
-        let config = WebSocketConfig {
-            max_message_size: None,
-            max_frame_size: None,
-            ..Default::default()
-        };
-        let (stream, _) = connect_async_with_config(url, Some(config), false).await?;
+impl Compression {
+    fn new(compression: TransportCompression, encoding: GatewayEncoding) -> Self {
+        match compression {
+            // ETF payloads are binary messages as well, so per-payload compression is only asked
+            // for with JSON, where binary messages can only be compressed ones.
+            TransportCompression::None if encoding != GatewayEncoding::Json => Self::None,
+            TransportCompression::None => Self::Payload {
+                decompressed: Vec::new(),
+            },
+            TransportCompression::Zlib => Self::Zlib {
+                inflater: Decompress::new(true),
+                compressed: Vec::new(),
+                decompressed: Vec::new(),
+            },
+            #[cfg(feature = "transport_compression_zstd")]
+            TransportCompression::Zstd => Self::Zstd {
+                decompressor: DCtx::create(),
+                decompressed: Vec::new(),
+            },
+        }
+    }
+
+    /// Decompresses a binary message, returning `None` if more frames are needed before a full
+    /// payload can be read.
+    ///
+    /// The returned slice is only valid until the next message is inflated.
+    #[cfg(feature = "client")]
+    fn inflate<'a>(&'a mut self, frame: &'a [u8]) -> io::Result<Option<&'a [u8]>> {
+        match self {
+            Self::None => Ok(Some(frame)),
+            Self::Payload {
+                decompressed,
+            } => {
+                decompressed.clear();
+                ZlibDecoder::new(frame).read_to_end(decompressed)?;
+
+                Ok(Some(&decompressed[..]))
+            },
+            Self::Zlib {
+                inflater,
+                compressed,
+                decompressed,
+            } => {
+                compressed.extend_from_slice(frame);
+                if !compressed.ends_with(&ZLIB_SUFFIX) {
+                    return Ok(None);
+                }
+
+                decompressed.clear();
+                let mut consumed = 0;
+                loop {
+                    if decompressed.len() == decompressed.capacity() {
+                        decompressed.reserve(compressed.len() * DECOMPRESSION_MULTIPLIER);
+                    }
+
+                    let total_in = inflater.total_in();
+                    let total_out = inflater.total_out();
+                    let status = inflater
+                        .decompress_vec(
+                            &compressed[consumed..],
+                            decompressed,
+                            FlushDecompress::Sync,
+                        )
+                        .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))?;
+                    consumed += (inflater.total_in() - total_in) as usize;
+
+                    let done = consumed == compressed.len()
+                        && decompressed.len() < decompressed.capacity();
+                    let stalled =
+                        inflater.total_in() == total_in && inflater.total_out() == total_out;
+                    if done || status == Status::StreamEnd || stalled {
+                        break;
+                    }
+                }
+
+                let complete = consumed == compressed.len();
+                compressed.clear();
+                if !complete {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "zlib stream stopped before the end of the message",
+                    ));
+                }
+
+                Ok(Some(&decompressed[..]))
+            },
+            #[cfg(feature = "transport_compression_zstd")]
+            Self::Zstd {
+                decompressor,
+                decompressed,
+            } => {
+                decompressed.clear();
+                let mut input = InBuffer::around(frame);
+                loop {
+                    if decompressed.len() == decompressed.capacity() {
+                        decompressed.reserve(frame.len() * DECOMPRESSION_MULTIPLIER);
+                    }
+
+                    let pos = decompressed.len();
+                    let mut output = OutBuffer::around_pos(decompressed, pos);
+                    decompressor.decompress_stream(&mut output, &mut input).map_err(|code| {
+                        io::Error::new(io::ErrorKind::InvalidData, zstd_safe::get_error_name(code))
+                    })?;
+
+                    // Once all input is read, zstd may still hold back output if the buffer was
+                    // filled exactly, so only stop when there is room left.
+                    if input.pos() == frame.len() && decompressed.len() < decompressed.capacity() {
+                        break;
+                    }
+                }
+
+                Ok(Some(&decompressed[..]))
+            },
+        }
+    }
 
-        Ok(Self(stream))
+    /// Whether the IDENTIFY payload should ask for per-payload compression.
+    fn compress_payloads(&self) -> bool {
+        matches!(self, Self::Payload { .. })
+    }
+}
+
+impl WsClient {
+    pub(crate) async fn connect(
+        transport: &dyn GatewayTransport,
+        url: Url,
+        compression: TransportCompression,
+        encoding: GatewayEncoding,
+    ) -> Result<Self> {
+        let stream = transport.connect(url).await?;
+
+        Ok(Self {
+            stream,
+            compression: Compression::new(compression, encoding),
+            encoding,
+            recorder: None,
+        })
     }
 
     #[cfg(feature = "client")]
     pub(crate) async fn recv_json(&mut self) -> Result<Option<GatewayEvent>> {
-        let message = match timeout(TIMEOUT, self.0.next()).await {
+        let message = match timeout(TIMEOUT, self.stream.next()).await {
             Ok(Some(Ok(msg))) => msg,
             Ok(Some(Err(e))) => return Err(e.into()),
             Ok(None) | Err(_) => return Ok(None),
@@ -122,28 +290,41 @@ impl WsClient {
 
         let value = match message {
             Message::Binary(bytes) => {
-                let mut decompressed =
-                    String::with_capacity(bytes.len() * DECOMPRESSION_MULTIPLIER);
-
-                ZlibDecoder::new(&bytes[..]).read_to_string(&mut decompressed).map_err(|why| {
+                let decompressed = self.compression.inflate(&bytes).map_err(|why| {
                     warn!("Err decompressing bytes: {why:?}");
                     debug!("Failing bytes: {bytes:?}");
 
                     why
                 })?;
-
-                from_str(&decompressed).map_err(|why| {
+                let Some(decompressed) = decompressed else {
+                    return Ok(None);
+                };
+
+                if let Some((recorder, shard_id)) = &self.recorder {
+                    match to_json_text(self.encoding, decompressed) {
+                        Ok(payload) => recorder.record(*shard_id, payload),
+                        Err(why) => warn!("Err converting payload for recording: {why:?}"),
+                    }
+                }
+
+                decode(self.encoding, decompressed).map_err(|why| {
                     warn!("Err deserializing bytes: {why:?}");
                     debug!("Failing bytes: {bytes:?}");
 
                     why
                 })?
             },
-            Message::Text(payload) => from_str(&payload).map_err(|why| {
-                warn!("Err deserializing text: {why:?}; text: {payload}");
+            Message::Text(payload) => {
+                if let Some((recorder, shard_id)) = &self.recorder {
+                    recorder.record(*shard_id, payload.clone());
+                }
+
+                from_str(&payload).map_err(|why| {
+                    warn!("Err deserializing text: {why:?}; text: {payload}");
 
-                why
-            })?,
+                    why
+                })?
+            },
             Message::Close(Some(frame)) => {
                 return Err(Error::Gateway(GatewayError::Closed(Some(frame))));
             },
@@ -153,30 +334,40 @@ impl WsClient {
         Ok(Some(value))
     }
 
+    /// Records every payload received from now on, as received by the given shard.
+    pub(crate) fn set_recorder(&mut self, recorder: Arc<GatewayRecorder>, shard_id: ShardId) {
+        self.recorder = Some((recorder, shard_id));
+    }
+
+    /// Sends a payload, encoded according to the connection's [`GatewayEncoding`].
     pub(crate) async fn send_json(&mut self, value: &impl serde::Serialize) -> Result<()> {
-        let message = to_string(value).map(Message::Text)?;
+        let message = match self.encoding {
+            GatewayEncoding::Json => to_string(value).map(Message::Text)?,
+            #[cfg(feature = "etf")]
+            GatewayEncoding::Etf => etf::to_vec(value).map(Message::Binary)?,
+        };
 
-        self.0.send(message).await?;
+        self.stream.send(message).await?;
         Ok(())
     }
 
     /// Delegate to `StreamExt::next`
     #[cfg(feature = "client")]
     pub(crate) async fn next(&mut self) -> Option<std::result::Result<Message, WsError>> {
-        self.0.next().await
+        self.stream.next().await
     }
 
     /// Delegate to `SinkExt::send`
     #[cfg(feature = "client")]
     pub(crate) async fn send(&mut self, message: Message) -> Result<()> {
-        self.0.send(message).await?;
+        self.stream.send(message).await?;
         Ok(())
     }
 
-    /// Delegate to `WebSocketStream::close`
+    /// Sends a close frame, as `WebSocketStream::close` does.
     #[cfg(feature = "client")]
-    pub(crate) async fn close(&mut self, msg: Option<CloseFrame<'_>>) -> Result<()> {
-        self.0.close(msg).await?;
+    pub(crate) async fn close(&mut self, msg: Option<CloseFrame<'static>>) -> Result<()> {
+        self.stream.send(Message::Close(msg)).await?;
         Ok(())
     }
 
@@ -242,7 +433,7 @@ impl WsClient {
                 token,
                 shard,
                 intents,
-                compress: true,
+                compress: self.compression.compress_payloads(),
                 large_threshold: constants::LARGE_THRESHOLD,
                 properties: IdentifyProperties {
                     browser: "serenity",
@@ -305,3 +496,106 @@ impl WsClient {
         .await
     }
 }
+
+/// Deserializes a decompressed binary message according to the connection's encoding.
+#[cfg(feature = "client")]
+fn decode(encoding: GatewayEncoding, payload: &[u8]) -> Result<GatewayEvent> {
+    match encoding {
+        GatewayEncoding::Json => {
+            let payload = std::str::from_utf8(payload)
+                .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))?;
+
+            from_str(payload)
+        },
+        #[cfg(feature = "etf")]
+        GatewayEncoding::Etf => Ok(etf::from_slice(payload)?),
+    }
+}
+
+/// Converts a decompressed binary message to JSON text, for recording.
+#[cfg(feature = "client")]
+fn to_json_text(encoding: GatewayEncoding, payload: &[u8]) -> Result<String> {
+    match encoding {
+        GatewayEncoding::Json => Ok(std::str::from_utf8(payload)
+            .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))?
+            .to_owned()),
+        #[cfg(feature = "etf")]
+        GatewayEncoding::Etf => to_string(&etf::from_slice::<crate::json::Value>(payload)?),
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use flate2::{Compress, Compression as Level, FlushCompress};
+
+    use super::{Compression, GatewayEncoding, TransportCompression};
+
+    /// Compresses each message as discord does for a zlib-stream connection, sharing one
+    /// compression context and ending every message with a sync flush.
+    fn compress_stream(messages: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut compress = Compress::new(Level::default(), true);
+
+        messages
+            .iter()
+            .map(|message| {
+                let mut out = Vec::with_capacity(message.len() * 2 + 64);
+                compress.compress_vec(message, &mut out, FlushCompress::Sync).unwrap();
+                out
+            })
+            .collect()
+    }
+
+    #[test]
+    fn zlib_stream_keeps_context_between_messages() {
+        let messages: [&[u8]; 3] = [br#"{"op":10}"#, br#"{"op":11}"#, br#"{"op":10}"#];
+        let mut compression = Compression::new(TransportCompression::Zlib, GatewayEncoding::Json);
+
+        for (message, frame) in messages.iter().zip(compress_stream(&messages)) {
+            assert_eq!(compression.inflate(&frame).unwrap(), Some(*message));
+        }
+    }
+
+    #[test]
+    fn zlib_stream_waits_for_sync_flush_suffix() {
+        let frames = compress_stream(&[br#"{"op":0,"d":{"content":"hello"}}"#]);
+        let (first, second) = frames[0].split_at(frames[0].len() / 2);
+        let mut compression = Compression::new(TransportCompression::Zlib, GatewayEncoding::Json);
+
+        assert_eq!(compression.inflate(first).unwrap(), None);
+        assert_eq!(
+            compression.inflate(second).unwrap(),
+            Some(&br#"{"op":0,"d":{"content":"hello"}}"#[..])
+        );
+    }
+
+    #[test]
+    fn zlib_stream_grows_buffer_for_large_messages() {
+        let large = "a".repeat(1 << 20);
+        let frames = compress_stream(&[b"{}", large.as_bytes()]);
+        let mut compression = Compression::new(TransportCompression::Zlib, GatewayEncoding::Json);
+
+        assert_eq!(compression.inflate(&frames[0]).unwrap(), Some(&b"{}"[..]));
+        assert_eq!(compression.inflate(&frames[1]).unwrap(), Some(large.as_bytes()));
+    }
+
+    #[test]
+    #[cfg(feature = "transport_compression_zstd")]
+    fn zstd_stream_keeps_context_between_messages() {
+        use zstd_safe::{CCtx, InBuffer, OutBuffer};
+
+        let large = "b".repeat(1 << 20);
+        let messages: [&[u8]; 3] = [br#"{"op":10}"#, large.as_bytes(), br#"{"op":11}"#];
+        let mut compressor = CCtx::create();
+        let mut compression = Compression::new(TransportCompression::Zstd, GatewayEncoding::Json);
+
+        for message in messages {
+            let mut frame = Vec::with_capacity(message.len() + 64);
+            let mut input = InBuffer::around(message);
+            let mut output = OutBuffer::around(&mut frame);
+            compressor.compress_stream(&mut output, &mut input).unwrap();
+            compressor.flush_stream(&mut output).unwrap();
+
+            assert_eq!(compression.inflate(&frame).unwrap(), Some(message));
+        }
+    }
+}
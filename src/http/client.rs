@@ -4,6 +4,7 @@ use std::borrow::Cow;
 use std::num::NonZeroU64;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::header::{HeaderMap as Headers, HeaderValue};
@@ -12,11 +13,16 @@ use reqwest::Url;
 use reqwest::{Client, ClientBuilder, Response as ReqwestResponse, StatusCode};
 use secrecy::{ExposeSecret, SecretString};
 use serde::de::DeserializeOwned;
+use tokio::time::sleep;
 use tracing::{debug, instrument, trace};
 
+use super::metrics::{MetricsRecorder, MetricsSnapshot};
+use super::middleware::{Middleware, ResponseInfo};
 use super::multipart::{Multipart, MultipartUpload};
-use super::ratelimiting::Ratelimiter;
+use super::options::RequestOptions;
+use super::ratelimiting::{RatelimitStore, Ratelimiter};
 use super::request::Request;
+use super::retry::{RetryInfo, RetryPolicy, RetryReason};
 use super::routing::Route;
 use super::typing::Typing;
 use super::{
@@ -53,6 +59,9 @@ pub struct HttpBuilder {
     client: Option<Client>,
     ratelimiter: Option<Ratelimiter>,
     ratelimiter_disabled: bool,
+    ratelimit_store: Option<Arc<dyn RatelimitStore>>,
+    retry_policy: RetryPolicy,
+    middleware: Vec<Arc<dyn Middleware>>,
     token: SecretString,
     proxy: Option<String>,
     application_id: Option<ApplicationId>,
@@ -67,6 +76,9 @@ impl HttpBuilder {
             client: None,
             ratelimiter: None,
             ratelimiter_disabled: false,
+            ratelimit_store: None,
+            retry_policy: RetryPolicy::default(),
+            middleware: Vec::new(),
             token: SecretString::new(parse_token(token)),
             proxy: None,
             application_id: None,
@@ -110,6 +122,39 @@ impl HttpBuilder {
         self
     }
 
+    /// Sets the [`RatelimitStore`] the ratelimiter keeps its state in. If one isn't provided, the
+    /// [`InMemoryRatelimitStore`] is used.
+    ///
+    /// Sharing a store such as the
+    #[cfg_attr(
+        feature = "ratelimit_daemon",
+        doc = "[`UnixRatelimitStore`](super::ratelimit_daemon::UnixRatelimitStore)"
+    )]
+    #[cfg_attr(not(feature = "ratelimit_daemon"), doc = "`UnixRatelimitStore`")]
+    /// lets several processes using the same token share their ratelimits, without routing their
+    /// requests through a proxy.
+    ///
+    /// [`InMemoryRatelimitStore`]: super::InMemoryRatelimitStore
+    pub fn ratelimit_store<S: RatelimitStore + 'static>(mut self, store: S) -> Self {
+        self.ratelimit_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] deciding which requests are retried after a transient failure,
+    /// such as a 502 from Discord. If one isn't provided, the default policy is used.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Adds a [`Middleware`] layer, which sees every request before it is sent and its response
+    /// after. Layers are called in the order they were added before sending a request, and in the
+    /// reverse order after.
+    pub fn middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
     /// Sets the proxy that Discord HTTP API requests will be passed to. This is mainly intended
     /// for something like [`twilight-http-proxy`] where multiple processes can make API requests
     /// while sharing a single ratelimiter.
@@ -120,7 +165,8 @@ impl HttpBuilder {
     /// This will simply send HTTP API requests to the proxy instead of Discord API to allow the
     /// proxy to intercept, rate limit, and forward requests. This is different than a native
     /// proxy's behavior where it will tunnel requests that use TLS via [`HTTP CONNECT`] method
-    /// (e.g. using [`reqwest::Proxy`]).
+    /// (e.g. using [`reqwest::Proxy`]). Requests that go through the [`Ratelimiter`] are only sent
+    /// to the proxy if it is set with [`Ratelimiter::set_proxy`] as well.
     ///
     /// [`twilight-http-proxy`]: https://github.com/twilight-rs/http-proxy
     /// [`HTTP CONNECT`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Methods/CONNECT
@@ -149,14 +195,23 @@ impl HttpBuilder {
         });
 
         let ratelimiter = (!self.ratelimiter_disabled).then(|| {
-            self.ratelimiter
-                .unwrap_or_else(|| Ratelimiter::new(client.clone(), self.token.expose_secret()))
+            let mut ratelimiter = self
+                .ratelimiter
+                .unwrap_or_else(|| Ratelimiter::new(client.clone(), self.token.expose_secret()));
+            if let Some(store) = self.ratelimit_store {
+                ratelimiter.set_store(store);
+            }
+            ratelimiter
         });
+        let metrics = ratelimiter.as_ref().map_or_else(Arc::default, |r| Arc::clone(&r.metrics));
 
         Http {
             client,
             ratelimiter,
             proxy: self.proxy,
+            retry_policy: self.retry_policy,
+            middleware: self.middleware,
+            metrics,
             token: self.token,
             application_id,
             default_allowed_mentions: self.default_allowed_mentions,
@@ -196,6 +251,9 @@ pub struct Http {
     pub(crate) client: Client,
     pub ratelimiter: Option<Ratelimiter>,
     pub proxy: Option<String>,
+    retry_policy: RetryPolicy,
+    middleware: Vec<Arc<dyn Middleware>>,
+    metrics: Arc<MetricsRecorder>,
     token: SecretString,
     application_id: AtomicU64,
     pub default_allowed_mentions: Option<CreateAllowedMentions>,
@@ -224,6 +282,16 @@ impl Http {
         self.token.expose_secret()
     }
 
+    /// Returns a snapshot of the metrics of the requests made by this client: the number of
+    /// requests and errors, the latency of Discord's responses and the time spent waiting for
+    /// ratelimits, for every route and [`LightMethod`].
+    ///
+    /// Call [`MetricsSnapshot::to_prometheus`] on it to serve the metrics to Prometheus.
+    #[must_use]
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
     /// Adds a [`User`] to a [`Guild`] with a valid OAuth2 access token.
     ///
     /// Returns the created [`Member`] object, or nothing if the user is already a guild member.
@@ -246,6 +314,7 @@ impl Http {
                     user_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -279,6 +348,7 @@ impl Http {
                 user_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -311,6 +381,7 @@ impl Http {
                 user_id,
             },
             params: Some(vec![("delete_message_seconds", delete_message_seconds.to_string())]),
+            options: None,
         })
         .await
     }
@@ -333,6 +404,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -354,10 +426,41 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
 
+    /// Requests slots to upload files to before sending them in a channel, each with the `id` of
+    /// the file of the given map it is for. Prefer [`ChannelId::upload_attachments`], which also
+    /// uploads the files.
+    pub async fn create_attachment_uploads(
+        &self,
+        channel_id: ChannelId,
+        map: &impl serde::Serialize,
+    ) -> Result<Vec<AttachmentUpload>> {
+        #[derive(Deserialize)]
+        struct AttachmentUploads {
+            attachments: Vec<AttachmentUpload>,
+        }
+
+        let uploads: AttachmentUploads = self
+            .fire(Request {
+                body: Some(to_vec(map)?),
+                multipart: None,
+                headers: None,
+                method: LightMethod::Post,
+                route: Route::ChannelAttachments {
+                    channel_id,
+                },
+                params: None,
+                options: None,
+            })
+            .await?;
+
+        Ok(uploads.attachments)
+    }
+
     /// Creates a [`GuildChannel`] in the [`Guild`] given its Id.
     ///
     /// Refer to the Discord's [docs] for information on what fields this requires.
@@ -383,6 +486,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -400,6 +504,7 @@ impl Http {
             method: LightMethod::Post,
             route: Route::StageInstances,
             params: None,
+            options: None,
         })
         .await
     }
@@ -424,6 +529,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -446,6 +552,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -481,6 +588,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -507,6 +615,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -530,6 +639,7 @@ impl Http {
                 token: interaction_token,
             },
             params: None,
+            options: None,
         };
 
         if files.is_empty() {
@@ -565,6 +675,7 @@ impl Http {
                 application_id: self.try_application_id()?,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -583,6 +694,7 @@ impl Http {
                 application_id: self.try_application_id()?,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -603,6 +715,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -648,6 +761,7 @@ impl Http {
             method: LightMethod::Post,
             route: Route::Guilds,
             params: None,
+            options: None,
         })
         .await
     }
@@ -674,6 +788,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -703,6 +818,7 @@ impl Http {
                 integration_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -730,6 +846,7 @@ impl Http {
                 token: interaction_token,
             },
             params: None,
+            options: None,
         };
 
         if files.is_empty() {
@@ -772,6 +889,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -796,6 +914,7 @@ impl Http {
                 target_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -811,6 +930,7 @@ impl Http {
             method: LightMethod::Post,
             route: Route::UserMeDmChannels,
             params: None,
+            options: None,
         })
         .await
     }
@@ -833,6 +953,7 @@ impl Http {
                 reaction: &reaction_type.as_data(),
             },
             params: Some(vec![("burst", burst.to_string())]),
+            options: None,
         })
         .await
     }
@@ -874,6 +995,7 @@ impl Http {
                     guild_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -907,6 +1029,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -936,6 +1059,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -966,6 +1090,7 @@ impl Http {
                 application_id: self.try_application_id()?,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1013,6 +1138,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1032,6 +1158,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1051,6 +1178,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1074,6 +1202,7 @@ impl Http {
                 emoji_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1095,6 +1224,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1111,6 +1241,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1126,6 +1257,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1147,6 +1279,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1168,6 +1301,7 @@ impl Http {
                 integration_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1187,6 +1321,7 @@ impl Http {
                 code,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1208,6 +1343,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1228,6 +1364,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1265,6 +1402,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1287,6 +1425,7 @@ impl Http {
                 reaction: &reaction_type.as_data(),
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1306,6 +1445,7 @@ impl Http {
                 token: interaction_token,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1327,6 +1467,7 @@ impl Http {
                 target_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1351,6 +1492,7 @@ impl Http {
                 reaction: &reaction_type.as_data(),
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1373,6 +1515,7 @@ impl Http {
                 reaction: &reaction_type.as_data(),
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1394,6 +1537,7 @@ impl Http {
                 role_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1419,6 +1563,7 @@ impl Http {
                 event_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1442,6 +1587,7 @@ impl Http {
                 sticker_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1459,6 +1605,7 @@ impl Http {
                 entitlement_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1496,6 +1643,7 @@ impl Http {
                 webhook_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1537,6 +1685,7 @@ impl Http {
                 token,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1559,6 +1708,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1579,6 +1729,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1605,6 +1756,7 @@ impl Http {
                 emoji_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1632,6 +1784,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         };
 
         if new_attachments.is_empty() {
@@ -1668,6 +1821,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1694,6 +1848,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1716,6 +1871,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1744,6 +1900,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1772,6 +1929,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1793,6 +1951,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1820,6 +1979,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
         .map(|mfa: GuildMfaLevel| mfa.level)
@@ -1843,6 +2003,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1865,6 +2026,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1890,6 +2052,7 @@ impl Http {
                     user_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -1920,6 +2083,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         };
 
         if new_attachments.is_empty() {
@@ -1953,6 +2117,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -1975,6 +2140,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2000,6 +2166,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2022,6 +2189,7 @@ impl Http {
                 channel_id: news_channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2041,6 +2209,7 @@ impl Http {
                 token: interaction_token,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2066,6 +2235,7 @@ impl Http {
                 token: interaction_token,
             },
             params: None,
+            options: None,
         };
 
         if new_attachments.is_empty() {
@@ -2092,6 +2262,7 @@ impl Http {
             method: LightMethod::Patch,
             route: Route::UserMe,
             params: None,
+            options: None,
         })
         .await
     }
@@ -2115,6 +2286,7 @@ impl Http {
                     role_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -2149,6 +2321,7 @@ impl Http {
                     guild_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -2186,6 +2359,7 @@ impl Http {
                 event_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2213,6 +2387,7 @@ impl Http {
                     sticker_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -2239,6 +2414,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2289,6 +2465,7 @@ impl Http {
                 user_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2340,6 +2517,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2391,6 +2569,7 @@ impl Http {
                 webhook_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2439,6 +2618,7 @@ impl Http {
                 token,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2517,6 +2697,7 @@ impl Http {
                 token,
             },
             params: Some(params),
+            options: None,
         };
 
         if files.is_empty() {
@@ -2557,6 +2738,7 @@ impl Http {
                 message_id,
             },
             params: thread_id.map(|thread_id| vec![("thread_id", thread_id.to_string())]),
+            options: None,
         })
         .await
     }
@@ -2582,6 +2764,7 @@ impl Http {
                 message_id,
             },
             params: thread_id.map(|thread_id| vec![("thread_id", thread_id.to_string())]),
+            options: None,
         };
 
         if new_attachments.is_empty() {
@@ -2616,6 +2799,7 @@ impl Http {
                 message_id,
             },
             params: thread_id.map(|thread_id| vec![("thread_id", thread_id.to_string())]),
+            options: None,
         })
         .await
     }
@@ -2638,6 +2822,7 @@ impl Http {
                 method: LightMethod::Get,
                 route: Route::StatusMaintenancesActive,
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -2682,6 +2867,7 @@ impl Http {
                 guild_id,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -2718,6 +2904,7 @@ impl Http {
                 guild_id,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -2735,6 +2922,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2753,6 +2941,7 @@ impl Http {
                 rule_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2777,6 +2966,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2803,6 +2993,7 @@ impl Http {
                 rule_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2826,6 +3017,7 @@ impl Http {
                 rule_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2839,6 +3031,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::GatewayBot,
             params: None,
+            options: None,
         })
         .await
     }
@@ -2854,6 +3047,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2872,6 +3066,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2887,6 +3082,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -2915,6 +3111,7 @@ impl Http {
                 channel_id,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -2943,6 +3140,7 @@ impl Http {
                 channel_id,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -2971,6 +3169,7 @@ impl Http {
                 channel_id,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -2986,6 +3185,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3001,6 +3201,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3021,6 +3222,7 @@ impl Http {
                 user_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3041,6 +3243,7 @@ impl Http {
                 user_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3061,6 +3264,7 @@ impl Http {
                 user_id,
             },
             params: Some(vec![("with_member", with_member.to_string())]),
+            options: None,
         })
         .await
     }
@@ -3095,6 +3299,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3110,6 +3315,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3125,6 +3331,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3140,6 +3347,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3179,6 +3387,7 @@ impl Http {
                     answer_id,
                 },
                 params: Some(params),
+                options: None,
             })
             .await?;
 
@@ -3200,6 +3409,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3215,6 +3425,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::Oauth2ApplicationCurrent,
             params: None,
+            options: None,
         })
         .await
     }
@@ -3228,6 +3439,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::UserMe,
             params: None,
+            options: None,
         })
         .await
     }
@@ -3243,6 +3455,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3259,6 +3472,7 @@ impl Http {
                 emoji_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3310,6 +3524,7 @@ impl Http {
                 application_id: self.try_application_id()?,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -3323,6 +3538,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::Gateway,
             params: None,
+            options: None,
         })
         .await
     }
@@ -3338,6 +3554,7 @@ impl Http {
                 application_id: self.try_application_id()?,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3353,6 +3570,7 @@ impl Http {
                 application_id: self.try_application_id()?,
             },
             params: Some(vec![("with_localizations", true.to_string())]),
+            options: None,
         })
         .await
     }
@@ -3369,6 +3587,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3384,6 +3603,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3399,6 +3619,7 @@ impl Http {
                 guild_id,
             },
             params: Some(vec![("with_counts", true.to_string())]),
+            options: None,
         })
         .await
     }
@@ -3415,6 +3636,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3435,6 +3657,7 @@ impl Http {
                 guild_id,
             },
             params: Some(vec![("with_localizations", true.to_string())]),
+            options: None,
         })
         .await
     }
@@ -3456,6 +3679,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3475,6 +3699,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3496,6 +3721,7 @@ impl Http {
                 command_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3513,6 +3739,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3528,6 +3755,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3543,6 +3771,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3558,6 +3787,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3573,6 +3803,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3593,6 +3824,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
         .map(|x| x.code)
@@ -3628,6 +3860,7 @@ impl Http {
                     guild_id,
                 },
                 params: Some(params),
+                options: None,
             })
             .await?;
 
@@ -3653,6 +3886,7 @@ impl Http {
                 guild_id,
             },
             params: Some(vec![("days", days.to_string())]),
+            options: None,
         })
         .await
     }
@@ -3669,6 +3903,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3685,6 +3920,7 @@ impl Http {
                     guild_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -3720,6 +3956,7 @@ impl Http {
                 event_id,
             },
             params: Some(vec![("with_user_count", with_user_count.to_string())]),
+            options: None,
         })
         .await
     }
@@ -3743,6 +3980,7 @@ impl Http {
                 guild_id,
             },
             params: Some(vec![("with_user_count", with_user_count.to_string())]),
+            options: None,
         })
         .await
     }
@@ -3795,6 +4033,7 @@ impl Http {
                 event_id,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -3811,6 +4050,7 @@ impl Http {
                     guild_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -3842,6 +4082,7 @@ impl Http {
                     sticker_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -3882,6 +4123,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -3935,6 +4177,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::UserMeGuilds,
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -3979,6 +4222,7 @@ impl Http {
                     guild_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -4027,6 +4271,7 @@ impl Http {
                 code,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -4044,6 +4289,7 @@ impl Http {
                     user_id,
                 },
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -4070,6 +4316,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4102,6 +4349,7 @@ impl Http {
                 channel_id,
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -4120,6 +4368,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::StickerPacks,
             params: None,
+            options: None,
         })
         .await
         .map(|s| s.sticker_packs)
@@ -4136,6 +4385,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4164,6 +4414,7 @@ impl Http {
                 reaction: &reaction_type.as_data(),
             },
             params: Some(params),
+            options: None,
         })
         .await
     }
@@ -4179,6 +4430,7 @@ impl Http {
                 application_id: self.try_application_id()?,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4194,6 +4446,7 @@ impl Http {
                 sticker_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4216,6 +4469,7 @@ impl Http {
                 method: LightMethod::Get,
                 route: Route::StatusIncidentsUnresolved,
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -4240,6 +4494,7 @@ impl Http {
                 method: LightMethod::Get,
                 route: Route::StatusMaintenancesUpcoming,
                 params: None,
+                options: None,
             })
             .await?;
 
@@ -4257,6 +4512,7 @@ impl Http {
                 user_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4274,6 +4530,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::UserMeConnections,
             params: None,
+            options: None,
         })
         .await
     }
@@ -4287,6 +4544,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::UserMeDmChannels,
             params: None,
+            options: None,
         })
         .await
     }
@@ -4300,6 +4558,7 @@ impl Http {
             method: LightMethod::Get,
             route: Route::VoiceRegions,
             params: None,
+            options: None,
         })
         .await
     }
@@ -4334,6 +4593,7 @@ impl Http {
                 webhook_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4374,6 +4634,7 @@ impl Http {
                 token,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4411,6 +4672,7 @@ impl Http {
                 token,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4432,6 +4694,7 @@ impl Http {
                 user_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4447,6 +4710,7 @@ impl Http {
                 guild_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4471,6 +4735,7 @@ impl Http {
                 channel_id,
             },
             params: None,
+            options: None,
         };
 
         if files.is_empty() {
@@ -4486,6 +4751,43 @@ impl Http {
         self.fire(request).await
     }
 
+    /// Uploads the data of an attachment to a slot returned by
+    /// [`Self::create_attachment_uploads`].
+    ///
+    /// The data is sent straight to Discord's storage rather than to the API, so the upload is not
+    /// ratelimited and doesn't go through the middleware. The deadline and timeouts of the
+    /// [`RequestOptions::scope`] it is made in still apply.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`HttpError::UnsuccessfulRequest`] if the storage rejects the upload, for
+    /// example because the slot expired.
+    pub async fn upload_attachment(
+        &self,
+        upload: &AttachmentUpload,
+        attachment: CreateAttachment,
+    ) -> Result<()> {
+        let options = RequestOptions::resolve(None);
+        let (body, length) = attachment.into_body();
+
+        let mut request = self
+            .client
+            .put(&upload.upload_url)
+            .header(reqwest::header::CONTENT_LENGTH, length)
+            .body(body)
+            .build()?;
+        options.apply(&mut request);
+
+        let response = options.run(async { Ok(self.client.execute(request).await?) }).await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::Http(HttpError::UnsuccessfulRequest(
+                ErrorResponse::from_response(response, reqwest::Method::PUT).await,
+            )))
+        }
+    }
+
     /// Pins a message in a channel.
     pub async fn pin_message(
         &self,
@@ -4503,6 +4805,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4524,6 +4827,7 @@ impl Http {
                 user_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4551,6 +4855,7 @@ impl Http {
                 role_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4576,6 +4881,7 @@ impl Http {
                     ("query", query.to_string()),
                     ("limit", limit.unwrap_or(constants::MEMBER_FETCH_LIMIT).to_string()),
                 ]),
+                options: None,
             })
             .await?;
 
@@ -4606,6 +4912,7 @@ impl Http {
                 guild_id,
             },
             params: Some(vec![("days", days.to_string())]),
+            options: None,
         })
         .await
     }
@@ -4626,6 +4933,7 @@ impl Http {
                 integration_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4687,6 +4995,7 @@ impl Http {
                 message_id,
             },
             params: None,
+            options: None,
         })
         .await
     }
@@ -4738,6 +5047,9 @@ impl Http {
     /// Returns the raw reqwest Response. Use [`Self::fire`] to deserialize the response into some
     /// type.
     ///
+    /// The deadline and timeouts of the request are set with [`Request::options`] or
+    /// [`RequestOptions::scope`].
+    ///
     /// # Examples
     ///
     /// Send a body of bytes over the create message endpoint:
@@ -4767,12 +5079,32 @@ impl Http {
     #[instrument]
     pub async fn request(&self, req: Request<'_>) -> Result<ReqwestResponse> {
         let method = req.method.reqwest_method();
-        let response = if let Some(ratelimiter) = &self.ratelimiter {
-            ratelimiter.perform(req).await?
-        } else {
-            let request = req.build(&self.client, self.token(), self.proxy.as_deref())?.build()?;
-            self.client.execute(request).await?
-        };
+        let options = RequestOptions::resolve(req.options);
+        let response = options
+            .run(async {
+                let mut attempt = 1;
+                loop {
+                    let result = self.perform(req.clone()).await;
+
+                    let Some(reason) = RetryReason::from_result(&result) else { return result };
+                    let Some(delay) = self.retry_policy.delay(req.method, attempt) else {
+                        return result;
+                    };
+
+                    debug!("Retrying request after {:?} in {:?}", reason, delay);
+                    self.retry_policy.report(RetryInfo {
+                        attempt,
+                        delay,
+                        reason,
+                        method: req.method,
+                        path: req.route.path().to_string(),
+                    });
+
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+            })
+            .await?;
 
         if response.status().is_success() {
             Ok(response)
@@ -4783,6 +5115,48 @@ impl Http {
         }
     }
 
+    /// Makes a single attempt at a request, passing it through the middleware.
+    async fn perform(&self, mut req: Request<'_>) -> Result<ReqwestResponse> {
+        for middleware in &self.middleware {
+            middleware.before(&mut req).await?;
+        }
+
+        let (route, method) = (req.route, req.method);
+        let start = Instant::now();
+        let result = self.send(req).await;
+
+        let info = ResponseInfo {
+            route: &route,
+            method,
+            result: &result,
+            elapsed: start.elapsed(),
+        };
+        for middleware in self.middleware.iter().rev() {
+            middleware.after(&info).await;
+        }
+
+        result
+    }
+
+    /// Sends a request, through the ratelimiter if there is one.
+    async fn send(&self, req: Request<'_>) -> Result<ReqwestResponse> {
+        if let Some(ratelimiter) = &self.ratelimiter {
+            ratelimiter.perform(req).await
+        } else {
+            let (bucket, method) = (req.route.ratelimiting_bucket(), req.method);
+            let options = RequestOptions::resolve(req.options);
+            let mut request =
+                req.build(&self.client, self.token(), self.proxy.as_deref())?.build()?;
+            options.apply(&mut request);
+
+            let start = Instant::now();
+            let result = self.client.execute(request).await;
+            let status = result.as_ref().ok().map(ReqwestResponse::status);
+            self.metrics.record_response(&bucket, method, status, start.elapsed());
+            Ok(result?)
+        }
+    }
+
     /// Performs a request and then verifies that the response status code is equal to the expected
     /// value.
     ///
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::error::Error as StdError;
 use std::fmt;
 
@@ -6,6 +7,7 @@ use reqwest::{Error as ReqwestError, Method, Response, StatusCode};
 use serde::de::{Deserialize, Deserializer, Error as _};
 use url::ParseError as UrlError;
 
+use super::{DiscordJsonErrorCode, RatelimitInfo};
 use crate::internal::prelude::*;
 use crate::json::*;
 
@@ -21,6 +23,45 @@ pub struct DiscordJsonError {
     pub errors: Vec<DiscordJsonSingleError>,
 }
 
+impl DiscordJsonError {
+    /// Returns the error code as a [`DiscordJsonErrorCode`].
+    #[must_use]
+    pub fn error_code(&self) -> DiscordJsonErrorCode {
+        self.code.into()
+    }
+
+    /// Returns the [`errors`] as a tree following the structure of the request body.
+    ///
+    /// # Examples
+    ///
+    /// Find out what was wrong with the title of the first embed of a message:
+    ///
+    /// ```rust,no_run
+    /// # use serenity::http::DiscordJsonError;
+    /// # fn run(error: &DiscordJsonError) {
+    /// if let Some(title) = error.error_tree().get("embeds.0.title") {
+    ///     for error in &title.errors {
+    ///         println!("{}: {}", error.code, error.message);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`errors`]: Self::errors
+    #[must_use]
+    pub fn error_tree(&self) -> DiscordJsonErrorTree {
+        let mut tree = DiscordJsonErrorTree::default();
+        for error in &self.errors {
+            let node = error
+                .path_segments()
+                .into_iter()
+                .fold(&mut tree, |node, segment| node.children.entry(segment).or_default());
+            node.errors.push(error.clone());
+        }
+        tree
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct DiscordJsonSingleError {
     /// The error code.
@@ -31,6 +72,80 @@ pub struct DiscordJsonSingleError {
     pub path: String,
 }
 
+impl DiscordJsonSingleError {
+    /// Returns the segments of the [`path`], with array indices parsed as such.
+    ///
+    /// [`path`]: Self::path
+    #[must_use]
+    pub fn path_segments(&self) -> Vec<ErrorPathSegment> {
+        if self.path.is_empty() {
+            return vec![];
+        }
+
+        self.path.split('.').map(ErrorPathSegment::parse).collect()
+    }
+}
+
+/// A segment of the path to a field of a request body, see
+/// [`DiscordJsonSingleError::path_segments`].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum ErrorPathSegment {
+    /// The name of a field of an object.
+    Field(String),
+    /// The index of an element of an array.
+    Index(usize),
+}
+
+impl ErrorPathSegment {
+    fn parse(segment: &str) -> Self {
+        match segment.parse() {
+            Ok(index) => Self::Index(index),
+            Err(_) => Self::Field(segment.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for ErrorPathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field(field) => f.write_str(field),
+            Self::Index(index) => fmt::Display::fmt(index, f),
+        }
+    }
+}
+
+/// The errors of a request body arranged by field, as returned by
+/// [`DiscordJsonError::error_tree`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DiscordJsonErrorTree {
+    /// The errors about this field itself.
+    pub errors: Vec<DiscordJsonSingleError>,
+    /// The errors about the fields nested in this one.
+    pub children: BTreeMap<ErrorPathSegment, DiscordJsonErrorTree>,
+}
+
+impl DiscordJsonErrorTree {
+    /// Returns the errors about the field at the given dot separated path, such as
+    /// `embeds.0.title`, if there are any.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&Self> {
+        if path.is_empty() {
+            return Some(self);
+        }
+
+        path.split('.')
+            .try_fold(self, |node, segment| node.children.get(&ErrorPathSegment::parse(segment)))
+    }
+
+    /// Returns true if there are no errors about this field nor any field nested in it.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.children.values().all(Self::is_empty)
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive]
 pub struct ErrorResponse {
@@ -80,6 +195,19 @@ pub enum HttpError {
     InvalidPort,
     /// When an application id was expected but missing.
     ApplicationIdMissing,
+    /// When a request was ratelimited while failing fast, see [`RequestOptions::fail_fast`].
+    ///
+    /// [`RequestOptions::fail_fast`]: super::RequestOptions::fail_fast
+    Ratelimited(RatelimitInfo),
+    /// When a request was not over by its deadline, see [`RequestOptions::deadline`].
+    ///
+    /// [`RequestOptions::deadline`]: super::RequestOptions::deadline
+    DeadlineExceeded,
+    /// When Discord returned no upload slot for the file of the given name, see
+    /// [`ChannelId::upload_attachments`].
+    ///
+    /// [`ChannelId::upload_attachments`]: crate::model::id::ChannelId::upload_attachments
+    MissingAttachmentUpload(String),
 }
 
 impl HttpError {
@@ -109,6 +237,49 @@ impl HttpError {
             _ => None,
         }
     }
+
+    /// Returns the JSON error code sent by Discord if the error is an unsuccessful request
+    #[must_use]
+    pub fn discord_error_code(&self) -> Option<DiscordJsonErrorCode> {
+        match self {
+            Self::UnsuccessfulRequest(res) => Some(res.error.error_code()),
+            _ => None,
+        }
+    }
+
+    /// Returns true when the bot lacks the permissions to perform the request
+    #[must_use]
+    pub fn is_missing_permissions(&self) -> bool {
+        self.discord_error_code() == Some(DiscordJsonErrorCode::MissingPermissions)
+    }
+
+    /// Returns true when the bot cannot see the resource of the request, such as a channel it
+    /// has no access to
+    #[must_use]
+    pub fn is_missing_access(&self) -> bool {
+        self.discord_error_code() == Some(DiscordJsonErrorCode::MissingAccess)
+    }
+
+    /// Returns true when the resource of the request doesn't exist, see
+    /// [`DiscordJsonErrorCode::is_unknown_resource`]
+    #[must_use]
+    pub fn is_unknown_resource(&self) -> bool {
+        self.discord_error_code().is_some_and(DiscordJsonErrorCode::is_unknown_resource)
+    }
+
+    /// Returns true when a message could not be sent to a user, usually because they don't
+    /// accept direct messages
+    #[must_use]
+    pub fn is_cannot_message_user(&self) -> bool {
+        self.discord_error_code() == Some(DiscordJsonErrorCode::CannotMessageUser)
+    }
+
+    /// Returns true when the request body was rejected, in which case the reasons can be found in
+    /// [`DiscordJsonError::error_tree`]
+    #[must_use]
+    pub fn is_invalid_form_body(&self) -> bool {
+        self.discord_error_code() == Some(DiscordJsonErrorCode::InvalidFormBody)
+    }
 }
 
 impl From<ErrorResponse> for HttpError {
@@ -168,6 +339,13 @@ impl fmt::Display for HttpError {
             Self::InvalidScheme => f.write_str("Invalid Url scheme."),
             Self::InvalidPort => f.write_str("Invalid port."),
             Self::ApplicationIdMissing => f.write_str("Application id was expected but missing."),
+            Self::Ratelimited(info) => {
+                write!(f, "Request would have been ratelimited for {:?}.", info.timeout)
+            },
+            Self::DeadlineExceeded => f.write_str("Request was not over by its deadline."),
+            Self::MissingAttachmentUpload(filename) => {
+                write!(f, "No upload slot was returned for file {filename}.")
+            },
         }
     }
 }
@@ -278,4 +456,42 @@ mod test {
 
         assert_eq!(error_response, known);
     }
+
+    #[test]
+    fn test_typed_error_code_and_tree() {
+        let error: DiscordJsonError = from_str(
+            r#"{
+                "code": 50035,
+                "message": "Invalid Form Body",
+                "errors": {
+                    "content": {"_errors": [{"code": "BASE_TYPE_MAX_LENGTH", "message": "Too long"}]},
+                    "embeds": {"0": {"title": {"_errors": [
+                        {"code": "BASE_TYPE_REQUIRED", "message": "This field is required"}
+                    ]}}}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(error.error_code(), DiscordJsonErrorCode::InvalidFormBody);
+        assert_eq!(DiscordJsonErrorCode::from(12345), DiscordJsonErrorCode::Unknown(12345));
+        assert!(DiscordJsonErrorCode::UnknownChannel.is_unknown_resource());
+
+        let tree = error.error_tree();
+        let title = tree.get("embeds.0.title").unwrap();
+        assert_eq!(title.errors[0].code, "BASE_TYPE_REQUIRED");
+        assert!(tree.children.contains_key(&ErrorPathSegment::Field("content".into())));
+        assert!(tree.get("embeds").unwrap().children.contains_key(&ErrorPathSegment::Index(0)));
+        assert!(tree.get("embeds.1").is_none());
+
+        let http_error = HttpError::UnsuccessfulRequest(ErrorResponse {
+            status_code: StatusCode::BAD_REQUEST,
+            url: String::new(),
+            method: Method::POST,
+            error,
+        });
+        assert!(http_error.is_invalid_form_body());
+        assert!(!http_error.is_missing_permissions());
+        assert!(!http_error.is_unknown_resource());
+    }
 }
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{LightMethod, RatelimitingBucket, StatusCode};
+
+/// The upper bounds of the buckets of a [`LatencyHistogram`].
+const LATENCY_BOUNDS: [Duration; 9] = [
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+/// The distribution of the time Discord took to answer requests.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    /// The number of requests in each of the [`LATENCY_BOUNDS`], and then above the last one.
+    counts: [u64; LATENCY_BOUNDS.len() + 1],
+    sum: Duration,
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        let index = LATENCY_BOUNDS.iter().position(|bound| latency <= *bound);
+        self.counts[index.unwrap_or(LATENCY_BOUNDS.len())] += 1;
+        self.sum += latency;
+    }
+
+    /// The upper bounds of the buckets of the histogram, from 25 milliseconds to 10 seconds.
+    #[must_use]
+    pub fn bounds(&self) -> &'static [Duration] {
+        &LATENCY_BOUNDS
+    }
+
+    /// The number of requests that took at most the bound of the same index, and more than the
+    /// previous bound. The last count is for requests that took longer than every bound.
+    #[must_use]
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// The number of requests recorded.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The total time taken by the recorded requests.
+    #[must_use]
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+}
+
+/// The metrics of the requests made with one method to one route, across all of its major
+/// parameters.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RouteMetrics {
+    /// The name of the route, as returned by [`RatelimitingBucket::route`]. Routes without
+    /// ratelimits are grouped together under [`None`].
+    pub route: Option<&'static str>,
+    pub method: LightMethod,
+    /// The number of requests sent to Discord, counting every retry.
+    pub requests: u64,
+    /// The number of responses with a 4xx status other than 429.
+    pub client_errors: u64,
+    /// The number of responses with a 5xx status.
+    pub server_errors: u64,
+    /// The number of responses with a 429 status.
+    pub ratelimited: u64,
+    /// The number of requests that got no response, such as after a timeout.
+    pub failures: u64,
+    /// How long Discord took to answer, not counting time spent waiting for ratelimits.
+    pub latency: LatencyHistogram,
+    /// The number of times a request waited for a ratelimit before being sent.
+    pub ratelimit_waits: u64,
+    /// The total time requests waited for ratelimits before being sent.
+    pub ratelimit_wait_time: Duration,
+}
+
+impl RouteMetrics {
+    fn new(route: Option<&'static str>, method: LightMethod) -> Self {
+        Self {
+            route,
+            method,
+            requests: 0,
+            client_errors: 0,
+            server_errors: 0,
+            ratelimited: 0,
+            failures: 0,
+            latency: LatencyHistogram::default(),
+            ratelimit_waits: 0,
+            ratelimit_wait_time: Duration::ZERO,
+        }
+    }
+}
+
+/// A snapshot of the metrics of an [`Http`] client, as returned by [`Http::metrics`].
+///
+/// # Examples
+///
+/// Find the routes that spent the most time waiting for ratelimits:
+///
+/// ```rust,no_run
+/// # use serenity::http::Http;
+/// # fn run(http: &Http) {
+/// let mut routes = http.metrics().routes;
+/// routes.sort_by_key(|route| std::cmp::Reverse(route.ratelimit_wait_time));
+///
+/// for route in routes.iter().take(5) {
+///     println!("{:?} {:?}: waited {:?}", route.method, route.route, route.ratelimit_wait_time);
+/// }
+/// # }
+/// ```
+///
+/// [`Http`]: super::Http
+/// [`Http::metrics`]: super::Http::metrics
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct MetricsSnapshot {
+    /// The metrics of every route and method a request was made with, in no particular order.
+    pub routes: Vec<RouteMetrics>,
+    /// The number of times the global ratelimit was hit.
+    pub global_ratelimits: u64,
+}
+
+impl MetricsSnapshot {
+    /// Formats the metrics in the Prometheus text exposition format, to be served on a metrics
+    /// endpoint.
+    ///
+    /// Every metric is labelled with the `route` and `method` it was recorded for, and named with
+    /// the `serenity_http_` prefix.
+    #[must_use]
+    pub fn to_prometheus(&self) -> String {
+        type Counter = fn(&RouteMetrics) -> u64;
+        let counters: [(&str, &str, Counter); 6] = [
+            ("requests_total", "Requests sent to Discord.", |r| r.requests),
+            ("client_errors_total", "Responses with a 4xx status other than 429.", |r| {
+                r.client_errors
+            }),
+            ("server_errors_total", "Responses with a 5xx status.", |r| r.server_errors),
+            ("ratelimited_total", "Responses with a 429 status.", |r| r.ratelimited),
+            ("failures_total", "Requests that got no response.", |r| r.failures),
+            ("ratelimit_waits_total", "Times a request waited for a ratelimit.", |r| {
+                r.ratelimit_waits
+            }),
+        ];
+
+        let mut out = String::new();
+        for (name, help, value) in counters {
+            header(&mut out, name, help, "counter");
+            for route in &self.routes {
+                writeln!(out, "serenity_http_{name}{{{}}} {}", labels(route), value(route)).ok();
+            }
+        }
+
+        header(
+            &mut out,
+            "ratelimit_wait_seconds_total",
+            "Time requests waited for ratelimits.",
+            "counter",
+        );
+        for route in &self.routes {
+            let seconds = route.ratelimit_wait_time.as_secs_f64();
+            writeln!(
+                out,
+                "serenity_http_ratelimit_wait_seconds_total{{{}}} {seconds}",
+                labels(route)
+            )
+            .ok();
+        }
+
+        header(
+            &mut out,
+            "request_duration_seconds",
+            "Time taken by Discord to answer requests.",
+            "histogram",
+        );
+        for route in &self.routes {
+            let labels = labels(route);
+            let histogram = &route.latency;
+
+            let mut cumulative = 0;
+            for (bound, count) in histogram.bounds().iter().zip(histogram.counts()) {
+                cumulative += count;
+                let le = bound.as_secs_f64();
+                writeln!(
+                    out,
+                    "serenity_http_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} \
+                     {cumulative}"
+                )
+                .ok();
+            }
+
+            let (count, sum) = (histogram.count(), histogram.sum().as_secs_f64());
+            writeln!(
+                out,
+                "serenity_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {count}"
+            )
+            .ok();
+            writeln!(out, "serenity_http_request_duration_seconds_sum{{{labels}}} {sum}").ok();
+            writeln!(out, "serenity_http_request_duration_seconds_count{{{labels}}} {count}").ok();
+        }
+
+        header(
+            &mut out,
+            "global_ratelimits_total",
+            "Times the global ratelimit was hit.",
+            "counter",
+        );
+        writeln!(out, "serenity_http_global_ratelimits_total {}", self.global_ratelimits).ok();
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP serenity_http_{name} {help}").ok();
+    writeln!(out, "# TYPE serenity_http_{name} {kind}").ok();
+}
+
+fn labels(route: &RouteMetrics) -> String {
+    let name = route.route.unwrap_or("None").replace('\\', "\\\\").replace('"', "\\\"");
+    format!("route=\"{name}\",method=\"{}\"", route.method.reqwest_method())
+}
+
+/// Collects the metrics of the requests made by an [`Http`] client and its [`Ratelimiter`].
+///
+/// [`Http`]: super::Http
+/// [`Ratelimiter`]: super::Ratelimiter
+#[derive(Debug, Default)]
+pub(super) struct MetricsRecorder {
+    /// Keyed by route name rather than bucket, so that the number of entries stays bounded by the
+    /// number of routes however many guilds and channels requests are made for.
+    routes: Mutex<HashMap<(Option<&'static str>, LightMethod), RouteMetrics>>,
+    global_ratelimits: AtomicU64,
+}
+
+impl MetricsRecorder {
+    fn with_route(
+        &self,
+        bucket: &RatelimitingBucket,
+        method: LightMethod,
+        update: impl FnOnce(&mut RouteMetrics),
+    ) {
+        let name = bucket.route();
+        let mut routes = self.routes.lock().expect("poisoned HTTP metrics");
+        let route = routes.entry((name, method)).or_insert_with(|| RouteMetrics::new(name, method));
+        update(route);
+    }
+
+    /// Records a request sent to Discord, with the status of its response if it got one.
+    pub fn record_response(
+        &self,
+        bucket: &RatelimitingBucket,
+        method: LightMethod,
+        status: Option<StatusCode>,
+        latency: Duration,
+    ) {
+        self.with_route(bucket, method, |route| {
+            route.requests += 1;
+            route.latency.record(latency);
+
+            match status {
+                Some(StatusCode::TOO_MANY_REQUESTS) => route.ratelimited += 1,
+                Some(status) if status.is_client_error() => route.client_errors += 1,
+                Some(status) if status.is_server_error() => route.server_errors += 1,
+                Some(_) => {},
+                None => route.failures += 1,
+            }
+        });
+    }
+
+    /// Records a request waiting for a ratelimit before being sent.
+    pub fn record_wait(&self, bucket: &RatelimitingBucket, method: LightMethod, delay: Duration) {
+        self.with_route(bucket, method, |route| {
+            route.ratelimit_waits += 1;
+            route.ratelimit_wait_time += delay;
+        });
+    }
+
+    pub fn record_global_ratelimit(&self) {
+        self.global_ratelimits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let routes = self.routes.lock().expect("poisoned HTTP metrics");
+
+        MetricsSnapshot {
+            routes: routes.values().cloned().collect(),
+            global_ratelimits: self.global_ratelimits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Route;
+    use crate::model::id::ChannelId;
+
+    #[test]
+    fn records_and_exports_routes() {
+        let recorder = MetricsRecorder::default();
+        let bucket = Route::ChannelMessages {
+            channel_id: ChannelId::new(1),
+        }
+        .ratelimiting_bucket();
+        let other_channel = Route::ChannelMessages {
+            channel_id: ChannelId::new(2),
+        }
+        .ratelimiting_bucket();
+
+        let ok = Some(StatusCode::OK);
+        recorder.record_response(&bucket, LightMethod::Get, ok, Duration::from_millis(40));
+        recorder.record_response(&other_channel, LightMethod::Get, ok, Duration::from_secs(20));
+        let ratelimited = Some(StatusCode::TOO_MANY_REQUESTS);
+        recorder.record_response(&bucket, LightMethod::Post, ratelimited, Duration::ZERO);
+        recorder.record_wait(&bucket, LightMethod::Post, Duration::from_millis(1500));
+        recorder.record_global_ratelimit();
+
+        let snapshot = recorder.snapshot();
+        assert_eq!(snapshot.routes.len(), 2);
+        assert_eq!(snapshot.global_ratelimits, 1);
+
+        let get = snapshot.routes.iter().find(|r| r.method == LightMethod::Get).unwrap();
+        assert_eq!(get.requests, 2);
+        assert_eq!(get.latency.counts()[1], 1);
+        assert_eq!(get.latency.counts().last(), Some(&1));
+
+        let post = snapshot.routes.iter().find(|r| r.method == LightMethod::Post).unwrap();
+        assert_eq!((post.ratelimited, post.client_errors), (1, 0));
+        assert_eq!(post.ratelimit_wait_time, Duration::from_millis(1500));
+
+        let text = snapshot.to_prometheus();
+        let labels = r#"route="ChannelMessages",method="GET""#;
+        assert!(text.contains(&format!("serenity_http_requests_total{{{labels}}} 2")));
+        assert!(text.contains(&format!(
+            "serenity_http_request_duration_seconds_bucket{{{labels},le=\"0.05\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "serenity_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} 2"
+        )));
+        assert!(text.contains("serenity_http_global_ratelimits_total 1"));
+    }
+}
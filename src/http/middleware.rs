@@ -0,0 +1,161 @@
+use std::fmt;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap as Headers;
+use reqwest::Response;
+
+use super::{LightMethod, Request, Route, StatusCode};
+use crate::internal::prelude::*;
+
+/// Passed to [`Middleware::after`] once an attempt at a request is over.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ResponseInfo<'a> {
+    pub route: &'a Route<'a>,
+    pub method: LightMethod,
+    /// The response to the attempt, or the error that prevented getting one.
+    ///
+    /// Unsuccessful statuses are not errors at this point, they are only turned into
+    /// [`HttpError::UnsuccessfulRequest`] once the request is not retried any more.
+    ///
+    /// [`HttpError::UnsuccessfulRequest`]: super::HttpError::UnsuccessfulRequest
+    pub result: &'a Result<Response>,
+    /// How long the attempt took, including any time spent waiting for ratelimits.
+    pub elapsed: Duration,
+}
+
+impl ResponseInfo<'_> {
+    /// The status of the response, if one was received.
+    #[must_use]
+    pub fn status(&self) -> Option<StatusCode> {
+        self.result.as_ref().ok().map(Response::status)
+    }
+
+    /// The headers of the response, if one was received.
+    #[must_use]
+    pub fn headers(&self) -> Option<&Headers> {
+        self.result.as_ref().ok().map(Response::headers)
+    }
+}
+
+/// A layer that sees every request made by [`Http`], registered with [`HttpBuilder::middleware`].
+///
+/// Both methods are called once per attempt, so a request that is retried under the
+/// [`RetryPolicy`] goes through the middleware again. Layers are called in the order they were
+/// registered before sending a request, and in the reverse order after.
+///
+/// # Examples
+///
+/// Log every moderation action taken through the bot:
+///
+/// ```rust,no_run
+/// use serenity::async_trait;
+/// use serenity::http::{HttpBuilder, Middleware, ResponseInfo};
+///
+/// #[derive(Debug)]
+/// struct AuditLogger;
+///
+/// #[async_trait]
+/// impl Middleware for AuditLogger {
+///     async fn after(&self, response: &ResponseInfo<'_>) {
+///         let path = response.route.path();
+///         if path.contains("/bans/") || path.contains("/members/") {
+///             println!("{:?} {} -> {:?}", response.method, path, response.status());
+///         }
+///     }
+/// }
+///
+/// let http = HttpBuilder::new("token").middleware(AuditLogger).build();
+/// ```
+///
+/// [`Http`]: super::Http
+/// [`HttpBuilder::middleware`]: super::HttpBuilder::middleware
+/// [`RetryPolicy`]: super::RetryPolicy
+#[async_trait]
+pub trait Middleware: fmt::Debug + Send + Sync {
+    /// Called before a request is sent, with its route, method, headers and body. The request
+    /// may be changed, for example to add headers to it.
+    ///
+    /// The `Authorization`, `User-Agent` and content headers are only added when sending the
+    /// request, so they aren't seen here.
+    ///
+    /// # Errors
+    ///
+    /// Returning an error cancels the request, and the error is returned to the caller without
+    /// calling any further layer.
+    async fn before(&self, _request: &mut Request<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after an attempt at a request is over, whether or not it succeeded.
+    async fn after(&self, _response: &ResponseInfo<'_>) {}
+}
+
+#[cfg(all(test, feature = "http_mock"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+    use crate::http::mock::{MockResponse, MockServer};
+    use crate::model::id::{ChannelId, MessageId};
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Recorder {
+        async fn before(&self, request: &mut Request<'_>) -> Result<()> {
+            request
+                .headers_mut()
+                .get_or_insert_with(Headers::new)
+                .insert("x-middleware", HeaderValue::from_static(self.name));
+            self.calls.lock().unwrap().push(format!("before {}", self.name));
+            Ok(())
+        }
+
+        async fn after(&self, response: &ResponseInfo<'_>) {
+            let status = response.status().map(|status| status.as_u16());
+            self.calls.lock().unwrap().push(format!("after {} {:?}", self.name, status));
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_layers_in_order() {
+        let server = MockServer::start().await.unwrap();
+        let route = Route::ChannelMessage {
+            channel_id: ChannelId::new(1),
+            message_id: MessageId::new(2),
+        };
+        server.mock(LightMethod::Delete, route, MockResponse::no_content());
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let http = server
+            .http_builder()
+            .middleware(Recorder {
+                name: "outer",
+                calls: Arc::clone(&calls),
+            })
+            .middleware(Recorder {
+                name: "inner",
+                calls: Arc::clone(&calls),
+            })
+            .build();
+
+        http.delete_message(ChannelId::new(1), MessageId::new(2), None).await.unwrap();
+
+        assert_eq!(*calls.lock().unwrap(), [
+            "before outer",
+            "before inner",
+            "after inner Some(204)",
+            "after outer Some(204)",
+        ]);
+        // The last layer to touch the request has the final say.
+        assert_eq!(server.requests()[0].header("x-middleware"), Some("inner"));
+    }
+}
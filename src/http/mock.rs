@@ -0,0 +1,589 @@
+//! A mock of the Discord HTTP API, to test code making HTTP requests without a token or a network.
+//!
+//! A [`MockServer`] listens on a local port, and is used through an [`Http`] whose
+//! [`HttpBuilder::proxy`] and [`Ratelimiter::set_proxy`] point at it. Responses are registered per
+//! [`Route`], either as canned [`MockResponse`]s or as closures building a response from the
+//! [`MockRequest`], which allows keeping state between requests. Every request received is
+//! recorded, so that tests can assert on what was sent.
+//!
+//! Ratelimits can be simulated per route with [`MockServer::ratelimit`], in which case responses
+//! carry the same headers Discord sends, and requests going over the limit receive a 429.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use serenity::http::mock::{MockResponse, MockServer};
+//! use serenity::http::{LightMethod, Route};
+//! use serenity::model::id::{ChannelId, MessageId};
+//!
+//! # async fn run() -> serenity::Result<()> {
+//! let server = MockServer::start().await?;
+//! let channel_id = ChannelId::new(1);
+//! server.mock(
+//!     LightMethod::Delete,
+//!     Route::ChannelMessage {
+//!         channel_id,
+//!         message_id: MessageId::new(2),
+//!     },
+//!     MockResponse::no_content(),
+//! );
+//!
+//! let http = server.http();
+//! http.delete_message(channel_id, MessageId::new(2), None).await?;
+//! assert_eq!(server.requests().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Http`]: super::Http
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncWrite, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use super::server::{self, ReadRequest, DEFAULT_MAX_BODY_SIZE};
+use super::{Http, HttpBuilder, LightMethod, Ratelimiter, Route, StatusCode};
+use crate::internal::prelude::*;
+use crate::internal::tokio::spawn_named;
+use crate::json::{from_slice, json, to_vec};
+
+/// Builds the response to a request, see [`MockServer::mock_with`].
+type Handler = Arc<dyn Fn(&MockRequest) -> MockResponse + Send + Sync>;
+
+/// The key responses and ratelimits are registered under.
+type Endpoint = (LightMethod, String);
+
+/// A request received by a [`MockServer`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct MockRequest {
+    /// The method of the request.
+    pub method: LightMethod,
+    /// The path of the request, such as `/api/v10/channels/1`, without the query.
+    pub path: String,
+    /// The query of the request, if any, without the leading `?`.
+    pub query: Option<String>,
+    /// The headers of the request, with lowercase names.
+    pub headers: Vec<(String, String)>,
+    /// The body of the request.
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// Returns the value of the first header with the given name, ignoring case.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Deserializes the body of the request from JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if the body is not valid JSON for `T`.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        from_slice(&self.body)
+    }
+
+    /// Whether the request was made to the given route, with the given method.
+    #[must_use]
+    pub fn is(&self, method: LightMethod, route: Route<'_>) -> bool {
+        self.method == method && self.path == route_path(route)
+    }
+}
+
+/// A response sent by a [`MockServer`].
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: StatusCode,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// Creates a response with the given status and an empty body.
+    #[must_use]
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Creates a `200 OK` response with the given value as its JSON body.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value fails to serialize.
+    #[must_use]
+    pub fn json(value: &impl serde::Serialize) -> Self {
+        let body = to_vec(value).expect("failed to serialize mock response");
+        Self::new(StatusCode::OK).body(body)
+    }
+
+    /// Creates a `204 No Content` response.
+    #[must_use]
+    pub fn no_content() -> Self {
+        Self::new(StatusCode::NO_CONTENT)
+    }
+
+    /// Creates an error response, with a body in the format Discord uses for JSON error codes.
+    #[must_use]
+    pub fn error(status: StatusCode, code: isize, message: &str) -> Self {
+        Self::json(&json!({
+            "code": code,
+            "message": message,
+        }))
+        .status(status)
+    }
+
+    /// Creates a `429 Too Many Requests` response, as sent by Discord when a ratelimit is hit.
+    #[must_use]
+    pub fn ratelimited(retry_after: Duration, global: bool) -> Self {
+        let retry_after = retry_after.as_secs_f64();
+        let response = Self::json(&json!({
+            "message": "You are being rate limited.",
+            "retry_after": retry_after,
+            "global": global,
+        }))
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header("retry-after", retry_after.to_string());
+
+        if global {
+            response.header("x-ratelimit-global", "true")
+        } else {
+            response
+        }
+    }
+
+    /// Sets the status of the response.
+    #[must_use]
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Adds a header to the response.
+    #[must_use]
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the body of the response.
+    #[must_use]
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+/// A ratelimit simulated by a [`MockServer`] for a single endpoint.
+#[derive(Debug)]
+struct SimulatedRatelimit {
+    limit: u32,
+    remaining: u32,
+    reset_after: Duration,
+    reset: Option<Instant>,
+}
+
+impl SimulatedRatelimit {
+    /// Counts a request against the ratelimit. Returns the headers to send along with the
+    /// response, and whether the request went over the limit.
+    fn hit(&mut self, bucket: &str) -> (Vec<(String, String)>, bool) {
+        let now = Instant::now();
+        let reset = match self.reset {
+            Some(reset) if reset > now => reset,
+            _ => {
+                self.remaining = self.limit;
+                *self.reset.insert(now + self.reset_after)
+            },
+        };
+
+        let limited = self.remaining == 0;
+        self.remaining = self.remaining.saturating_sub(1);
+
+        let reset_after = reset - now;
+        let reset_at =
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default() + reset_after;
+        let headers = vec![
+            ("x-ratelimit-limit".into(), self.limit.to_string()),
+            ("x-ratelimit-remaining".into(), self.remaining.to_string()),
+            ("x-ratelimit-reset".into(), reset_at.as_secs_f64().to_string()),
+            ("x-ratelimit-reset-after".into(), reset_after.as_secs_f64().to_string()),
+            ("x-ratelimit-bucket".into(), bucket.into()),
+        ];
+
+        (headers, limited)
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    handlers: Mutex<HashMap<Endpoint, Handler>>,
+    ratelimits: Mutex<HashMap<Endpoint, SimulatedRatelimit>>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+impl MockState {
+    fn respond(&self, request: MockRequest) -> MockResponse {
+        let endpoint = (request.method, request.path.clone());
+
+        let mut ratelimits = self.ratelimits.lock().expect("poisoned ratelimits");
+        let (headers, limited) = match ratelimits.get_mut(&endpoint) {
+            Some(ratelimit) => ratelimit.hit(&request.path),
+            None => (Vec::new(), false),
+        };
+        drop(ratelimits);
+
+        let mut response = if limited {
+            let reset_after = headers
+                .iter()
+                .find(|(name, _)| name == "x-ratelimit-reset-after")
+                .and_then(|(_, value)| value.parse().ok())
+                .unwrap_or_default();
+            MockResponse::ratelimited(Duration::from_secs_f64(reset_after), false)
+        } else {
+            let handler = self.handlers.lock().expect("poisoned handlers").get(&endpoint).cloned();
+            match handler {
+                Some(handler) => handler(&request),
+                None => MockResponse::error(StatusCode::NOT_FOUND, 0, "404: Not Found"),
+            }
+        };
+
+        response.headers.extend(headers);
+        self.requests.lock().expect("poisoned requests").push(request);
+        response
+    }
+}
+
+/// A local HTTP server answering requests in place of Discord.
+///
+/// Refer to the [module-level documentation] for an overview.
+///
+/// The server stops when it is dropped.
+///
+/// [module-level documentation]: self
+pub struct MockServer {
+    url: String,
+    state: Arc<MockState>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a server listening on a random local port.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if no port could be bound.
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(MockState::default());
+
+        let task = spawn_named("http::mock::accept", accept(listener, Arc::clone(&state)));
+
+        Ok(Self {
+            url,
+            state,
+            task,
+        })
+    }
+
+    /// Returns the base URL of the server, to be given to [`HttpBuilder::proxy`].
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Creates an [`Http`] sending its requests to this server.
+    #[must_use]
+    pub fn http(&self) -> Http {
+        self.http_builder().build()
+    }
+
+    /// Creates an [`HttpBuilder`] sending its requests to this server, both with and without its
+    /// ratelimiter, to configure the client further.
+    pub fn http_builder(&self) -> HttpBuilder {
+        let client = reqwest::Client::new();
+        let mut ratelimiter = Ratelimiter::new(client.clone(), "Bot mock-token");
+        ratelimiter.set_proxy(Some(self.url.clone()));
+
+        HttpBuilder::new("mock-token").client(client).ratelimiter(ratelimiter).proxy(&self.url)
+    }
+
+    /// Answers every request to the given route with a copy of the given response.
+    ///
+    /// This replaces any response previously registered for the route.
+    pub fn mock(&self, method: LightMethod, route: Route<'_>, response: MockResponse) {
+        self.mock_with(method, route, move |_| response.clone());
+    }
+
+    /// Answers every request to the given route with the response built by the given closure.
+    ///
+    /// The closure may keep state between requests, for example to hand out increasing IDs, or to
+    /// return the messages created so far.
+    ///
+    /// This replaces any response previously registered for the route.
+    pub fn mock_with<F>(&self, method: LightMethod, route: Route<'_>, handler: F)
+    where
+        F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        let endpoint = (method, route_path(route));
+        self.state.handlers.lock().expect("poisoned handlers").insert(endpoint, Arc::new(handler));
+    }
+
+    /// Simulates a ratelimit of `limit` requests per `reset_after` for the given route.
+    ///
+    /// Responses to the route carry the `x-ratelimit-*` headers, and requests over the limit are
+    /// answered with a 429 instead of the registered response.
+    pub fn ratelimit(
+        &self,
+        method: LightMethod,
+        route: Route<'_>,
+        limit: u32,
+        reset_after: Duration,
+    ) {
+        let endpoint = (method, route_path(route));
+        self.state.ratelimits.lock().expect("poisoned ratelimits").insert(
+            endpoint,
+            SimulatedRatelimit {
+                limit,
+                remaining: limit,
+                reset_after,
+                reset: None,
+            },
+        );
+    }
+
+    /// Returns every request received so far, in order.
+    #[must_use]
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.requests.lock().expect("poisoned requests").clone()
+    }
+
+    /// Forgets the requests received so far.
+    pub fn clear_requests(&self) {
+        self.state.requests.lock().expect("poisoned requests").clear();
+    }
+}
+
+impl fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockServer").field("url", &self.url).finish_non_exhaustive()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Returns the path a route is requested at, percent-encoded as it is sent.
+fn route_path(route: Route<'_>) -> String {
+    match Url::parse(&route.path()) {
+        Ok(url) => url.path().to_owned(),
+        Err(_) => route.path().trim_start_matches("https://discord.com").to_owned(),
+    }
+}
+
+async fn accept(listener: TcpListener, state: Arc<MockState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                spawn_named("http::mock::connection", handle(stream, Arc::clone(&state)));
+            },
+            Err(why) => {
+                warn!("[Mock Server] Err accepting connection: {:?}", why);
+                break;
+            },
+        }
+    }
+}
+
+/// Answers the requests of a connection until it is closed.
+async fn handle(stream: TcpStream, state: Arc<MockState>) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+
+    loop {
+        let request = match server::read_request(&mut reader, DEFAULT_MAX_BODY_SIZE).await {
+            Ok(ReadRequest::Request(request)) => request,
+            Ok(ReadRequest::TooLarge) => {
+                let response = MockResponse::error(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    40005,
+                    "Request entity too large",
+                );
+                if let Err(why) = write_response(&mut write, &response).await {
+                    warn!("[Mock Server] Err writing response: {:?}", why);
+                }
+                break;
+            },
+            Ok(ReadRequest::Closed) => break,
+            Err(why) => {
+                warn!("[Mock Server] Err reading request: {:?}", why);
+                break;
+            },
+        };
+
+        let response = match request.light_method() {
+            Some(method) => {
+                debug!("[Mock Server] {:?} {}", method, request.path);
+                state.respond(MockRequest {
+                    method,
+                    path: request.path,
+                    query: request.query,
+                    headers: request.headers,
+                    body: request.body,
+                })
+            },
+            None => MockResponse::error(
+                StatusCode::METHOD_NOT_ALLOWED,
+                0,
+                &format!("405: Method {} Not Allowed", request.method),
+            ),
+        };
+
+        if let Err(why) = write_response(&mut write, &response).await {
+            warn!("[Mock Server] Err writing response: {:?}", why);
+            break;
+        }
+    }
+}
+
+async fn write_response<W>(writer: &mut W, response: &MockResponse) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut headers = Vec::with_capacity(response.headers.len() + 1);
+    if response.status != StatusCode::NO_CONTENT && !response.body.is_empty() {
+        headers.push(("content-type".into(), "application/json".into()));
+    }
+    headers.extend(response.headers.iter().cloned());
+
+    server::write_response(writer, response.status, &headers, &response.body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::model::id::{ChannelId, MessageId, UserId};
+
+    fn message_route(channel_id: ChannelId) -> Route<'static> {
+        Route::ChannelMessage {
+            channel_id,
+            message_id: MessageId::new(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_and_records_requests() {
+        let server = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        server.mock(LightMethod::Delete, message_route(channel_id), MockResponse::no_content());
+        server.mock(
+            LightMethod::Get,
+            Route::User {
+                user_id: UserId::new(3),
+            },
+            MockResponse::json(&json!({
+                "id": "3",
+                "username": "mock",
+                "discriminator": "0",
+                "global_name": null,
+                "avatar": null,
+            })),
+        );
+
+        let http = server.http();
+        http.delete_message(channel_id, MessageId::new(2), Some("cleanup")).await.unwrap();
+        assert_eq!(http.get_user(UserId::new(3)).await.unwrap().name, "mock");
+
+        // Routes without a registered response are answered with a 404.
+        assert!(http.get_user(UserId::new(4)).await.is_err());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].is(LightMethod::Delete, message_route(channel_id)));
+        assert_eq!(requests[0].header("X-Audit-Log-Reason"), Some("cleanup"));
+        assert_eq!(requests[0].header("authorization"), Some("Bot mock-token"));
+    }
+
+    #[tokio::test]
+    async fn keeps_state_between_requests() {
+        let server = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        let deleted = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&deleted);
+        server.mock_with(LightMethod::Delete, message_route(channel_id), move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                MockResponse::no_content()
+            } else {
+                MockResponse::error(StatusCode::NOT_FOUND, 10008, "Unknown Message")
+            }
+        });
+
+        let http = server.http();
+        assert!(http.delete_message(channel_id, MessageId::new(2), None).await.is_ok());
+        assert!(http.delete_message(channel_id, MessageId::new(2), None).await.is_err());
+        assert_eq!(deleted.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn simulates_ratelimits() {
+        let server = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        let route = message_route(channel_id);
+        server.mock(LightMethod::Delete, route, MockResponse::no_content());
+        server.ratelimit(LightMethod::Delete, route, 1, Duration::from_millis(300));
+
+        // The ratelimiter waits for the reset given in the headers of the first response.
+        let http = server.http();
+        let start = Instant::now();
+        http.delete_message(channel_id, MessageId::new(2), None).await.unwrap();
+        http.delete_message(channel_id, MessageId::new(2), None).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        assert_eq!(server.requests().len(), 2);
+        tokio::time::sleep(Duration::from_millis(350)).await;
+
+        // Without a ratelimiter, the request over the limit gets a 429.
+        let http =
+            HttpBuilder::new("mock-token").proxy(server.url()).ratelimiter_disabled(true).build();
+        http.delete_message(channel_id, MessageId::new(2), None).await.unwrap();
+        assert!(http.delete_message(channel_id, MessageId::new(2), None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn retries_after_429() {
+        let server = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        let calls = AtomicUsize::new(0);
+        server.mock_with(LightMethod::Delete, message_route(channel_id), move |_| {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                MockResponse::ratelimited(Duration::from_millis(100), false)
+            } else {
+                MockResponse::no_content()
+            }
+        });
+
+        server.http().delete_message(channel_id, MessageId::new(2), None).await.unwrap();
+        assert_eq!(server.requests().len(), 2);
+    }
+}
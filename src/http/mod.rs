@@ -11,7 +11,9 @@
 //! The former require a [`Client`] to have logged in, while the latter may be made regardless of
 //! any other usage of the library.
 //!
-//! If a request spuriously fails, it will be retried once.
+//! If a request spuriously fails, for example with a 502 from Discord, it may be retried as
+//! decided by the [`RetryPolicy`] of the [`Http`] client. Every request, including retries, also
+//! goes through the [`Middleware`] registered on the client, which can inspect and change it.
 //!
 //! Note that you may want to perform requests through a [model]s' instance methods where possible,
 //! as they each offer different levels of a high-level interface to the HTTP module.
@@ -21,10 +23,25 @@
 
 mod client;
 mod error;
+mod error_code;
+mod metrics;
+mod middleware;
+#[cfg(feature = "http_mock")]
+pub mod mock;
 mod multipart;
+mod options;
+mod pagination;
+mod priority;
+#[cfg(feature = "http_proxy")]
+pub mod proxy;
+#[cfg(all(feature = "ratelimit_daemon", unix))]
+pub mod ratelimit_daemon;
 mod ratelimiting;
 mod request;
+mod retry;
 mod routing;
+#[cfg(any(feature = "http_mock", feature = "http_proxy"))]
+mod server;
 mod typing;
 
 use std::sync::Arc;
@@ -34,9 +51,16 @@ pub use reqwest::StatusCode;
 
 pub use self::client::*;
 pub use self::error::*;
+pub use self::error_code::*;
+pub use self::metrics::*;
+pub use self::middleware::*;
 pub use self::multipart::*;
+pub use self::options::*;
+pub use self::pagination::*;
+pub use self::priority::*;
 pub use self::ratelimiting::*;
 pub use self::request::*;
+pub use self::retry::*;
 pub use self::routing::*;
 pub use self::typing::*;
 #[cfg(feature = "cache")]
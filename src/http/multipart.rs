@@ -1,15 +1,47 @@
 use std::borrow::Cow;
+use std::io;
+use std::sync::Arc;
 
+use futures::stream::{self, Stream};
 use reqwest::multipart::{Form, Part};
+use reqwest::Body;
+use tokio::io::AsyncReadExt;
 
-use crate::builder::CreateAttachment;
+use crate::builder::{
+    AttachmentReader,
+    AttachmentStream,
+    CreateAttachment,
+    ProgressCallback,
+    UploadProgress,
+};
 use crate::internal::prelude::*;
 
+/// The size of the chunks streamed attachments are read in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
 impl CreateAttachment {
-    fn into_part(self) -> Result<Part> {
-        let mut part = Part::bytes(self.data);
-        part = guess_mime_str(part, &self.filename)?;
-        part = part.file_name(self.filename);
+    /// Returns the data of the attachment as a request body, along with its length.
+    pub(super) fn into_body(self) -> (Body, u64) {
+        match (self.stream, self.progress) {
+            (None, None) => {
+                let length = self.data.len() as u64;
+                (self.data.into(), length)
+            },
+            (stream, progress) => {
+                let stream = stream.unwrap_or_else(|| AttachmentStream::bytes(self.data));
+                let length = stream.length;
+                (Body::wrap_stream(read_chunks(stream, progress)), length)
+            },
+        }
+    }
+
+    fn into_part(mut self) -> Result<Part> {
+        let filename = std::mem::take(&mut self.filename);
+        let (body, length) = self.into_body();
+
+        let mut part = Part::stream_with_length(body, length);
+        part = guess_mime_str(part, &filename)?;
+        part = part.file_name(filename);
         Ok(part)
     }
 }
@@ -61,6 +93,50 @@ impl Multipart {
     }
 }
 
+/// Reads a streamed attachment chunk by chunk, opening it once the body starts being sent.
+fn read_chunks(
+    stream: AttachmentStream,
+    progress: Option<ProgressCallback>,
+) -> impl Stream<Item = io::Result<Vec<u8>>> {
+    let total = stream.length;
+    let state: (Option<AttachmentReader>, u64) = (None, 0);
+
+    stream::try_unfold(state, move |(reader, sent)| {
+        let open = Arc::clone(&stream.open);
+        let progress = progress.clone();
+        async move {
+            let remaining = total - sent;
+            if remaining == 0 {
+                return Ok(None);
+            }
+
+            let mut reader = match reader {
+                Some(reader) => reader,
+                None => open().await?,
+            };
+
+            let mut chunk =
+                vec![0; usize::try_from(remaining).map_or(CHUNK_SIZE, |r| r.min(CHUNK_SIZE))];
+            let read = reader.read(&mut chunk).await?;
+            if read == 0 {
+                let message = "attachment ended before the end of its length";
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, message));
+            }
+            chunk.truncate(read);
+
+            let sent = sent + read as u64;
+            if let Some(progress) = progress {
+                (progress.0)(UploadProgress {
+                    sent,
+                    total,
+                });
+            }
+
+            Ok(Some((chunk, (Some(reader), sent))))
+        }
+    })
+}
+
 fn guess_mime_str(part: Part, filename: &str) -> Result<Part> {
     // This is required for certain endpoints like create sticker, otherwise the Discord API will
     // respond with a 500 Internal Server Error. The mime type chosen is the same as what reqwest
@@ -70,3 +146,150 @@ fn guess_mime_str(part: Part, filename: &str) -> Result<Part> {
     let mime_type = mime_guess::from_path(filename).first_or_octet_stream();
     part.mime_str(mime_type.essence_str()).map_err(Into::into)
 }
+
+#[cfg(all(test, feature = "http_mock"))]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::http::mock::{MockResponse, MockServer};
+    use crate::http::{LightMethod, Request, Route};
+    use crate::model::id::ChannelId;
+
+    #[tokio::test]
+    async fn streams_attachments_again_on_retry() {
+        let server = MockServer::start().await.unwrap();
+        let route = Route::ChannelMessages {
+            channel_id: ChannelId::new(1),
+        };
+        let calls = Mutex::new(0);
+        server.mock_with(LightMethod::Post, route, move |_| {
+            let mut calls = calls.lock().unwrap();
+            *calls += 1;
+            if *calls == 1 {
+                MockResponse::ratelimited(Duration::ZERO, false)
+            } else {
+                MockResponse::no_content()
+            }
+        });
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&progress);
+        let data = vec![7; CHUNK_SIZE + 10];
+        let length = data.len() as u64;
+        let attachment = CreateAttachment::stream(
+            move || std::future::ready(Ok(io::Cursor::new(data.clone()))),
+            length,
+            "data.bin",
+        )
+        .on_progress(move |progress| recorded.lock().unwrap().push(progress.sent));
+
+        let multipart = Multipart {
+            upload: MultipartUpload::Attachments(vec![attachment]),
+            fields: vec![],
+            payload_json: None,
+        };
+        let request = Request::new(route, LightMethod::Post).multipart(Some(multipart));
+        server.http().request(request).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        for request in &requests {
+            assert!(request.body.windows(10).any(|window| window == [7; 10]));
+            assert!(request.body.len() > CHUNK_SIZE + 10);
+        }
+        assert_eq!(*progress.lock().unwrap(), [
+            CHUNK_SIZE as u64,
+            length,
+            CHUNK_SIZE as u64,
+            length
+        ]);
+
+        // A reader can't be read again for the retry.
+        let attachment = CreateAttachment::reader(io::Cursor::new(vec![1, 2, 3]), 3, "once.bin");
+        let multipart = Multipart {
+            upload: MultipartUpload::File(attachment),
+            fields: vec![],
+            payload_json: None,
+        };
+        server.mock(LightMethod::Post, route, MockResponse::ratelimited(Duration::ZERO, false));
+        let request = Request::new(route, LightMethod::Post).multipart(Some(multipart));
+        assert!(server.http().request(request).await.is_err());
+    }
+
+    #[cfg(feature = "model")]
+    #[tokio::test]
+    async fn uploads_attachments_ahead_of_messages() {
+        use crate::builder::CreateMessage;
+        use crate::http::{HttpError, StatusCode};
+        use crate::json::{json, to_value, Value};
+        use crate::model::id::MessageId;
+
+        let server = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        // A route of the mock server stands in for the storage the files are uploaded to.
+        let storage = Route::ChannelMessage {
+            channel_id,
+            message_id: MessageId::new(9),
+        };
+        let upload_url = storage.path().replace("https://discord.com", server.url());
+        let uploads = json!({
+            "attachments": [{"id": 0, "upload_url": upload_url, "upload_filename": "1/2/video.mp4"}],
+        });
+        let route = Route::ChannelAttachments {
+            channel_id,
+        };
+        server.mock(LightMethod::Post, route, MockResponse::json(&uploads));
+        server.mock(LightMethod::Put, storage, MockResponse::new(StatusCode::OK));
+
+        let video = CreateAttachment::bytes(vec![1, 2, 3], "video.mp4").description("Highlights");
+        let uploaded = channel_id.upload_attachments(server.http(), vec![video]).await.unwrap();
+
+        let requests = server.requests();
+        let files: Value = requests[0].json().unwrap();
+        assert_eq!(files, json!({"files": [{"id": "0", "filename": "video.mp4", "file_size": 3}]}));
+        assert_eq!(requests[1].body, [1, 2, 3]);
+        assert_eq!(requests[1].header("authorization"), None);
+
+        // The uploaded file is only referred to in the payload of the message.
+        let mut builder = CreateMessage::new().add_files(uploaded);
+        assert!(builder.attachments.take_files().is_empty());
+        assert_eq!(
+            to_value(&builder).unwrap()["attachments"],
+            json!([{
+                "id": 0,
+                "filename": "video.mp4",
+                "description": "Highlights",
+                "uploaded_filename": "1/2/video.mp4",
+            }])
+        );
+
+        // Slots are matched to files by their id, whatever their order.
+        let slot = |id: &str, filename: &str| json!({"id": id, "upload_url": upload_url, "upload_filename": format!("1/2/{filename}")});
+        let uploads = json!({"attachments": [slot("1", "b.bin"), slot("0", "a.bin")]});
+        server.mock(LightMethod::Post, route, MockResponse::json(&uploads));
+        let files = || {
+            vec![
+                CreateAttachment::bytes(vec![1], "a.bin"),
+                CreateAttachment::bytes(vec![2], "b.bin"),
+            ]
+        };
+        let uploaded = channel_id.upload_attachments(server.http(), files()).await.unwrap();
+        let builder = CreateMessage::new().add_files(uploaded);
+        let attachments = to_value(&builder).unwrap()["attachments"].clone();
+        assert_eq!(attachments[0]["uploaded_filename"], "1/2/a.bin");
+        assert_eq!(attachments[1]["uploaded_filename"], "1/2/b.bin");
+
+        // A file Discord returned no slot for fails the upload before any file is sent.
+        server.clear_requests();
+        let uploads = json!({"attachments": [slot("1", "b.bin")]});
+        server.mock(LightMethod::Post, route, MockResponse::json(&uploads));
+        let err = channel_id.upload_attachments(server.http(), files()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Http(HttpError::MissingAttachmentUpload(filename)) if filename == "a.bin"
+        ));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
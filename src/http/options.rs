@@ -0,0 +1,186 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tokio::time::{sleep, timeout_at};
+
+use super::{HttpError, RatelimitInfo};
+use crate::internal::prelude::*;
+
+tokio::task_local! {
+    static OPTIONS: RequestOptions;
+}
+
+/// Limits how long a request may take, and whether it may wait for ratelimits.
+///
+/// Options are set for a single request with [`Request::options`], or for every request made in a
+/// future with [`RequestOptions::scope`], which also covers the requests made by builders. The
+/// options of a request take precedence over the ones of its scope.
+///
+/// # Examples
+///
+/// Give up on a command if Discord doesn't answer within 10 seconds, ratelimits included:
+///
+/// ```rust,no_run
+/// # use serenity::http::Http;
+/// # use serenity::model::id::ChannelId;
+/// # async fn run(http: &Http, channel_id: ChannelId) {
+/// use std::time::Duration;
+///
+/// use serenity::http::RequestOptions;
+///
+/// let result = RequestOptions::new()
+///     .timeout(Duration::from_secs(10))
+///     .scope(channel_id.say(http, "Pong!"))
+///     .await;
+/// # }
+/// ```
+///
+/// [`Request::options`]: super::Request::options
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[must_use]
+pub struct RequestOptions {
+    deadline: Option<Instant>,
+    attempt_timeout: Option<Duration>,
+    fail_fast: bool,
+}
+
+impl RequestOptions {
+    /// Creates options without a deadline, which wait for ratelimits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time by which a request must be over, including the time spent waiting for
+    /// ratelimits and between retries. A request that isn't over by then fails with
+    /// [`HttpError::DeadlineExceeded`], as does one that would have to wait for a ratelimit past
+    /// it.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets the deadline to the given time from now, see [`Self::deadline`].
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// Sets how long each attempt at a request may take to connect to Discord and read its
+    /// response, not counting the time spent waiting for ratelimits. This overrides the timeout of
+    /// the underlying client.
+    pub fn attempt_timeout(mut self, attempt_timeout: Duration) -> Self {
+        self.attempt_timeout = Some(attempt_timeout);
+        self
+    }
+
+    /// Sets whether a request fails with [`HttpError::Ratelimited`] instead of waiting when it is
+    /// ratelimited, whether the ratelimit is known beforehand or Discord answers with a 429.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// The time by which a request must be over.
+    #[must_use]
+    pub fn get_deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// How long each attempt at a request may take.
+    #[must_use]
+    pub fn get_attempt_timeout(&self) -> Option<Duration> {
+        self.attempt_timeout
+    }
+
+    /// Whether a request fails instead of waiting for ratelimits.
+    #[must_use]
+    pub fn get_fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+
+    /// Makes every request in the given future with these options, unless the request has options
+    /// of its own.
+    ///
+    /// The options only apply to requests made by the future itself, not to tasks it spawns.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        OPTIONS.scope(self, future).await
+    }
+
+    /// Returns the options of the [`Self::scope`] the current task is in, if any.
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        OPTIONS.try_with(|options| *options).ok()
+    }
+
+    /// Returns the options of a request: its own if it has any, or else the ones of its scope.
+    pub(super) fn resolve(options: Option<Self>) -> Self {
+        options.or_else(Self::current).unwrap_or_default()
+    }
+
+    /// Runs a request, cancelling it once the deadline is reached.
+    pub(super) async fn run<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(deadline) = self.deadline else { return request.await };
+
+        timeout_at(deadline.into(), request)
+            .await
+            .unwrap_or(Err(Error::Http(HttpError::DeadlineExceeded)))
+    }
+
+    /// Applies the attempt timeout to a request about to be sent.
+    pub(super) fn apply(&self, request: &mut reqwest::Request) {
+        if let Some(attempt_timeout) = self.attempt_timeout {
+            *request.timeout_mut() = Some(attempt_timeout);
+        }
+    }
+
+    /// Sleeps for a ratelimit, unless failing fast or the ratelimit lasts past the deadline.
+    pub(super) async fn wait(&self, info: RatelimitInfo) -> Result<()> {
+        if self.fail_fast {
+            return Err(Error::Http(HttpError::Ratelimited(info)));
+        }
+
+        if self.deadline.is_some_and(|deadline| Instant::now() + info.timeout > deadline) {
+            return Err(Error::Http(HttpError::DeadlineExceeded));
+        }
+
+        sleep(info.timeout).await;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "http_mock"))]
+mod tests {
+    use super::*;
+    use crate::http::mock::{MockResponse, MockServer};
+    use crate::http::{LightMethod, Request, Route};
+    use crate::model::id::{ChannelId, MessageId};
+
+    #[tokio::test]
+    async fn fails_instead_of_waiting() {
+        let server = MockServer::start().await.unwrap();
+        let route = Route::ChannelMessage {
+            channel_id: ChannelId::new(1),
+            message_id: MessageId::new(2),
+        };
+        server.mock(LightMethod::Delete, route, MockResponse::no_content());
+        server.ratelimit(LightMethod::Delete, route, 1, Duration::from_secs(5));
+
+        let http = server.http();
+        let request = Request::new(route, LightMethod::Delete);
+        http.request(request.clone()).await.unwrap();
+
+        let start = Instant::now();
+        let fail_fast = RequestOptions::new().fail_fast(true);
+        let err = http.request(request.clone().options(fail_fast)).await.unwrap_err();
+        let Error::Http(HttpError::Ratelimited(info)) = err else { panic!("{err:?}") };
+        assert!(info.timeout > Duration::from_secs(4));
+        assert!(!info.global);
+
+        // The ratelimit outlasts the deadline, so the request fails without waiting.
+        let deadline = RequestOptions::new().timeout(Duration::from_secs(1));
+        let err = Box::pin(deadline.scope(http.request(request))).await.unwrap_err();
+        assert!(matches!(err, Error::Http(HttpError::DeadlineExceeded)), "{err:?}");
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
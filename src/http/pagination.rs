@@ -0,0 +1,580 @@
+use std::collections::VecDeque;
+use std::future::Future;
+
+use futures::stream::{self, Stream};
+
+use crate::internal::prelude::*;
+
+/// The highest ID Discord accepts as a cursor.
+///
+/// Some endpoints return their oldest items when no cursor is given, so walking them
+/// [`PaginationDirection::Backward`] starts from a `before` cursor with this ID instead.
+pub(crate) const MAX_SNOWFLAKE: u64 = i64::MAX.unsigned_abs();
+
+/// The direction a paginated endpoint is walked in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PaginationDirection {
+    /// From the newest items to the oldest, continuing from a `before` cursor.
+    #[default]
+    Backward,
+    /// From the oldest items to the newest, continuing from an `after` cursor.
+    Forward,
+}
+
+/// Configures the streams returned by [`paginate`] and the `*_iter` methods built on it, such as
+/// [`GuildId::bans_iter`].
+///
+/// Some endpoints can only be walked in one direction, in which case the direction is ignored, as
+/// documented on their `*_iter` method. The page size is capped to the maximum of each endpoint.
+///
+/// [`GuildId::bans_iter`]: crate::model::id::GuildId::bans_iter
+#[derive(Clone, Copy, Debug)]
+#[must_use]
+pub struct PaginationOptions {
+    page_size: u16,
+    direction: PaginationDirection,
+}
+
+impl PaginationOptions {
+    /// Creates options with a page size of 100, walking [`PaginationDirection::Backward`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many items are requested at once. A value of 0 is treated as 1.
+    pub fn page_size(mut self, page_size: u16) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Sets the direction the endpoint is walked in.
+    pub fn direction(mut self, direction: PaginationDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// The number of items requested at once.
+    #[must_use]
+    pub fn get_page_size(&self) -> u16 {
+        self.page_size
+    }
+
+    /// The direction the endpoint is walked in.
+    #[must_use]
+    pub fn get_direction(&self) -> PaginationDirection {
+        self.direction
+    }
+
+    /// Caps the page size to the maximum an endpoint accepts, so that the limit sent is the one
+    /// pages are compared against to recognise the last one.
+    pub(crate) fn max_page_size(mut self, max: u16) -> Self {
+        self.page_size = self.page_size.min(max);
+        self
+    }
+}
+
+impl Default for PaginationOptions {
+    fn default() -> Self {
+        Self {
+            page_size: 100,
+            direction: PaginationDirection::Backward,
+        }
+    }
+}
+
+/// The page a [`paginate`] stream asks for next.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct PageRequest<C> {
+    /// The cursor to continue from, or [`None`] for the first page.
+    pub cursor: Option<C>,
+    /// The number of items to request.
+    pub limit: u16,
+    /// Whether the cursor is a `before` or an `after` cursor.
+    pub direction: PaginationDirection,
+}
+
+/// A page returned by the `fetch` closure of [`paginate`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Page<T> {
+    /// The items of the page, in any order.
+    pub items: Vec<T>,
+    /// Whether the endpoint said there are more items, for the endpoints that do.
+    pub has_more: Option<bool>,
+}
+
+impl<T> Page<T> {
+    /// Creates a page from the items returned by an endpoint that doesn't say whether there are
+    /// more. The last page is then recognised by having fewer items than requested.
+    #[must_use]
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            items,
+            has_more: None,
+        }
+    }
+
+    /// Sets whether the endpoint said there are more items.
+    #[must_use]
+    pub fn has_more(mut self, has_more: bool) -> Self {
+        self.has_more = Some(has_more);
+        self
+    }
+}
+
+impl<T> From<Vec<T>> for Page<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self::new(items)
+    }
+}
+
+struct PaginationState<T, C, K, F> {
+    key: K,
+    fetch: F,
+    cursor: Option<C>,
+    buffer: VecDeque<T>,
+    done: bool,
+}
+
+/// Turns a cursor-based endpoint into a stream over all of its items.
+///
+/// `fetch` is called for every page with the cursor to continue from, and `cursor` returns the
+/// cursor of an item, usually its ID. Items are sorted by their cursor, newest first when walking
+/// [`PaginationDirection::Backward`] and oldest first otherwise, and the cursor of the last item of
+/// a page is used to request the next one.
+///
+/// The stream ends after a page with fewer items than requested, a page saying there are no more
+/// items, or an error.
+///
+/// # Examples
+///
+/// Stream over all bans of a guild, oldest first:
+///
+/// ```rust,no_run
+/// # use serenity::http::Http;
+/// # use serenity::model::id::GuildId;
+/// # async fn run(http: &Http, guild_id: GuildId) {
+/// use serenity::futures::StreamExt;
+/// use serenity::http::{paginate, Page, PaginationDirection, PaginationOptions, UserPagination};
+///
+/// let options = PaginationOptions::new().direction(PaginationDirection::Forward);
+/// let bans = paginate(
+///     options,
+///     |ban: &serenity::model::guild::Ban| ban.user.id,
+///     |page| async move {
+///         let target = page.cursor.map(UserPagination::After);
+///         let limit = u8::try_from(page.limit).ok();
+///         http.get_bans(guild_id, target, limit).await.map(Page::new)
+///     },
+/// );
+///
+/// let mut bans = Box::pin(bans);
+/// while let Some(ban) = bans.next().await {
+///     println!("{:?}", ban.map(|ban| ban.user.name));
+/// }
+/// # }
+/// ```
+pub fn paginate<T, C, K, F, Fut>(
+    options: PaginationOptions,
+    cursor: K,
+    fetch: F,
+) -> impl Stream<Item = Result<T>>
+where
+    C: Ord + Clone,
+    K: Fn(&T) -> C,
+    F: FnMut(PageRequest<C>) -> Fut,
+    Fut: Future<Output = Result<Page<T>>>,
+{
+    let state = PaginationState {
+        key: cursor,
+        fetch,
+        cursor: None,
+        buffer: VecDeque::new(),
+        done: false,
+    };
+
+    stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(item) = state.buffer.pop_front() {
+                return Some((Ok(item), state));
+            }
+
+            if state.done {
+                return None;
+            }
+
+            let request = PageRequest {
+                cursor: state.cursor.clone(),
+                limit: options.page_size,
+                direction: options.direction,
+            };
+            let page = match (state.fetch)(request).await {
+                Ok(page) => page,
+                Err(why) => {
+                    state.done = true;
+                    return Some((Err(why), state));
+                },
+            };
+
+            let mut items = page.items;
+            let full = items.len() >= usize::from(options.page_size);
+            items.sort_by_key(&state.key);
+            if options.direction == PaginationDirection::Backward {
+                items.reverse();
+            }
+
+            state.done = items.is_empty() || !page.has_more.unwrap_or(full);
+            state.cursor = items.last().map(&state.key).or(state.cursor);
+            state.buffer.extend(items);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    /// Pages through the numbers up to 10 like an endpoint with `before` and `after` cursors.
+    async fn walk(options: PaginationOptions) -> (Vec<u64>, Vec<Option<u64>>) {
+        let mut cursors = Vec::new();
+        let items = paginate(
+            options,
+            |n: &u64| *n,
+            |page: PageRequest<u64>| {
+                cursors.push(page.cursor);
+                let numbers = (1..=10).filter(|n| match (page.direction, page.cursor) {
+                    (_, None) => true,
+                    (PaginationDirection::Backward, Some(before)) => *n < before,
+                    (PaginationDirection::Forward, Some(after)) => *n > after,
+                });
+                let numbers: Vec<_> = match page.direction {
+                    PaginationDirection::Backward => {
+                        numbers.rev().take(page.limit.into()).collect()
+                    },
+                    PaginationDirection::Forward => numbers.take(page.limit.into()).collect(),
+                };
+
+                async move { Ok(Page::new(numbers)) }
+            },
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+        (items, cursors)
+    }
+
+    #[tokio::test]
+    async fn walks_both_directions() {
+        let (items, cursors) = walk(PaginationOptions::new().page_size(4)).await;
+        assert_eq!(items, [10, 9, 8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(cursors, [None, Some(7), Some(3)]);
+
+        let options = PaginationOptions::new().page_size(5).direction(PaginationDirection::Forward);
+        let (items, cursors) = walk(options).await;
+        assert_eq!(items, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        // The second page is full, so one more page is needed to find out it was the last.
+        assert_eq!(cursors, [None, Some(5), Some(10)]);
+    }
+
+    #[tokio::test]
+    async fn stops_when_told_there_are_no_more() {
+        let mut calls = 0;
+        let items: Vec<_> = paginate(
+            PaginationOptions::new().page_size(2),
+            |n: &u64| *n,
+            |_| {
+                calls += 1;
+                async { Ok(Page::new(vec![2, 1]).has_more(false)) }
+            },
+        )
+        .collect()
+        .await;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(calls, 1);
+    }
+}
+
+#[cfg(all(test, feature = "http_mock", feature = "model"))]
+mod endpoint_tests {
+    use futures::StreamExt;
+    use percent_encoding::percent_decode_str;
+
+    use super::*;
+    use crate::http::mock::{MockRequest, MockResponse, MockServer};
+    use crate::http::{LightMethod, Route};
+    use crate::json::{json, Value};
+    use crate::model::prelude::*;
+
+    /// The order items are returned in by an endpoint when no cursor is given.
+    #[derive(Clone, Copy)]
+    enum FirstPage {
+        Oldest,
+        Newest,
+    }
+
+    /// Answers like a cursor endpoint over the IDs 1 to `total`, which accepts at most `max` items
+    /// per page. The page is built by `item`, with whether there are more items.
+    fn mock_endpoint(
+        server: &MockServer,
+        route: Route<'_>,
+        total: u64,
+        max: u64,
+        first_page: FirstPage,
+        item: impl Fn(u64) -> Value + Send + Sync + 'static,
+        page: impl Fn(Vec<Value>, bool) -> Value + Send + Sync + 'static,
+    ) {
+        server.mock_with(LightMethod::Get, route, move |request: &MockRequest| {
+            let (mut before, mut after, mut limit) = (None, None, 50);
+            for (name, value) in
+                request.query.as_deref().unwrap_or("").split('&').filter_map(|p| p.split_once('='))
+            {
+                let value = percent_decode_str(value).decode_utf8_lossy();
+                // Thread cursors are archive timestamps, which are in seconds since the epoch here.
+                let value = value
+                    .parse()
+                    .unwrap_or_else(|_| Timestamp::parse(&value).unwrap().unix_timestamp() as u64);
+                match name {
+                    "before" => before = Some(value),
+                    "after" => after = Some(value),
+                    "limit" => limit = value,
+                    _ => {},
+                }
+            }
+            assert!(limit <= max, "requested {limit} items, over the maximum of {max}");
+
+            let ids = (1..=total)
+                .filter(|id| before.map_or(true, |b| *id < b) && after.map_or(true, |a| *id > a));
+            let newest =
+                before.is_some() || (after.is_none() && matches!(first_page, FirstPage::Newest));
+            let mut ids: Vec<_> = if newest {
+                ids.rev().take(limit as usize).collect()
+            } else {
+                ids.take(limit as usize).collect()
+            };
+            let has_more = ids.len() as u64 == limit;
+            ids.sort_unstable();
+
+            MockResponse::json(&page(ids.into_iter().map(&item).collect(), has_more))
+        });
+    }
+
+    fn user(id: u64) -> Value {
+        json!({"id": id.to_string(), "username": format!("user{id}"), "discriminator": null})
+    }
+
+    fn list(items: Vec<Value>, _: bool) -> Value {
+        Value::from(items)
+    }
+
+    /// Collects the IDs of the items of a stream, which is boxed as the requests it makes are
+    /// large.
+    fn collect<T>(
+        stream: impl Stream<Item = Result<T>>,
+        id: impl Fn(&T) -> u64,
+    ) -> impl Future<Output = Vec<u64>> {
+        Box::pin(stream).map(move |item| id(&item.unwrap())).collect()
+    }
+
+    fn newest_first(total: u64) -> Vec<u64> {
+        (1..=total).rev().collect()
+    }
+
+    fn oldest_first(total: u64) -> Vec<u64> {
+        (1..=total).collect()
+    }
+
+    /// Over the maximum page size of every endpoint.
+    fn options() -> PaginationOptions {
+        PaginationOptions::new().page_size(1000)
+    }
+
+    fn forward() -> PaginationOptions {
+        options().direction(PaginationDirection::Forward)
+    }
+
+    #[tokio::test]
+    async fn bans_iter() {
+        let server = MockServer::start().await.unwrap();
+        let guild_id = GuildId::new(1);
+        let route = Route::GuildBans {
+            guild_id,
+        };
+        let ban = |id| json!({"reason": null, "user": user(id)});
+        mock_endpoint(&server, route, 600, 255, FirstPage::Oldest, ban, list);
+
+        let http = server.http();
+        let id = |ban: &Ban| ban.user.id.get();
+        assert_eq!(collect(guild_id.bans_iter(&http, options()), id).await, newest_first(600));
+        assert_eq!(collect(guild_id.bans_iter(&http, forward()), id).await, oldest_first(600));
+        assert_eq!(
+            collect(guild_id.bans_iter(&http, PaginationOptions::new()), id).await,
+            newest_first(600)
+        );
+    }
+
+    #[tokio::test]
+    async fn audit_logs_iter() {
+        let server = MockServer::start().await.unwrap();
+        let guild_id = GuildId::new(1);
+        let route = Route::GuildAuditLogs {
+            guild_id,
+        };
+        let entry = |id: u64| json!({"id": id.to_string(), "action_type": 1, "user_id": "1"});
+        let logs = |entries, _| {
+            json!({
+                "audit_log_entries": entries,
+                "auto_moderation_rules": [],
+                "application_commands": [],
+                "guild_scheduled_events": [],
+                "integrations": [],
+                "threads": [],
+                "users": [],
+                "webhooks": [],
+            })
+        };
+        mock_endpoint(&server, route, 250, 100, FirstPage::Newest, entry, logs);
+
+        let http = server.http();
+        let entries = guild_id.audit_logs_iter(&http, None, None, forward());
+        assert_eq!(collect(entries, |entry| entry.id.get()).await, newest_first(250));
+    }
+
+    #[tokio::test]
+    async fn guilds_iter() {
+        let server = MockServer::start().await.unwrap();
+        let guild = |id: u64| {
+            json!({
+                "id": id.to_string(),
+                "name": "guild",
+                "icon": null,
+                "owner": false,
+                "permissions": "0",
+                "features": [],
+            })
+        };
+        mock_endpoint(&server, Route::UserMeGuilds, 450, 200, FirstPage::Oldest, guild, list);
+
+        let http = server.http();
+        let user = CurrentUser::default();
+        let id = |guild: &GuildInfo| guild.id.get();
+        assert_eq!(collect(user.guilds_iter(&http, options()), id).await, newest_first(450));
+        assert_eq!(collect(user.guilds_iter(&http, forward()), id).await, oldest_first(450));
+    }
+
+    #[tokio::test]
+    async fn scheduled_event_users_iter() {
+        let server = MockServer::start().await.unwrap();
+        let (guild_id, event_id) = (GuildId::new(1), ScheduledEventId::new(2));
+        let route = Route::GuildScheduledEventUsers {
+            guild_id,
+            event_id,
+        };
+        let event_user = |id| json!({"guild_scheduled_event_id": "2", "user": user(id)});
+        mock_endpoint(&server, route, 250, 100, FirstPage::Oldest, event_user, list);
+
+        let http = server.http();
+        let id = |user: &ScheduledEventUser| user.user.id.get();
+        let users = event_id.users_iter(&http, guild_id, None, options());
+        assert_eq!(collect(users, id).await, newest_first(250));
+        let users = event_id.users_iter(&http, guild_id, None, forward());
+        assert_eq!(collect(users, id).await, oldest_first(250));
+    }
+
+    #[tokio::test]
+    async fn entitlements_iter() {
+        let server = MockServer::start().await.unwrap();
+        let application_id = ApplicationId::new(1);
+        let route = Route::Entitlements {
+            application_id,
+        };
+        let entitlement = |id: u64| {
+            json!({
+                "id": id.to_string(),
+                "sku_id": "1",
+                "application_id": "1",
+                "type": 8,
+                "deleted": false,
+            })
+        };
+        mock_endpoint(&server, route, 250, 100, FirstPage::Oldest, entitlement, list);
+
+        let http = server.http();
+        let id = |entitlement: &Entitlement| entitlement.id.get();
+        let entitlements = application_id.entitlements_iter(&http, None, None, options());
+        assert_eq!(collect(entitlements, id).await, newest_first(250));
+        let entitlements = application_id.entitlements_iter(&http, None, None, forward());
+        assert_eq!(collect(entitlements, id).await, oldest_first(250));
+    }
+
+    #[tokio::test]
+    async fn reaction_users_iter() {
+        let server = MockServer::start().await.unwrap();
+        let (channel_id, message_id) = (ChannelId::new(1), MessageId::new(2));
+        let reaction = ReactionType::Unicode("x".to_string());
+        let data = reaction.as_data();
+        let route = Route::ChannelMessageReactionEmoji {
+            channel_id,
+            message_id,
+            reaction: &data,
+        };
+        mock_endpoint(&server, route, 250, 100, FirstPage::Oldest, user, list);
+
+        let http = server.http();
+        let users = message_id.reaction_users_iter(&http, channel_id, reaction, options());
+        assert_eq!(collect(users, |user| user.id.get()).await, oldest_first(250));
+    }
+
+    #[tokio::test]
+    async fn archived_threads_iter() {
+        let server = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        let thread = |id: u64| {
+            let archived_at = Timestamp::from_unix_timestamp(id as i64).unwrap();
+            json!({
+                "id": id.to_string(),
+                "guild_id": "1",
+                "type": 11,
+                "name": "thread",
+                "permission_overwrites": [],
+                "thread_metadata": {
+                    "archived": true,
+                    "archive_timestamp": archived_at,
+                    "auto_archive_duration": 60,
+                    "locked": false,
+                },
+            })
+        };
+        let threads =
+            |threads, has_more| json!({"threads": threads, "members": [], "has_more": has_more});
+        let routes = [
+            Route::ChannelArchivedPublicThreads {
+                channel_id,
+            },
+            Route::ChannelArchivedPrivateThreads {
+                channel_id,
+            },
+            Route::ChannelJoinedPrivateThreads {
+                channel_id,
+            },
+        ];
+        for route in routes {
+            mock_endpoint(&server, route, 250, 100, FirstPage::Newest, thread, threads);
+        }
+
+        let http = server.http();
+        let id = |thread: &GuildChannel| thread.id.get();
+        let public = channel_id.archived_public_threads_iter(&http, options().page_size(100));
+        assert_eq!(collect(public, id).await, newest_first(250));
+        let private = channel_id.archived_private_threads_iter(&http, options().page_size(100));
+        assert_eq!(collect(private, id).await, newest_first(250));
+        let joined =
+            channel_id.joined_archived_private_threads_iter(&http, options().page_size(100));
+        assert_eq!(collect(joined, id).await, newest_first(250));
+    }
+}
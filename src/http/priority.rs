@@ -0,0 +1,224 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+tokio::task_local! {
+    static PRIORITY: RequestPriority;
+}
+
+/// The priority of a request, deciding which request goes first when several are waiting for a
+/// ratelimit.
+///
+/// The priority of a request is, in order:
+/// 1. the priority of the [`RequestPriority::scope`] it is made in, if any;
+/// 2. the priority returned by the callback set with [`Ratelimiter::set_route_priority`], if any;
+/// 3. the default priority of its route, see [`Route::priority`].
+///
+/// Requests waiting for the same bucket, or for the global ratelimit, are then served from the
+/// highest priority to the lowest, and in the order they arrived for the same priority.
+///
+/// [`Ratelimiter::set_route_priority`]: super::Ratelimiter::set_route_priority
+/// [`Route::priority`]: super::Route::priority
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum RequestPriority {
+    /// For background work, such as bulk syncing roles.
+    Low,
+    #[default]
+    Normal,
+    /// For requests that have to be made quickly, such as responses to interactions.
+    High,
+}
+
+impl RequestPriority {
+    /// Makes every request in the given future with this priority.
+    ///
+    /// The priority only applies to requests made by the future itself, not to tasks it spawns.
+    ///
+    /// # Examples
+    ///
+    /// Sync roles without delaying more important requests:
+    ///
+    /// ```rust,no_run
+    /// # use serenity::http::Http;
+    /// # use serenity::model::id::{GuildId, RoleId, UserId};
+    /// # async fn run(http: &Http, guild_id: GuildId, role_id: RoleId, members: Vec<UserId>) {
+    /// use serenity::http::RequestPriority;
+    ///
+    /// RequestPriority::Low
+    ///     .scope(async {
+    ///         for user_id in members {
+    ///             http.add_member_role(guild_id, user_id, role_id, None).await?;
+    ///         }
+    ///         Ok::<_, serenity::Error>(())
+    ///     })
+    ///     .await;
+    /// # }
+    /// ```
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        PRIORITY.scope(self, future).await
+    }
+
+    /// Returns the priority of the [`Self::scope`] the current task is in, if any.
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        PRIORITY.try_with(|priority| *priority).ok()
+    }
+}
+
+/// A lock handed to waiters from the highest priority to the lowest, and in the order they arrived
+/// for the same priority.
+#[derive(Debug, Default)]
+pub(super) struct PriorityGate {
+    state: Mutex<GateState>,
+}
+
+#[derive(Debug, Default)]
+struct GateState {
+    locked: bool,
+    next_sequence: u64,
+    waiters: BinaryHeap<Waiter>,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    priority: RequestPriority,
+    sequence: u64,
+    sender: oneshot::Sender<GateGuard>,
+}
+
+impl Waiter {
+    fn key(&self) -> (RequestPriority, Reverse<u64>) {
+        (self.priority, Reverse(self.sequence))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PriorityGate {
+    /// Waits for the turn of a request with the given priority. The gate is released when the
+    /// returned guard is dropped.
+    pub async fn lock(self: &Arc<Self>, priority: RequestPriority) -> GateGuard {
+        let receiver = {
+            let mut state = self.state.lock().expect("poisoned priority gate");
+            if !state.locked {
+                state.locked = true;
+                return GateGuard::new(self);
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let sequence = state.next_sequence;
+            state.next_sequence += 1;
+            state.waiters.push(Waiter {
+                priority,
+                sequence,
+                sender,
+            });
+            receiver
+        };
+
+        // Waiters are only dropped once their receiver is gone, so a guard is always sent.
+        receiver.await.expect("priority gate waiter dropped")
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().expect("poisoned priority gate");
+
+        // The guard is handed over directly, so the gate stays locked in between. A waiter that
+        // stopped waiting gives its guard back, which must not release the gate again.
+        while let Some(waiter) = state.waiters.pop() {
+            match waiter.sender.send(GateGuard::new(self)) {
+                Ok(()) => return,
+                Err(mut guard) => guard.gate = None,
+            }
+        }
+
+        state.locked = false;
+    }
+}
+
+/// Holds a [`PriorityGate`] until dropped.
+#[derive(Debug)]
+pub(super) struct GateGuard {
+    gate: Option<Arc<PriorityGate>>,
+}
+
+impl GateGuard {
+    fn new(gate: &Arc<PriorityGate>) -> Self {
+        Self {
+            gate: Some(Arc::clone(gate)),
+        }
+    }
+}
+
+impl Drop for GateGuard {
+    fn drop(&mut self) {
+        if let Some(gate) = self.gate.take() {
+            gate.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_highest_priority_first() {
+        let gate = Arc::new(PriorityGate::default());
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let held = gate.lock(RequestPriority::Normal).await;
+
+        let mut tasks = Vec::new();
+        for (name, priority) in [
+            ("low", RequestPriority::Low),
+            ("first normal", RequestPriority::Normal),
+            ("high", RequestPriority::High),
+            ("second normal", RequestPriority::Normal),
+        ] {
+            let (gate, order) = (Arc::clone(&gate), Arc::clone(&order));
+            tasks.push(tokio::spawn(async move {
+                let _guard = gate.lock(priority).await;
+                order.lock().unwrap().push(name);
+            }));
+            // Lets the task start waiting before the next one is spawned.
+            tokio::task::yield_now().await;
+        }
+
+        // A waiter that gives up is skipped.
+        tasks.remove(0).abort();
+        tokio::task::yield_now().await;
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), ["high", "first normal", "second normal"]);
+        assert_eq!(RequestPriority::current(), None);
+        let scoped = RequestPriority::High.scope(async { RequestPriority::current() }).await;
+        assert_eq!(scoped, Some(RequestPriority::High));
+    }
+}
@@ -0,0 +1,463 @@
+//! A proxy for the Discord REST API, ratelimiting the requests of any number of local clients.
+//!
+//! Every service making requests with the same bot token shares its ratelimits, so they should
+//! all go through a single gatekeeper. The [`HttpProxy`] accepts plain Discord REST requests over
+//! HTTP/1.1, and forwards them upstream through a single [`Ratelimiter`], waiting for the bucket
+//! of each request and for the global ratelimit as needed.
+//!
+//! The proxy makes every request with its own token, replacing any `Authorization` header sent by
+//! a client, so it must only be reachable by trusted clients.
+//!
+//! Clients can be pointed at the proxy with [`HttpBuilder::proxy`]. Since the proxy already
+//! ratelimits their requests, they may also disable their own ratelimiter with
+//! [`HttpBuilder::ratelimiter_disabled`].
+//!
+//! Requests waiting for a ratelimit are served by priority, read from the `x-serenity-priority`
+//! header as `low`, `normal` or `high`. Interaction responses are high priority by default, and
+//! clients can set the header with a [`Middleware`].
+//!
+//! The proxy is shipped as the `serenity-http-proxy` binary, which reads the bot token from the
+//! `DISCORD_TOKEN` environment variable and takes the address to listen on as its only argument,
+//! defaulting to `127.0.0.1:3000`.
+//!
+//! # Examples
+//!
+//! Running the proxy in a process:
+//!
+//! ```rust,no_run
+//! use serenity::http::proxy::HttpProxy;
+//! use serenity::http::Ratelimiter;
+//! use tokio::net::TcpListener;
+//!
+//! # async fn run() -> serenity::Result<()> {
+//! let ratelimiter = Ratelimiter::new(reqwest::Client::new(), "Bot token");
+//! let listener = TcpListener::bind("127.0.0.1:3000").await?;
+//! HttpProxy::new(ratelimiter).serve(listener).await
+//! # }
+//! ```
+//!
+//! Sending requests through it:
+//!
+//! ```rust,no_run
+//! use serenity::http::HttpBuilder;
+//!
+//! let http =
+//!     HttpBuilder::new("token").proxy("http://127.0.0.1:3000").ratelimiter_disabled(true).build();
+//! ```
+//!
+//! [`Ratelimiter`]: super::Ratelimiter
+//! [`Middleware`]: super::Middleware
+//! [`HttpBuilder::proxy`]: super::HttpBuilder::proxy
+//! [`HttpBuilder::ratelimiter_disabled`]: super::HttpBuilder::ratelimiter_disabled
+
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+
+use reqwest::header::AUTHORIZATION;
+use reqwest::Url;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, instrument, warn};
+
+use super::server::{self, RawRequest, ReadRequest, DEFAULT_MAX_BODY_SIZE};
+use super::{
+    HttpError,
+    LightMethod,
+    Ratelimiter,
+    RatelimitingBucket,
+    RequestOptions,
+    RequestPriority,
+    StatusCode,
+};
+use crate::internal::prelude::*;
+use crate::internal::tokio::spawn_named;
+use crate::json::{json, to_vec};
+
+/// The header clients can set the [`RequestPriority`] of their requests with.
+const PRIORITY_HEADER: &str = "x-serenity-priority";
+
+/// Headers that only apply to a single connection, or only to the proxy, and so are not forwarded.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    PRIORITY_HEADER,
+];
+
+/// The most routes the proxy keeps ratelimits for. Requests to any other route share a single
+/// bucket, so that clients can't grow the memory of the proxy without bound.
+const MAX_ROUTES: usize = 1024;
+
+/// The bucket shared by the requests to routes past [`MAX_ROUTES`].
+const OVERFLOW_ROUTE: &str = "overflow";
+
+/// The status, headers and body of a response to send back to a client.
+type ProxyResponse = (StatusCode, Vec<(String, String)>, Vec<u8>);
+
+/// Forwards requests to the Discord REST API within the ratelimits of a single token.
+///
+/// Refer to the [module-level documentation] for an overview.
+///
+/// [module-level documentation]: self
+#[derive(Debug)]
+pub struct HttpProxy {
+    ratelimiter: Ratelimiter,
+    upstream: String,
+    max_body_size: usize,
+    /// The names of the routes requests were made to, see [`Self::bucket`].
+    routes: Mutex<HashSet<&'static str>>,
+}
+
+impl HttpProxy {
+    /// Creates a proxy making its requests with the client and token of the given ratelimiter.
+    /// Call [`Self::serve`] to start accepting clients.
+    #[must_use]
+    pub fn new(ratelimiter: Ratelimiter) -> Self {
+        Self {
+            ratelimiter,
+            upstream: "https://discord.com".into(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            routes: Mutex::default(),
+        }
+    }
+
+    /// Sets the base URL requests are forwarded to. Defaults to `https://discord.com`.
+    #[must_use]
+    pub fn upstream(mut self, upstream: impl Into<String>) -> Self {
+        self.upstream = upstream.into();
+        self
+    }
+
+    /// Sets the size of the largest request body accepted from clients, in bytes. Larger requests
+    /// are answered with a `413 Payload Too Large` before their body is read, and their connection
+    /// is closed. Defaults to 100 MiB.
+    #[must_use]
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Accepts clients over a TCP socket until accepting fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if a connection could not be accepted.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        let proxy = Arc::new(self);
+
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            debug!("[HTTP Proxy] Accepted connection from {}", addr);
+
+            spawn_named("http::proxy::connection", Arc::clone(&proxy).handle(stream));
+        }
+    }
+
+    /// Answers the requests of a connection until it is closed.
+    #[instrument(skip(self, stream))]
+    async fn handle(self: Arc<Self>, stream: TcpStream) {
+        let (read, mut write) = stream.into_split();
+        let mut reader = BufReader::new(read);
+
+        loop {
+            let request = match server::read_request(&mut reader, self.max_body_size).await {
+                Ok(ReadRequest::Request(request)) => request,
+                Ok(ReadRequest::TooLarge) => {
+                    let (status, headers, body) =
+                        error_response(StatusCode::PAYLOAD_TOO_LARGE, "413: Payload Too Large");
+                    if let Err(why) =
+                        server::write_response(&mut write, status, &headers, &body).await
+                    {
+                        warn!("[HTTP Proxy] Err writing response: {:?}", why);
+                    }
+                    break;
+                },
+                Ok(ReadRequest::Closed) => break,
+                Err(why) => {
+                    warn!("[HTTP Proxy] Err reading request: {:?}", why);
+                    break;
+                },
+            };
+
+            let (status, headers, body) = match self.forward(request).await {
+                Ok(response) => response,
+                Err(why) => {
+                    warn!("[HTTP Proxy] Err forwarding request: {:?}", why);
+                    error_response(StatusCode::BAD_GATEWAY, &why.to_string())
+                },
+            };
+
+            if let Err(why) = server::write_response(&mut write, status, &headers, &body).await {
+                warn!("[HTTP Proxy] Err writing response: {:?}", why);
+                break;
+            }
+        }
+    }
+
+    /// Forwards a request upstream within its ratelimits, returning the upstream response.
+    async fn forward(&self, request: RawRequest) -> Result<ProxyResponse> {
+        let Some(method) = request.light_method() else {
+            let message = format!("405: Method {} Not Allowed", request.method);
+            return Ok(error_response(StatusCode::METHOD_NOT_ALLOWED, &message));
+        };
+
+        let mut url = format!("{}{}", self.upstream.trim_end_matches('/'), request.path);
+        if let Some(query) = &request.query {
+            url.push('?');
+            url.push_str(query);
+        }
+        let url = Url::parse(&url).map_err(HttpError::Url)?;
+
+        let bucket = self.bucket(&ProxyRoute::from_path(method, &request.path));
+        let priority = request_priority(&request);
+        debug!("[HTTP Proxy] {:?} {} in bucket {}", method, request.path, bucket);
+
+        let response = self
+            .ratelimiter
+            .perform_raw(
+                &bucket,
+                method,
+                url.as_str(),
+                priority,
+                RequestOptions::default(),
+                |client, token| {
+                    let mut builder = client
+                        .request(method.reqwest_method(), url.clone())
+                        .header(AUTHORIZATION, token)
+                        .body(request.body.clone());
+
+                    for (name, value) in &request.headers {
+                        if name != "authorization" && !HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+                            builder = builder.header(name, value);
+                        }
+                    }
+
+                    Ok(builder.build()?)
+                },
+            )
+            .await?;
+
+        let status = response.status();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| !HOP_BY_HOP_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        let body = response.bytes().await?.to_vec();
+
+        Ok((status, headers, body))
+    }
+
+    /// Returns the bucket of a route in the ratelimiter.
+    ///
+    /// Buckets are keyed by a `'static` name, so the template of every route is leaked the first
+    /// time a request is made to it, up to [`MAX_ROUTES`]. Templates start with the method, so
+    /// they never collide with the names of [`Route`]s.
+    ///
+    /// [`Route`]: super::Route
+    fn bucket(&self, route: &ProxyRoute) -> RatelimitingBucket {
+        let mut routes = self.routes.lock().expect("poisoned proxy routes");
+        let name = match routes.get(route.template.as_str()) {
+            Some(name) => *name,
+            None if routes.len() < MAX_ROUTES => {
+                let name: &'static str = Box::leak(route.template.clone().into_boxed_str());
+                routes.insert(name);
+                name
+            },
+            None => OVERFLOW_ROUTE,
+        };
+
+        RatelimitingBucket::from_name(name, route.major)
+    }
+}
+
+/// The route of a request that is only known by its method and path, such as
+/// `/api/v10/channels/381880193251409931/messages`.
+struct ProxyRoute {
+    /// The method and path of the request, where every ID, token or emoji after the major
+    /// parameter is replaced by a placeholder, so that requests to the same endpoint share it.
+    template: String,
+    major: Option<NonZeroU64>,
+}
+
+impl ProxyRoute {
+    /// Reads the route of a request from its path, where the major parameter is at the start.
+    fn from_path(method: LightMethod, path: &str) -> Self {
+        let mut segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        if segments.first() == Some(&"api") {
+            let versioned = segments.get(1).is_some_and(|version| {
+                version.strip_prefix('v').is_some_and(|v| v.bytes().all(|b| b.is_ascii_digit()))
+            });
+            segments.drain(..if versioned { 2 } else { 1 });
+        }
+
+        let mut template = format!("{} ", method.reqwest_method());
+        let mut major = None;
+        for (i, &segment) in segments.iter().enumerate() {
+            let previous = |n: usize| i.checked_sub(n).map(|j| segments[j]);
+            let placeholder = if i == 1 && matches!(segments[0], "channels" | "guilds" | "webhooks")
+            {
+                major = segment.parse().ok();
+                major.map(|_| "{major}")
+            } else if segment.parse::<u64>().is_ok() {
+                Some("{id}")
+            } else if matches!(previous(1), Some("reactions" | "invites" | "templates"))
+                || matches!(previous(2), Some("webhooks" | "interactions"))
+            {
+                Some("{param}")
+            } else {
+                None
+            };
+
+            template.push('/');
+            template.push_str(placeholder.unwrap_or(segment));
+        }
+
+        Self {
+            template,
+            major,
+        }
+    }
+}
+
+/// Returns the priority set by the client, or the priority of the route the request is for.
+fn request_priority(request: &RawRequest) -> RequestPriority {
+    let header = request.headers.iter().find(|(name, _)| name == PRIORITY_HEADER);
+    match header.map(|(_, value)| value.to_ascii_lowercase()).as_deref() {
+        Some("low") => RequestPriority::Low,
+        Some("normal") => RequestPriority::Normal,
+        Some("high") => RequestPriority::High,
+        _ if request.path.contains("/interactions/") => RequestPriority::High,
+        _ => RequestPriority::Normal,
+    }
+}
+
+/// Builds a response in the format of Discord's JSON errors.
+fn error_response(status: StatusCode, message: &str) -> ProxyResponse {
+    let body = to_vec(&json!({
+        "code": 0,
+        "message": message,
+    }))
+    .unwrap_or_default();
+
+    (status, vec![("content-type".into(), "application/json".into())], body)
+}
+
+#[cfg(all(test, feature = "http_mock"))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::http::mock::{MockResponse, MockServer};
+    use crate::http::{HttpBuilder, LightMethod, Route};
+    use crate::model::id::{ChannelId, MessageId};
+
+    #[tokio::test]
+    async fn ratelimits_clients_together() {
+        let upstream = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        let route = Route::ChannelMessage {
+            channel_id,
+            message_id: MessageId::new(2),
+        };
+        upstream.mock(LightMethod::Delete, route, MockResponse::no_content());
+        upstream.ratelimit(LightMethod::Delete, route, 1, Duration::from_millis(300));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let ratelimiter = Ratelimiter::new(reqwest::Client::new(), "Bot proxy-token");
+        let proxy = HttpProxy::new(ratelimiter).upstream(upstream.url());
+        tokio::spawn(proxy.serve(listener));
+
+        // Neither client ratelimits its own requests, the proxy does it for both.
+        let client = || {
+            HttpBuilder::new("client-token").proxy(&proxy_url).ratelimiter_disabled(true).build()
+        };
+        let (first, second) = (client(), client());
+
+        let start = Instant::now();
+        first.delete_message(channel_id, MessageId::new(2), None).await.unwrap();
+        second.delete_message(channel_id, MessageId::new(2), None).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(250));
+
+        let requests = upstream.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| request.is(LightMethod::Delete, route)));
+        assert!(requests
+            .iter()
+            .all(|request| request.header("authorization") == Some("Bot proxy-token")));
+
+        // Methods Discord doesn't use are refused without reaching upstream.
+        let response = reqwest::Client::new()
+            .request(reqwest::Method::OPTIONS, format!("{proxy_url}/api/v10/gateway"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(upstream.requests().len(), 2);
+    }
+
+    #[test]
+    fn buckets_from_paths() {
+        let proxy = HttpProxy::new(Ratelimiter::new(reqwest::Client::new(), "Bot proxy-token"));
+        let bucket =
+            |path: &str| proxy.bucket(&ProxyRoute::from_path(LightMethod::Get, path)).to_string();
+
+        assert_eq!(
+            bucket("/api/v10/channels/1/messages/2"),
+            "GET /channels/{major}/messages/{id}/1"
+        );
+        assert_eq!(bucket("/api/v10/channels/1/messages/3"), bucket("/channels/1/messages/2"));
+        assert_ne!(bucket("/api/v10/channels/1/messages/2"), bucket("/channels/4/messages/2"));
+        assert_eq!(
+            bucket("/api/v10/channels/1/messages/2/reactions/%F0%9F%91%8D/@me"),
+            "GET /channels/{major}/messages/{id}/reactions/{param}/@me/1"
+        );
+        assert_eq!(bucket("/api/v10/webhooks/5/secret-token"), "GET /webhooks/{major}/{param}/5");
+        assert_eq!(bucket("/api/v10/invites/abc"), "GET /invites/{param}");
+
+        // Past the limit, new routes share a bucket instead of each getting one.
+        for i in 0..MAX_ROUTES {
+            bucket(&format!("/api/v10/applications/{i}-{i}"));
+        }
+        assert_eq!(bucket("/api/v10/unknown-route"), OVERFLOW_ROUTE);
+        assert_eq!(bucket("/api/v10/invites/abc"), "GET /invites/{param}");
+    }
+
+    #[tokio::test]
+    async fn refuses_large_bodies() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let upstream = MockServer::start().await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ratelimiter = Ratelimiter::new(reqwest::Client::new(), "Bot proxy-token");
+        let proxy = HttpProxy::new(ratelimiter).upstream(upstream.url()).max_body_size(16);
+        tokio::spawn(proxy.serve(listener));
+
+        // Neither body is allocated, let alone read, before the request is refused.
+        let heads = [
+            "content-length: 1099511627776\r\n\r\n",
+            "transfer-encoding: chunked\r\n\r\nffffffffff\r\n",
+        ];
+        for head in heads {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!("POST /api/v10/channels/1/messages HTTP/1.1\r\n{head}");
+            stream.write_all(request.as_bytes()).await.unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 413"), "{response}");
+        }
+
+        assert!(upstream.requests().is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+//! Sharing of HTTP ratelimits between processes.
+//!
+//! Every process making requests with the same token draws from the same ratelimit buckets on
+//! Discord's side, but each [`Ratelimiter`] only knows about its own requests. A
+//! [`RatelimitDaemon`] keeps the buckets of all processes in one place instead, and each process
+//! uses a [`UnixRatelimitStore`] to take its tickets from it.
+//!
+//! The daemon and its clients talk over a Unix socket, using newline-delimited JSON messages. The
+//! daemon is shipped as the `serenity-ratelimit-daemon` binary, which takes the path of the socket
+//! as its only argument, but it can also be run inside any process.
+//!
+//! # Examples
+//!
+//! Running the daemon in a process:
+//!
+//! ```rust,no_run
+//! use serenity::http::ratelimit_daemon::RatelimitDaemon;
+//! use tokio::net::UnixListener;
+//!
+//! # async fn run() -> serenity::Result<()> {
+//! let listener = UnixListener::bind("/tmp/serenity-ratelimits.sock")?;
+//! RatelimitDaemon::new().serve(listener).await
+//! # }
+//! ```
+//!
+//! Using it from every bot process:
+//!
+//! ```rust,no_run
+//! use serenity::http::ratelimit_daemon::UnixRatelimitStore;
+//! use serenity::http::HttpBuilder;
+//!
+//! let http = HttpBuilder::new("token")
+//!     .ratelimit_store(UnixRatelimitStore::new("/tmp/serenity-ratelimits.sock"))
+//!     .build();
+//! ```
+//!
+//! [`Ratelimiter`]: super::Ratelimiter
+
+use std::collections::HashMap;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, instrument, warn};
+
+use super::{Ratelimit, RatelimitHeaders, RatelimitStore, RatelimitWait, RatelimitingBucket};
+use crate::internal::prelude::*;
+use crate::internal::tokio::spawn_named;
+use crate::json::{from_str, to_vec};
+
+/// A request sent by a [`UnixRatelimitStore`] to the daemon.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "op", content = "d", rename_all = "snake_case")]
+enum StoreRequest {
+    Acquire { bucket: String },
+    Update { bucket: String, headers: RatelimitHeaders },
+    GlobalWait,
+    SetGlobal { retry_after: Duration },
+}
+
+/// The response of the daemon to a [`StoreRequest`] of the same name.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "op", content = "d", rename_all = "snake_case")]
+enum StoreResponse {
+    Acquire(Option<RatelimitWait>),
+    Update,
+    GlobalWait(Option<Duration>),
+    SetGlobal,
+}
+
+async fn write_line<T: serde::Serialize>(stream: &mut UnixStream, message: &T) -> Result<()> {
+    let mut bytes = to_vec(message)?;
+    bytes.push(b'\n');
+
+    stream.write_all(&bytes).await?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct State {
+    buckets: HashMap<String, Ratelimit>,
+    global: Option<SystemTime>,
+}
+
+/// Keeps the ratelimits of every process connected to it through a [`UnixRatelimitStore`].
+///
+/// Refer to the [module-level documentation] for an overview.
+///
+/// [module-level documentation]: self
+#[derive(Debug, Default)]
+pub struct RatelimitDaemon {
+    state: Mutex<State>,
+}
+
+impl RatelimitDaemon {
+    /// Creates a new daemon. Call [`Self::serve`] to start accepting clients.
+    #[must_use]
+    pub fn new() -> Arc<Self> {
+        Arc::default()
+    }
+
+    /// Accepts clients over a Unix socket until accepting fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if a connection could not be accepted.
+    pub async fn serve(self: Arc<Self>, listener: UnixListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            debug!("[Ratelimit Daemon] Accepted connection");
+
+            spawn_named("http::ratelimit_daemon::connection", Arc::clone(&self).handle(stream));
+        }
+    }
+
+    #[instrument(skip(self, stream))]
+    async fn handle(self: Arc<Self>, stream: UnixStream) {
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            match stream.read_line(&mut line).await {
+                Ok(0) => break,
+                Ok(_) => {},
+                Err(why) => {
+                    warn!("[Ratelimit Daemon] Err reading from client: {:?}", why);
+                    break;
+                },
+            }
+
+            let request = match from_str(line.trim_end()) {
+                Ok(request) => request,
+                Err(why) => {
+                    warn!("[Ratelimit Daemon] Err decoding request: {:?}", why);
+                    break;
+                },
+            };
+
+            let response = self.respond(request);
+            if let Err(why) = write_line(stream.get_mut(), &response).await {
+                warn!("[Ratelimit Daemon] Err writing response: {:?}", why);
+                break;
+            }
+        }
+    }
+
+    fn respond(&self, request: StoreRequest) -> StoreResponse {
+        let mut state = self.state.lock().expect("poisoned ratelimit daemon state");
+
+        match request {
+            StoreRequest::Acquire {
+                bucket,
+            } => {
+                let ratelimit = state.buckets.entry(bucket).or_default();
+                let wait =
+                    ratelimit.acquire().map(|delay| RatelimitWait::new(delay, ratelimit.limit()));
+
+                StoreResponse::Acquire(wait)
+            },
+            StoreRequest::Update {
+                bucket,
+                headers,
+            } => {
+                state.buckets.entry(bucket).or_default().update(&headers);
+
+                StoreResponse::Update
+            },
+            StoreRequest::GlobalWait => StoreResponse::GlobalWait(
+                state.global.and_then(|until| until.duration_since(SystemTime::now()).ok()),
+            ),
+            StoreRequest::SetGlobal {
+                retry_after,
+            } => {
+                state.global = Some(SystemTime::now() + retry_after);
+
+                StoreResponse::SetGlobal
+            },
+        }
+    }
+}
+
+/// A [`RatelimitStore`] keeping its state in a [`RatelimitDaemon`], reached over a Unix socket.
+///
+/// The connection is opened on the first request, and opened again after it fails. While the
+/// daemon can't be reached, requests are made without being ratelimited.
+#[derive(Debug)]
+pub struct UnixRatelimitStore {
+    path: PathBuf,
+    connection: tokio::sync::Mutex<Option<BufReader<UnixStream>>>,
+}
+
+impl UnixRatelimitStore {
+    /// Creates a store talking to the daemon listening on the given socket.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            connection: tokio::sync::Mutex::default(),
+        }
+    }
+
+    async fn call(&self, request: StoreRequest) -> Result<StoreResponse> {
+        let mut connection = self.connection.lock().await;
+        let mut stream = match connection.take() {
+            Some(stream) => stream,
+            None => BufReader::new(UnixStream::connect(&self.path).await?),
+        };
+
+        // A connection that failed may be left with half a message in it, so it is only kept if
+        // the exchange succeeded.
+        let response = Self::exchange(&mut stream, &request).await;
+        if response.is_ok() {
+            *connection = Some(stream);
+        }
+
+        response
+    }
+
+    async fn exchange(
+        stream: &mut BufReader<UnixStream>,
+        request: &StoreRequest,
+    ) -> Result<StoreResponse> {
+        write_line(stream.get_mut(), request).await?;
+
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(IoError::from(ErrorKind::UnexpectedEof).into());
+        }
+
+        from_str(line.trim_end())
+    }
+}
+
+fn unexpected(response: &StoreResponse) -> Error {
+    let message = format!("unexpected response from the ratelimit daemon: {response:?}");
+    IoError::new(ErrorKind::InvalidData, message).into()
+}
+
+#[async_trait]
+impl RatelimitStore for UnixRatelimitStore {
+    async fn acquire(&self, bucket: &RatelimitingBucket) -> Result<Option<RatelimitWait>> {
+        match self
+            .call(StoreRequest::Acquire {
+                bucket: bucket.to_string(),
+            })
+            .await?
+        {
+            StoreResponse::Acquire(wait) => Ok(wait),
+            response => Err(unexpected(&response)),
+        }
+    }
+
+    async fn update(&self, bucket: &RatelimitingBucket, headers: RatelimitHeaders) -> Result<()> {
+        match self
+            .call(StoreRequest::Update {
+                bucket: bucket.to_string(),
+                headers,
+            })
+            .await?
+        {
+            StoreResponse::Update => Ok(()),
+            response => Err(unexpected(&response)),
+        }
+    }
+
+    async fn global_wait(&self) -> Result<Option<Duration>> {
+        match self.call(StoreRequest::GlobalWait).await? {
+            StoreResponse::GlobalWait(wait) => Ok(wait),
+            response => Err(unexpected(&response)),
+        }
+    }
+
+    async fn set_global(&self, retry_after: Duration) -> Result<()> {
+        match self
+            .call(StoreRequest::SetGlobal {
+                retry_after,
+            })
+            .await?
+        {
+            StoreResponse::SetGlobal => Ok(()),
+            response => Err(unexpected(&response)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tokio::task::JoinHandle;
+
+    use super::*;
+    use crate::http::Route;
+    use crate::model::id::ChannelId;
+
+    /// A daemon serving on a socket of its own, stopped and its socket removed once dropped.
+    struct TestDaemon {
+        path: PathBuf,
+        task: JoinHandle<Result<()>>,
+    }
+
+    impl Drop for TestDaemon {
+        fn drop(&mut self) {
+            self.task.abort();
+            drop(std::fs::remove_file(&self.path));
+        }
+    }
+
+    fn store_pair() -> (TestDaemon, UnixRatelimitStore, UnixRatelimitStore) {
+        let path = std::env::temp_dir().join(format!(
+            "serenity-ratelimits-{}-{:?}.sock",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        drop(std::fs::remove_file(&path));
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let daemon = TestDaemon {
+            task: tokio::spawn(RatelimitDaemon::new().serve(listener)),
+            path: path.clone(),
+        };
+
+        (daemon, UnixRatelimitStore::new(&path), UnixRatelimitStore::new(path))
+    }
+
+    #[tokio::test]
+    async fn shares_buckets_between_stores() {
+        let (_daemon, first, second) = store_pair();
+        let bucket = Route::ChannelMessages {
+            channel_id: ChannelId::new(1),
+        }
+        .ratelimiting_bucket();
+        let other_bucket = Route::ChannelMessages {
+            channel_id: ChannelId::new(2),
+        }
+        .ratelimiting_bucket();
+
+        assert!(first.acquire(&bucket).await.unwrap().is_none());
+        first
+            .update(&bucket, RatelimitHeaders {
+                limit: Some(5),
+                remaining: Some(0),
+                reset: Some(SystemTime::now() + Duration::from_secs(10)),
+                reset_after: Some(Duration::from_secs(10)),
+            })
+            .await
+            .unwrap();
+
+        let wait = second.acquire(&bucket).await.unwrap().unwrap();
+        assert_eq!(wait.limit, 5);
+        assert!(wait.delay > Duration::from_secs(9));
+        assert!(second.acquire(&other_bucket).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn shares_global_ratelimit() {
+        let (_daemon, first, second) = store_pair();
+
+        assert!(second.global_wait().await.unwrap().is_none());
+        first.set_global(Duration::from_secs(10)).await.unwrap();
+
+        let wait = second.global_wait().await.unwrap().unwrap();
+        assert!(wait > Duration::from_secs(9));
+    }
+}
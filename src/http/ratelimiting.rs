@@ -39,17 +39,21 @@ use std::collections::HashMap;
 use std::fmt;
 use std::str::{self, FromStr};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 
+use async_trait::async_trait;
 use reqwest::header::HeaderMap;
 use reqwest::{Client, Response, StatusCode};
 use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::{sleep, Duration};
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
+use super::metrics::{MetricsRecorder, MetricsSnapshot};
+use super::priority::PriorityGate;
 pub use super::routing::RatelimitingBucket;
-use super::{HttpError, LightMethod, Request};
+use super::{HttpError, LightMethod, Request, RequestOptions, RequestPriority, Route};
 use crate::internal::prelude::*;
 
 /// Passed to the [`Ratelimiter::set_ratelimit_callback`] callback. If using Client, that callback
@@ -64,6 +68,8 @@ pub struct RatelimitInfo {
     pub global: bool,
 }
 
+type RoutePriority = Box<dyn Fn(&Route<'_>) -> Option<RequestPriority> + Send + Sync>;
+
 /// Ratelimiter for requests to the Discord API.
 ///
 /// This keeps track of ratelimit data for known routes through the [`Ratelimit`] implementation
@@ -83,24 +89,35 @@ pub struct RatelimitInfo {
 /// [`reset`]: Ratelimit::reset
 pub struct Ratelimiter {
     client: Client,
-    global: Arc<Mutex<()>>,
+    store: Arc<dyn RatelimitStore>,
     // When futures is implemented, make tasks clear out their respective entry when the 'reset'
     // passes.
     routes: Arc<RwLock<HashMap<RatelimitingBucket, Arc<Mutex<Ratelimit>>>>>,
     token: SecretString,
+    proxy: Option<String>,
     absolute_ratelimits: bool,
     ratelimit_callback: Box<dyn Fn(RatelimitInfo) + Send + Sync>,
+    route_priority: RoutePriority,
+    /// The queues of the requests waiting for the global ratelimit, and for each bucket.
+    global_gate: Arc<PriorityGate>,
+    gates: std::sync::Mutex<HashMap<RatelimitingBucket, Arc<PriorityGate>>>,
+    pub(super) metrics: Arc<MetricsRecorder>,
 }
 
 impl fmt::Debug for Ratelimiter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Ratelimiter")
             .field("client", &self.client)
-            .field("global", &self.global)
+            .field("store", &self.store)
             .field("routes", &self.routes)
             .field("token", &self.token)
+            .field("proxy", &self.proxy)
             .field("absolute_ratelimits", &self.absolute_ratelimits)
             .field("ratelimit_callback", &"Fn(RatelimitInfo)")
+            .field("route_priority", &"Fn(&Route) -> Option<RequestPriority>")
+            .field("global_gate", &self.global_gate)
+            .field("gates", &self.gates)
+            .field("metrics", &self.metrics)
             .finish()
     }
 }
@@ -115,16 +132,35 @@ impl Ratelimiter {
     }
 
     fn _new(client: Client, token: String) -> Self {
+        let store = InMemoryRatelimitStore::default();
+
         Self {
             client,
-            global: Arc::default(),
-            routes: Arc::default(),
+            routes: Arc::clone(&store.routes),
+            store: Arc::new(store),
             token: SecretString::new(token),
+            proxy: None,
             ratelimit_callback: Box::new(|_| {}),
             absolute_ratelimits: false,
+            route_priority: Box::new(|_| None),
+            global_gate: Arc::default(),
+            gates: std::sync::Mutex::default(),
+            metrics: Arc::default(),
         }
     }
 
+    /// Sets the proxy requests are sent to instead of Discord, in the same form as
+    /// [`HttpBuilder::proxy`].
+    ///
+    /// Unlike requests made with the ratelimiter disabled, requests going through the ratelimiter
+    /// are only sent to a proxy if one is set here, even if the [`Http`] client has one.
+    ///
+    /// [`HttpBuilder::proxy`]: super::HttpBuilder::proxy
+    /// [`Http`]: super::Http
+    pub fn set_proxy(&mut self, proxy: Option<String>) {
+        self.proxy = proxy;
+    }
+
     /// Sets a callback to be called when a route is rate limited.
     pub fn set_ratelimit_callback(
         &mut self,
@@ -133,17 +169,57 @@ impl Ratelimiter {
         self.ratelimit_callback = ratelimit_callback;
     }
 
+    /// Sets a callback deciding the priority of requests to a route, overriding the default
+    /// [`Route::priority`] when it returns [`Some`]. See [`RequestPriority`] for how priorities
+    /// are used.
+    ///
+    /// # Examples
+    ///
+    /// Give role updates a low priority:
+    ///
+    /// ```rust,no_run
+    /// use serenity::http::{Ratelimiter, RequestPriority, Route};
+    ///
+    /// let mut ratelimiter = Ratelimiter::new(reqwest::Client::new(), "Bot token");
+    /// ratelimiter.set_route_priority(|route| match route {
+    ///     Route::GuildMemberRole {
+    ///         ..
+    ///     } => Some(RequestPriority::Low),
+    ///     _ => None,
+    /// });
+    /// ```
+    pub fn set_route_priority<F>(&mut self, route_priority: F)
+    where
+        F: Fn(&Route<'_>) -> Option<RequestPriority> + Send + Sync + 'static,
+    {
+        self.route_priority = Box::new(route_priority);
+    }
+
     // Sets whether absolute ratelimits should be used.
     pub fn set_absolute_ratelimits(&mut self, absolute_ratelimits: bool) {
         self.absolute_ratelimits = absolute_ratelimits;
     }
 
+    /// Sets the store the state of the ratelimits is kept in, replacing the default
+    /// [`InMemoryRatelimitStore`].
+    ///
+    /// A store shared between processes, such as a [`UnixRatelimitStore`], lets several
+    /// processes using the same token avoid hitting each other's ratelimits.
+    ///
+    /// [`UnixRatelimitStore`]: super::ratelimit_daemon::UnixRatelimitStore
+    pub fn set_store(&mut self, store: Arc<dyn RatelimitStore>) {
+        self.store = store;
+    }
+
     /// The routes mutex is a HashMap of each [`RatelimitingBucket`] and their respective ratelimit
     /// information.
     ///
     /// See the documentation for [`Ratelimit`] for more information on how the library handles
     /// ratelimiting.
     ///
+    /// This is the map of the default [`InMemoryRatelimitStore`]. It stays empty if another store
+    /// was set with [`Self::set_store`].
+    ///
     /// # Examples
     ///
     /// View the `reset` time of the route for `ChannelsId(7)`:
@@ -175,84 +251,386 @@ impl Ratelimiter {
         Arc::clone(&self.routes)
     }
 
+    /// Returns a copy of the ratelimit of every bucket known to the default
+    /// [`InMemoryRatelimitStore`], without holding any of its locks.
+    ///
+    /// Like [`Self::routes`], this is empty if another store was set with [`Self::set_store`].
+    pub async fn ratelimits(&self) -> Vec<(RatelimitingBucket, Ratelimit)> {
+        let routes = self.routes.read().await;
+
+        let mut ratelimits = Vec::with_capacity(routes.len());
+        for (bucket, ratelimit) in routes.iter() {
+            ratelimits.push((*bucket, ratelimit.lock().await.clone()));
+        }
+        ratelimits
+    }
+
+    /// Returns a snapshot of the metrics of the requests made through the ratelimiter.
+    ///
+    /// This is the same as [`Http::metrics`] for the [`Http`] client using this ratelimiter.
+    ///
+    /// [`Http`]: super::Http
+    /// [`Http::metrics`]: super::Http::metrics
+    #[must_use]
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
     /// # Errors
     ///
     /// Only error kind that may be returned is [`Error::Http`].
     #[instrument]
     pub async fn perform(&self, req: Request<'_>) -> Result<Response> {
+        let ratelimiting_bucket = req.route.ratelimiting_bucket();
+        let path = req.route.path();
+
+        let priority = RequestPriority::current()
+            .or_else(|| (self.route_priority)(&req.route))
+            .unwrap_or_else(|| req.route.priority());
+        let options = RequestOptions::resolve(req.options);
+
+        self.perform_raw(
+            &ratelimiting_bucket,
+            req.method,
+            &path,
+            priority,
+            options,
+            |client, token| Ok(req.clone().build(client, token, self.proxy.as_deref())?.build()?),
+        )
+        .await
+    }
+
+    /// Performs the request built by the given closure within the ratelimits of the given bucket,
+    /// building it again every time it has to be retried.
+    ///
+    /// The closure is given the client and the token of the ratelimiter. The method and path are
+    /// only used to report ratelimits. Requests waiting for the same ratelimit are served in the
+    /// order of their priority, and only wait for as long as their options allow.
+    pub(super) async fn perform_raw<F>(
+        &self,
+        ratelimiting_bucket: &RatelimitingBucket,
+        method: LightMethod,
+        path: &str,
+        priority: RequestPriority,
+        options: RequestOptions,
+        build: F,
+    ) -> Result<Response>
+    where
+        F: Fn(&Client, &str) -> Result<reqwest::Request>,
+    {
         loop {
-            // This will block if another thread hit the global ratelimit.
-            drop(self.global.lock().await);
+            // This will block if another task or process hit the global ratelimit. Requests of
+            // any bucket only queue up here while it lasts, and go through by priority once it's
+            // over.
+            if store_result(self.store.global_wait().await).is_some() {
+                let global_turn = self.global_gate.lock(priority).await;
+                if let Some(delay) = store_result(self.store.global_wait().await) {
+                    options
+                        .wait(RatelimitInfo {
+                            timeout: delay,
+                            limit: 50,
+                            method,
+                            path: path.to_owned(),
+                            global: true,
+                        })
+                        .await?;
+                    self.metrics.record_wait(ratelimiting_bucket, method, delay);
+                }
+                drop(global_turn);
+            }
 
             // Perform pre-checking here:
-            // - get the route's relevant rate
-            // - sleep if that route's already rate-limited until the end of the 'reset' time;
-            // - get the global rate;
-            // - sleep if there is 0 remaining
+            // - take a ticket from the route's bucket in the store
+            // - sleep if that route's already rate-limited until the end of the 'reset' time
             // - then, perform the request
-            let ratelimiting_bucket = req.route.ratelimiting_bucket();
-            let bucket =
-                Arc::clone(self.routes.write().await.entry(ratelimiting_bucket).or_default());
-
-            bucket.lock().await.pre_hook(&req, &self.ratelimit_callback).await;
+            if !ratelimiting_bucket.is_none() {
+                let gate = self.gate(ratelimiting_bucket);
+                let _turn = gate.lock(priority).await;
+
+                if let Some(wait) = store_result(self.store.acquire(ratelimiting_bucket).await) {
+                    debug!(
+                        "Pre-emptive ratelimit on route {:?} for {}ms",
+                        ratelimiting_bucket,
+                        wait.delay.as_millis(),
+                    );
+                    let info = RatelimitInfo {
+                        timeout: wait.delay,
+                        limit: wait.limit,
+                        method,
+                        path: path.to_owned(),
+                        global: false,
+                    };
+                    (self.ratelimit_callback)(info.clone());
+
+                    options.wait(info).await?;
+                    self.metrics.record_wait(ratelimiting_bucket, method, wait.delay);
+                }
+            }
 
-            let request = req.clone().build(&self.client, self.token.expose_secret(), None)?;
-            let response = self.client.execute(request.build()?).await?;
+            let mut request = build(&self.client, self.token.expose_secret())?;
+            options.apply(&mut request);
+            let start = Instant::now();
+            let result = self.client.execute(request).await;
+            let status = result.as_ref().ok().map(Response::status);
+            self.metrics.record_response(ratelimiting_bucket, method, status, start.elapsed());
+            let response = result?;
 
             // Check if the request got ratelimited by checking for status 429, and if so, sleep
-            // for the value of the header 'retry-after' - which is in milliseconds - and then
+            // for the value of the header 'retry-after' - which is in seconds - and then
             // `continue` to try again
             //
-            // If it didn't ratelimit, subtract one from the Ratelimit's 'remaining'.
-            //
-            // Update `reset` with the value of 'x-ratelimit-reset' header. Similarly, update
-            // `reset-after` with the 'x-ratelimit-reset-after' header.
-            //
-            // It _may_ be possible for the limit to be raised at any time, so check if it did from
-            // the value of the 'x-ratelimit-limit' header. If the limit was 5 and is now 7, add 2
-            // to the 'remaining'
+            // Otherwise, the bucket in the store is updated from the 'x-ratelimit-*' headers. See
+            // `RatelimitHeaders` for how they are read.
             if ratelimiting_bucket.is_none() {
                 return Ok(response);
             }
 
             let redo = if response.headers().get("x-ratelimit-global").is_some() {
-                drop(self.global.lock().await);
-
-                Ok(
-                    if let Some(retry_after) =
-                        parse_header::<f64>(response.headers(), "retry-after")?
-                    {
-                        debug!(
-                            "Ratelimited on route {:?} for {:?}s",
-                            ratelimiting_bucket, retry_after
-                        );
-                        (self.ratelimit_callback)(RatelimitInfo {
-                            timeout: Duration::from_secs_f64(retry_after),
-                            limit: 50,
-                            method: req.method,
-                            path: req.route.path().to_string(),
-                            global: true,
-                        });
-                        sleep(Duration::from_secs_f64(retry_after)).await;
-
-                        true
-                    } else {
-                        false
-                    },
-                )
+                self.global_ratelimit(ratelimiting_bucket, &response, method, path, options).await
             } else {
-                bucket
-                    .lock()
-                    .await
-                    .post_hook(&response, &req, &self.ratelimit_callback, self.absolute_ratelimits)
-                    .await
+                self.bucket_ratelimit(ratelimiting_bucket, &response, method, path, options).await
             };
 
-            if !redo.unwrap_or(true) {
-                return Ok(response);
+            match redo {
+                Ok(false) => return Ok(response),
+                // The request may not wait for the ratelimit.
+                Err(why @ Error::Http(HttpError::Ratelimited(_) | HttpError::DeadlineExceeded)) => {
+                    return Err(why);
+                },
+                Ok(true) | Err(_) => {},
             }
         }
     }
+
+    /// Returns the queue of the requests waiting for the given bucket.
+    fn gate(&self, bucket: &RatelimitingBucket) -> Arc<PriorityGate> {
+        let mut gates = self.gates.lock().expect("poisoned ratelimiter gates");
+        Arc::clone(gates.entry(*bucket).or_default())
+    }
+
+    /// Shares a global ratelimit hit by a response through the store and sleeps until it is over,
+    /// returning whether the request should be retried.
+    async fn global_ratelimit(
+        &self,
+        bucket: &RatelimitingBucket,
+        response: &Response,
+        method: LightMethod,
+        path: &str,
+        options: RequestOptions,
+    ) -> Result<bool> {
+        let Some(retry_after) = parse_header::<f64>(response.headers(), "retry-after")? else {
+            return Ok(false);
+        };
+
+        debug!("Globally ratelimited on route {:?} for {:?}s", bucket, retry_after);
+        self.metrics.record_global_ratelimit();
+        let timeout = Duration::from_secs_f64(retry_after);
+        store_result(self.store.set_global(timeout).await);
+        let info = RatelimitInfo {
+            timeout,
+            limit: 50,
+            method,
+            path: path.to_owned(),
+            global: true,
+        };
+        (self.ratelimit_callback)(info.clone());
+
+        options.wait(info).await?;
+
+        Ok(true)
+    }
+
+    /// Updates the route's bucket in the store from the headers of a response. If the request was
+    /// ratelimited anyway, this sleeps for the 'retry-after' header and returns `true` so it can be
+    /// retried.
+    async fn bucket_ratelimit(
+        &self,
+        bucket: &RatelimitingBucket,
+        response: &Response,
+        method: LightMethod,
+        path: &str,
+        options: RequestOptions,
+    ) -> Result<bool> {
+        let headers = RatelimitHeaders::from_headers(response.headers(), self.absolute_ratelimits)?;
+        store_result(self.store.update(bucket, headers).await);
+
+        if response.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(false);
+        }
+
+        let Some(retry_after) = parse_header::<f64>(response.headers(), "retry-after")? else {
+            return Ok(false);
+        };
+
+        debug!("Ratelimited on route {:?} for {:?}s", bucket, retry_after);
+        let timeout = Duration::from_secs_f64(retry_after);
+        let info = RatelimitInfo {
+            timeout,
+            limit: headers.limit.unwrap_or(i64::MAX),
+            method,
+            path: path.to_owned(),
+            global: false,
+        };
+        (self.ratelimit_callback)(info.clone());
+
+        options.wait(info).await?;
+
+        Ok(true)
+    }
+}
+
+/// Unwraps the result of a [`RatelimitStore`] call.
+///
+/// A store that can't be reached should not stop the bot from making requests, so errors are only
+/// logged and the request goes ahead as if the store had no ratelimit for it.
+fn store_result<T: Default>(result: Result<T>) -> T {
+    result.unwrap_or_else(|why| {
+        warn!("Failed to access the ratelimit store: {why:?}");
+        T::default()
+    })
+}
+
+/// How long a request has to wait before a ticket of its bucket is available, as returned by
+/// [`RatelimitStore::acquire`].
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct RatelimitWait {
+    /// The time left until the bucket resets.
+    pub delay: Duration,
+    /// The total number of requests that can be made in the bucket's period of time.
+    pub limit: i64,
+}
+
+impl RatelimitWait {
+    #[must_use]
+    pub fn new(delay: Duration, limit: i64) -> Self {
+        Self {
+            delay,
+            limit,
+        }
+    }
+}
+
+/// The ratelimit headers of a response, as passed to [`RatelimitStore::update`].
+///
+/// A field is [`None`] if its header was missing from the response.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct RatelimitHeaders {
+    /// The value of the `x-ratelimit-limit` header.
+    pub limit: Option<i64>,
+    /// The value of the `x-ratelimit-remaining` header.
+    pub remaining: Option<i64>,
+    /// The absolute time when the bucket resets. This comes from the `x-ratelimit-reset` header if
+    /// absolute ratelimits are used, and from `x-ratelimit-reset-after` otherwise.
+    pub reset: Option<SystemTime>,
+    /// The value of the `x-ratelimit-reset-after` header.
+    pub reset_after: Option<Duration>,
+}
+
+impl RatelimitHeaders {
+    /// Reads the ratelimit headers of a response.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HttpError::RateLimitUtf8`] or [`HttpError::RateLimitI64F64`] if a header can't be
+    /// parsed.
+    pub fn from_headers(headers: &HeaderMap, absolute_ratelimits: bool) -> Result<Self> {
+        let reset_after =
+            parse_header::<f64>(headers, "x-ratelimit-reset-after")?.map(Duration::from_secs_f64);
+        let reset = if absolute_ratelimits {
+            parse_header::<f64>(headers, "x-ratelimit-reset")?
+                .map(|reset| std::time::UNIX_EPOCH + Duration::from_secs_f64(reset))
+        } else {
+            reset_after.map(|reset_after| SystemTime::now() + reset_after)
+        };
+
+        Ok(Self {
+            limit: parse_header(headers, "x-ratelimit-limit")?,
+            remaining: parse_header(headers, "x-ratelimit-remaining")?,
+            reset,
+            reset_after,
+        })
+    }
+}
+
+/// Keeps the state of the ratelimits used by the [`Ratelimiter`].
+///
+/// The default is the [`InMemoryRatelimitStore`], which only knows about the requests made by the
+/// ratelimiter it belongs to. A store can instead keep its state outside of the process, so that
+/// every process making requests with the same token takes its tickets from the same buckets. The
+/// [`UnixRatelimitStore`] does this through a daemon shipped with serenity.
+///
+/// Buckets can be keyed by the [`Display`] output of the [`RatelimitingBucket`], which is stable
+/// between processes. The logic of a single bucket is implemented by [`Ratelimit::acquire`] and
+/// [`Ratelimit::update`], which a store may reuse.
+///
+/// Errors returned by a store are logged, and the request is made as if it wasn't ratelimited.
+///
+/// [`UnixRatelimitStore`]: super::ratelimit_daemon::UnixRatelimitStore
+/// [`Display`]: std::fmt::Display
+#[async_trait]
+pub trait RatelimitStore: fmt::Debug + Send + Sync {
+    /// Takes a ticket from the bucket before a request is made, returning how long to wait first if
+    /// there are none left.
+    async fn acquire(&self, bucket: &RatelimitingBucket) -> Result<Option<RatelimitWait>>;
+
+    /// Updates the bucket from the ratelimit headers of the response to a request.
+    async fn update(&self, bucket: &RatelimitingBucket, headers: RatelimitHeaders) -> Result<()>;
+
+    /// Returns how much longer the global ratelimit lasts, if it was hit.
+    async fn global_wait(&self) -> Result<Option<Duration>>;
+
+    /// Records that the global ratelimit was hit, lasting for the given time.
+    async fn set_global(&self, retry_after: Duration) -> Result<()>;
+}
+
+/// The default [`RatelimitStore`], keeping the ratelimits of a single [`Ratelimiter`] in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryRatelimitStore {
+    routes: Arc<RwLock<HashMap<RatelimitingBucket, Arc<Mutex<Ratelimit>>>>>,
+    global: Mutex<Option<SystemTime>>,
+}
+
+impl InMemoryRatelimitStore {
+    /// Returns the ratelimit of each known [`RatelimitingBucket`], as described in
+    /// [`Ratelimiter::routes`].
+    #[must_use]
+    pub fn routes(&self) -> Arc<RwLock<HashMap<RatelimitingBucket, Arc<Mutex<Ratelimit>>>>> {
+        Arc::clone(&self.routes)
+    }
+
+    async fn bucket(&self, bucket: &RatelimitingBucket) -> Arc<Mutex<Ratelimit>> {
+        Arc::clone(self.routes.write().await.entry(*bucket).or_default())
+    }
+}
+
+#[async_trait]
+impl RatelimitStore for InMemoryRatelimitStore {
+    async fn acquire(&self, bucket: &RatelimitingBucket) -> Result<Option<RatelimitWait>> {
+        let bucket = self.bucket(bucket).await;
+        let mut ratelimit = bucket.lock().await;
+
+        Ok(ratelimit.acquire().map(|delay| RatelimitWait::new(delay, ratelimit.limit())))
+    }
+
+    async fn update(&self, bucket: &RatelimitingBucket, headers: RatelimitHeaders) -> Result<()> {
+        self.bucket(bucket).await.lock().await.update(&headers);
+
+        Ok(())
+    }
+
+    async fn global_wait(&self) -> Result<Option<Duration>> {
+        let global = *self.global.lock().await;
+
+        Ok(global.and_then(|until| until.duration_since(SystemTime::now()).ok()))
+    }
+
+    async fn set_global(&self, retry_after: Duration) -> Result<()> {
+        *self.global.lock().await = Some(SystemTime::now() + retry_after);
+
+        Ok(())
+    }
 }
 
 /// A set of data containing information about the ratelimits for a particular
@@ -264,7 +642,7 @@ impl Ratelimiter {
 ///
 /// [`Http`]: super::Http
 /// [Discord docs]: https://discord.com/developers/docs/topics/rate-limits
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Ratelimit {
     /// The total number of requests that can be made in a period of time.
     limit: i64,
@@ -283,44 +661,22 @@ impl Ratelimit {
         req: &Request<'_>,
         ratelimit_callback: &(dyn Fn(RatelimitInfo) + Send + Sync),
     ) {
-        if self.limit() == 0 {
-            return;
-        }
-
-        let Some(reset) = self.reset else {
-            // We're probably in the past.
-            self.remaining = self.limit;
-            return;
-        };
-
-        let Ok(delay) = reset.duration_since(SystemTime::now()) else {
-            // if duration is negative (i.e. adequate time has passed since last call to this api)
-            if self.remaining() != 0 {
-                self.remaining -= 1;
-            }
-            return;
-        };
-
-        if self.remaining() == 0 {
-            debug!(
-                "Pre-emptive ratelimit on route {:?} for {}ms",
-                req.route.ratelimiting_bucket(),
-                delay.as_millis(),
-            );
-            ratelimit_callback(RatelimitInfo {
-                timeout: delay,
-                limit: self.limit,
-                method: req.method,
-                path: req.route.path().to_string(),
-                global: false,
-            });
-
-            sleep(delay).await;
-
-            return;
-        }
-
-        self.remaining -= 1;
+        let Some(delay) = self.acquire() else { return };
+
+        debug!(
+            "Pre-emptive ratelimit on route {:?} for {}ms",
+            req.route.ratelimiting_bucket(),
+            delay.as_millis(),
+        );
+        ratelimit_callback(RatelimitInfo {
+            timeout: delay,
+            limit: self.limit,
+            method: req.method,
+            path: req.route.path().to_string(),
+            global: false,
+        });
+
+        sleep(delay).await;
     }
 
     #[instrument(skip(ratelimit_callback))]
@@ -331,29 +687,7 @@ impl Ratelimit {
         ratelimit_callback: &(dyn Fn(RatelimitInfo) + Send + Sync),
         absolute_ratelimits: bool,
     ) -> Result<bool> {
-        if let Some(limit) = parse_header(response.headers(), "x-ratelimit-limit")? {
-            self.limit = limit;
-        }
-
-        if let Some(remaining) = parse_header(response.headers(), "x-ratelimit-remaining")? {
-            self.remaining = remaining;
-        }
-
-        if absolute_ratelimits {
-            if let Some(reset) = parse_header::<f64>(response.headers(), "x-ratelimit-reset")? {
-                self.reset = Some(std::time::UNIX_EPOCH + Duration::from_secs_f64(reset));
-            }
-        }
-
-        if let Some(reset_after) =
-            parse_header::<f64>(response.headers(), "x-ratelimit-reset-after")?
-        {
-            if !absolute_ratelimits {
-                self.reset = Some(SystemTime::now() + Duration::from_secs_f64(reset_after));
-            }
-
-            self.reset_after = Some(Duration::from_secs_f64(reset_after));
-        }
+        self.update(&RatelimitHeaders::from_headers(response.headers(), absolute_ratelimits)?);
 
         Ok(if response.status() != StatusCode::TOO_MANY_REQUESTS {
             false
@@ -379,6 +713,54 @@ impl Ratelimit {
         })
     }
 
+    /// Takes a ticket for a request, returning the time left until the interval resets if there
+    /// are none remaining.
+    pub fn acquire(&mut self) -> Option<Duration> {
+        if self.limit() == 0 {
+            return None;
+        }
+
+        let Some(reset) = self.reset else {
+            // We're probably in the past.
+            self.remaining = self.limit;
+            return None;
+        };
+
+        let Ok(delay) = reset.duration_since(SystemTime::now()) else {
+            // if duration is negative (i.e. adequate time has passed since last call to this api)
+            if self.remaining() != 0 {
+                self.remaining -= 1;
+            }
+            return None;
+        };
+
+        if self.remaining() == 0 {
+            return Some(delay);
+        }
+
+        self.remaining -= 1;
+        None
+    }
+
+    /// Updates the ratelimit from the headers of a response.
+    pub fn update(&mut self, headers: &RatelimitHeaders) {
+        if let Some(limit) = headers.limit {
+            self.limit = limit;
+        }
+
+        if let Some(remaining) = headers.remaining {
+            self.remaining = remaining;
+        }
+
+        if let Some(reset) = headers.reset {
+            self.reset = Some(reset);
+        }
+
+        if let Some(reset_after) = headers.reset_after {
+            self.reset_after = Some(reset_after);
+        }
+    }
+
     /// The total number of requests that can be made in a period of time.
     #[inline]
     #[must_use]
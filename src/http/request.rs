@@ -13,7 +13,7 @@ use tracing::instrument;
 
 use super::multipart::Multipart;
 use super::routing::Route;
-use super::{HttpError, LightMethod};
+use super::{HttpError, LightMethod, RequestOptions};
 use crate::constants;
 use crate::internal::prelude::*;
 
@@ -29,6 +29,7 @@ pub struct Request<'a> {
     pub(super) method: LightMethod,
     pub(super) route: Route<'a>,
     pub(super) params: Option<Vec<(&'static str, String)>>,
+    pub(super) options: Option<RequestOptions>,
 }
 
 impl<'a> Request<'a> {
@@ -40,6 +41,7 @@ impl<'a> Request<'a> {
             method,
             route,
             params: None,
+            options: None,
         }
     }
 
@@ -63,6 +65,13 @@ impl<'a> Request<'a> {
         self
     }
 
+    /// Sets the options of the request, taking precedence over the ones of its
+    /// [`RequestOptions::scope`].
+    pub fn options(mut self, options: RequestOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
     #[instrument(skip(token))]
     pub fn build(
         self,
@@ -141,6 +150,11 @@ impl<'a> Request<'a> {
         self.params.as_deref()
     }
 
+    #[must_use]
+    pub fn options_ref(&self) -> Option<&RequestOptions> {
+        self.options.as_ref()
+    }
+
     #[must_use]
     pub fn params_mut(&mut self) -> Option<&mut [(&'static str, String)]> {
         self.params.as_deref_mut()
@@ -0,0 +1,286 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Response;
+
+use super::{HttpError, LightMethod, StatusCode};
+use crate::internal::prelude::*;
+
+/// Passed to the callback set with [`RetryPolicy::retry_callback`] every time a request is about to
+/// be retried.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RetryInfo {
+    /// The number of the attempt that failed, starting at 1.
+    pub attempt: u32,
+    /// How long to wait before the next attempt.
+    pub delay: Duration,
+    /// Why the attempt failed.
+    pub reason: RetryReason,
+    pub method: LightMethod,
+    pub path: String,
+}
+
+/// Why a request is retried, see [`RetryInfo`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum RetryReason {
+    /// Discord answered with a 5xx status.
+    ServerError(StatusCode),
+    /// The request timed out.
+    Timeout,
+    /// The connection could not be established, or was lost while sending the request.
+    Connection,
+}
+
+impl RetryReason {
+    /// Returns why the result of an attempt should be retried, if it failed in a transient way.
+    pub(super) fn from_result(result: &Result<Response>) -> Option<Self> {
+        match result {
+            Ok(response) if response.status().is_server_error() => {
+                Some(Self::ServerError(response.status()))
+            },
+            Err(Error::Http(HttpError::Request(why))) if why.is_timeout() => Some(Self::Timeout),
+            Err(Error::Http(HttpError::Request(why))) if why.is_connect() || why.is_request() => {
+                Some(Self::Connection)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Decides which failed requests [`Http`] retries, and how long it waits before each retry.
+///
+/// Requests are retried if Discord answers with a 5xx status, or if sending them fails because of
+/// a timeout or a connection error. The delay before each retry grows exponentially from the base
+/// delay, up to the maximum delay, and is randomized with full jitter unless disabled.
+///
+/// Only methods that are safe to repeat are retried: by default `GET`, `PUT` and `DELETE`. A
+/// failed `POST` may still have been applied by Discord, so retrying it could for example send a
+/// message twice.
+///
+/// The default policy makes up to 3 attempts, with a base delay of 500 milliseconds and a maximum
+/// delay of 5 seconds.
+///
+/// # Examples
+///
+/// Retry up to 5 times, including `PATCH` requests, and log every retry:
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use serenity::http::{HttpBuilder, LightMethod, RetryPolicy};
+///
+/// let policy = RetryPolicy::default()
+///     .max_attempts(6)
+///     .backoff(Duration::from_millis(250), Duration::from_secs(10))
+///     .retry_method(LightMethod::Patch, true)
+///     .retry_callback(|info| println!("Retrying {} after {:?}", info.path, info.reason));
+/// let http = HttpBuilder::new("token").retry_policy(policy).build();
+/// ```
+///
+/// [`Http`]: super::Http
+#[derive(Clone)]
+#[must_use]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    methods: Vec<LightMethod>,
+    retry_callback: Option<Arc<dyn Fn(RetryInfo) + Send + Sync>>,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries a request.
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Sets how many times a request is attempted in total, including the first attempt. A value
+    /// of 0 is treated as 1.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry, which doubles for every further retry, and the
+    /// maximum it can grow to.
+    pub fn backoff(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets whether the delays are randomized, so that clients failing at the same time don't all
+    /// retry at the same time. Enabled by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets whether requests with the given method are retried.
+    pub fn retry_method(mut self, method: LightMethod, retry: bool) -> Self {
+        self.methods.retain(|m| *m != method);
+        if retry {
+            self.methods.push(method);
+        }
+        self
+    }
+
+    /// Sets a callback to be called every time a request is about to be retried.
+    pub fn retry_callback<F>(mut self, retry_callback: F) -> Self
+    where
+        F: Fn(RetryInfo) + Send + Sync + 'static,
+    {
+        self.retry_callback = Some(Arc::new(retry_callback));
+        self
+    }
+
+    /// Returns how long to wait before retrying a request that failed on the given attempt, or
+    /// `None` if it must not be retried.
+    pub(super) fn delay(&self, method: LightMethod, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.methods.contains(&method) {
+            return None;
+        }
+
+        let factor = 2_u32.saturating_pow(attempt - 1);
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+
+        Some(if self.jitter { delay.mul_f64(random_fraction()) } else { delay })
+    }
+
+    pub(super) fn report(&self, info: RetryInfo) {
+        if let Some(retry_callback) = &self.retry_callback {
+            retry_callback(info);
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+            methods: vec![LightMethod::Get, LightMethod::Put, LightMethod::Delete],
+            retry_callback: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("methods", &self.methods)
+            .field("retry_callback", &self.retry_callback.as_ref().map(|_| "Fn(RetryInfo)"))
+            .finish()
+    }
+}
+
+/// Returns a random number in `[0, 1)`. Every [`RandomState`] is seeded differently, which is
+/// plenty for spreading out retries.
+#[allow(clippy::cast_precision_loss)]
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1_u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = RetryPolicy::default()
+            .max_attempts(5)
+            .backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .jitter(false);
+
+        let delays: Vec<_> =
+            (1..=5).map(|attempt| policy.delay(LightMethod::Get, attempt)).collect();
+        assert_eq!(delays, [
+            Some(Duration::from_millis(100)),
+            Some(Duration::from_millis(200)),
+            Some(Duration::from_millis(300)),
+            Some(Duration::from_millis(300)),
+            None,
+        ]);
+
+        let jittered = policy.jitter(true).delay(LightMethod::Get, 2).unwrap();
+        assert!(jittered <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn only_retries_idempotent_methods() {
+        let policy = RetryPolicy::default();
+        assert!(policy.delay(LightMethod::Delete, 1).is_some());
+        assert!(policy.delay(LightMethod::Post, 1).is_none());
+        assert!(policy.delay(LightMethod::Patch, 1).is_none());
+
+        let policy =
+            policy.retry_method(LightMethod::Post, true).retry_method(LightMethod::Get, false);
+        assert!(policy.delay(LightMethod::Post, 1).is_some());
+        assert!(policy.delay(LightMethod::Get, 1).is_none());
+        assert!(RetryPolicy::none().delay(LightMethod::Get, 1).is_none());
+    }
+
+    #[cfg(feature = "http_mock")]
+    #[tokio::test]
+    async fn retries_server_errors() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        use crate::http::mock::{MockResponse, MockServer};
+        use crate::http::Route;
+        use crate::model::id::{ChannelId, MessageId};
+
+        let server = MockServer::start().await.unwrap();
+        let channel_id = ChannelId::new(1);
+        let route = Route::ChannelMessage {
+            channel_id,
+            message_id: MessageId::new(2),
+        };
+        let calls = AtomicU32::new(0);
+        server.mock_with(LightMethod::Delete, route, move |_| {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                MockResponse::error(StatusCode::BAD_GATEWAY, 0, "502: Bad Gateway")
+            } else {
+                MockResponse::no_content()
+            }
+        });
+        server.mock(
+            LightMethod::Post,
+            Route::ChannelMessages {
+                channel_id,
+            },
+            MockResponse::error(StatusCode::BAD_GATEWAY, 0, "502: Bad Gateway"),
+        );
+
+        let retries = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&retries);
+        let policy = RetryPolicy::default().backoff(Duration::ZERO, Duration::ZERO).retry_callback(
+            move |info| {
+                assert_eq!(info.reason, RetryReason::ServerError(StatusCode::BAD_GATEWAY));
+                counter.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+        let http = server.http_builder().retry_policy(policy).build();
+
+        http.delete_message(channel_id, MessageId::new(2), None).await.unwrap();
+        assert_eq!(retries.load(Ordering::SeqCst), 1);
+        assert_eq!(server.requests().len(), 2);
+
+        // A POST is not retried, since it might have been applied before failing.
+        assert!(http.send_message(channel_id, vec![], &crate::json::json!({})).await.is_err());
+        assert_eq!(retries.load(Ordering::SeqCst), 1);
+        assert_eq!(server.requests().len(), 3);
+    }
+}
@@ -1,18 +1,46 @@
 use std::borrow::Cow;
-use std::mem::Discriminant;
+use std::fmt;
 use std::num::NonZeroU64;
 
+use super::RequestPriority;
 use crate::model::id::*;
 
 /// Used to group requests together for ratelimiting.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub struct RatelimitingBucket(Option<(std::mem::Discriminant<Route<'static>>, Option<NonZeroU64>)>);
+pub struct RatelimitingBucket(Option<(&'static str, Option<NonZeroU64>)>);
 
 impl RatelimitingBucket {
     #[must_use]
     pub fn is_none(&self) -> bool {
         self.0.is_none()
     }
+
+    /// Returns the name of the route of the bucket, such as `ChannelMessages`, without its major
+    /// parameter. Returns [`None`] for routes without ratelimits.
+    #[must_use]
+    pub fn route(&self) -> Option<&'static str> {
+        self.0.map(|(name, _)| name)
+    }
+
+    /// Creates the bucket of a route known by its name, which must not be the name of a variant
+    /// of [`Route`].
+    #[cfg(feature = "http_proxy")]
+    pub(super) fn from_name(name: &'static str, major: Option<NonZeroU64>) -> Self {
+        Self(Some((name, major)))
+    }
+}
+
+/// Formats the bucket as the name of its route, followed by the major parameter if there is one,
+/// such as `ChannelMessages/381880193251409931`. This is used as the key of the bucket when
+/// ratelimits are shared between processes.
+impl fmt::Display for RatelimitingBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some((name, Some(id))) => write!(f, "{name}/{id}"),
+            Some((name, None)) => f.write_str(name),
+            None => f.write_str("None"),
+        }
+    }
 }
 
 enum RatelimitingKind {
@@ -54,26 +82,18 @@ macro_rules! routes {
             #[must_use]
             pub fn ratelimiting_bucket(&self) -> RatelimitingBucket {
                 #[allow(unused_variables)]
-                let ratelimiting_kind = match *self {
+                let (name, ratelimiting_kind) = match *self {
                     $(
-                        Self::$name $({ $($field_name),* })? => $ratelimiting_kind,
+                        Self::$name $({ $($field_name),* })? => (stringify!($name), $ratelimiting_kind),
                     )+
                 };
 
-                // This avoids adding a lifetime on RatelimitingBucket and causing lifetime infection
-                // SAFETY: std::mem::discriminant erases lifetimes.
-                let discriminant = unsafe {
-                    std::mem::transmute::<Discriminant<Route<'a>>, Discriminant<Route<'static>>>(
-                        std::mem::discriminant(self),
-                    )
-                };
-
                 RatelimitingBucket(ratelimiting_kind.map(|r| {
                     let id = match r {
                         RatelimitingKind::PathAndId(id) => Some(id),
                         RatelimitingKind::Path => None,
                     };
-                    (discriminant, id)
+                    (name, id)
                 }))
             }
 
@@ -90,6 +110,10 @@ routes! ('a, {
     api!("/channels/{}", channel_id),
     Some(RatelimitingKind::PathAndId(channel_id.into()));
 
+    ChannelAttachments { channel_id: ChannelId },
+    api!("/channels/{}/attachments", channel_id),
+    Some(RatelimitingKind::PathAndId(channel_id.into()));
+
     ChannelInvites { channel_id: ChannelId },
     api!("/channels/{}/invites", channel_id),
     Some(RatelimitingKind::PathAndId(channel_id.into()));
@@ -486,3 +510,29 @@ routes! ('a, {
     api!("/stage-instances/{}", channel_id),
     Some(RatelimitingKind::Path);
 });
+
+impl Route<'_> {
+    /// The priority of requests to this route, unless set otherwise as explained in
+    /// [`RequestPriority`].
+    ///
+    /// Responses to interactions are [`RequestPriority::High`], since they have to be sent within
+    /// Discord's time limits, and every other route is [`RequestPriority::Normal`].
+    #[must_use]
+    pub fn priority(&self) -> RequestPriority {
+        match self {
+            Self::InteractionResponse {
+                ..
+            }
+            | Self::WebhookOriginalInteractionResponse {
+                ..
+            }
+            | Self::WebhookFollowupMessage {
+                ..
+            }
+            | Self::WebhookFollowupMessages {
+                ..
+            } => RequestPriority::High,
+            _ => RequestPriority::Normal,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! A minimal HTTP/1.1 server side, shared by the mock server and the ratelimiting proxy.
+
+use std::fmt::Write as _;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{LightMethod, StatusCode};
+use crate::internal::prelude::*;
+
+/// The largest request body read by default, which fits the largest attachments Discord accepts
+/// from bots.
+pub(super) const DEFAULT_MAX_BODY_SIZE: usize = 100 * 1024 * 1024;
+
+/// A request as read from a connection.
+pub(super) struct RawRequest {
+    pub method: String,
+    /// The path of the request, without the query.
+    pub path: String,
+    /// The query of the request, if any, without the leading `?`.
+    pub query: Option<String>,
+    /// The headers of the request, with lowercase names.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RawRequest {
+    /// Returns the method of the request, if it is one of [`LightMethod`].
+    pub fn light_method(&self) -> Option<LightMethod> {
+        match self.method.as_str() {
+            "DELETE" => Some(LightMethod::Delete),
+            "GET" => Some(LightMethod::Get),
+            "PATCH" => Some(LightMethod::Patch),
+            "POST" => Some(LightMethod::Post),
+            "PUT" => Some(LightMethod::Put),
+            _ => None,
+        }
+    }
+}
+
+/// The outcome of reading a request from a connection.
+pub(super) enum ReadRequest {
+    /// A complete request.
+    Request(RawRequest),
+    /// A request with a body over the maximum size, which was left unread. The connection can't be
+    /// used for further requests.
+    TooLarge,
+    /// The connection was closed.
+    Closed,
+}
+
+/// Reads an HTTP/1.1 request, refusing bodies larger than `max_body_size` before reading them.
+pub(super) async fn read_request<R>(reader: &mut R, max_body_size: usize) -> Result<ReadRequest>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(ReadRequest::Closed);
+    }
+
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_owned(), target.to_owned()),
+        _ => return Err(invalid_data("malformed request line")),
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await?;
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        let (name, value) =
+            header.split_once(':').ok_or_else(|| invalid_data("malformed header"))?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+    }
+
+    let header = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+    let body = if header("transfer-encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        match read_chunked(reader, max_body_size).await? {
+            Some(body) => body,
+            None => return Ok(ReadRequest::TooLarge),
+        }
+    } else {
+        let len = header("content-length").and_then(|v| v.parse().ok()).unwrap_or(0);
+        if len > max_body_size {
+            return Ok(ReadRequest::TooLarge);
+        }
+
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).await?;
+        body
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_owned(), Some(query.to_owned())),
+        None => (target, None),
+    };
+
+    Ok(ReadRequest::Request(RawRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+/// Reads a chunked body, returning `None` as soon as a chunk would take it over `max_size`.
+async fn read_chunked<R>(reader: &mut R, max_size: usize) -> Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut body = Vec::new();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await?;
+
+        let size = line.trim_end().split(';').next().unwrap_or_default();
+        let size =
+            usize::from_str_radix(size, 16).map_err(|_| invalid_data("malformed chunk size"))?;
+
+        let start = body.len();
+        if size > max_size - start {
+            return Ok(None);
+        }
+
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+
+        // Every chunk, including the last empty one, is followed by a CRLF.
+        line.clear();
+        reader.read_line(&mut line).await?;
+
+        if size == 0 {
+            return Ok(Some(body));
+        }
+    }
+}
+
+/// Writes an HTTP/1.1 response. The `content-length` header is added to the given headers.
+pub(super) async fn write_response<W>(
+    writer: &mut W,
+    status: StatusCode,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    );
+
+    if status != StatusCode::NO_CONTENT {
+        write!(head, "content-length: {}\r\n", body.len()).ok();
+    }
+
+    for (name, value) in headers {
+        write!(head, "{name}: {value}\r\n").ok();
+    }
+    head.push_str("\r\n");
+
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+fn invalid_data(why: &str) -> Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, why).into()
+}
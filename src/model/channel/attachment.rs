@@ -5,7 +5,7 @@ use serde_cow::CowStr;
 #[cfg(feature = "model")]
 use crate::internal::prelude::*;
 use crate::model::prelude::*;
-use crate::model::utils::is_false;
+use crate::model::utils::{is_false, StrOrInt};
 
 fn base64_bytes<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
 where
@@ -77,6 +77,33 @@ pub struct Attachment {
     pub waveform: Option<Vec<u8>>,
 }
 
+/// A slot a file is uploaded to before being sent with a message, so that the file is not part of
+/// the request sending the message.
+///
+/// Slots are requested with [`ChannelId::upload_attachments`], which also uploads the files.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct AttachmentUpload {
+    /// The `id` of the file the slot was requested for.
+    #[serde(deserialize_with = "upload_id")]
+    pub id: u64,
+    /// The URL the data of the file is uploaded to with a `PUT` request.
+    pub upload_url: String,
+    /// The name the file is referred to by once uploaded, see [`CreateAttachment::uploaded`].
+    ///
+    /// [`CreateAttachment::uploaded`]: crate::builder::CreateAttachment::uploaded
+    pub upload_filename: String,
+}
+
+fn upload_id<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    StrOrInt::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+}
+
 #[cfg(feature = "model")]
 impl Attachment {
     /// If this attachment is an image, then a tuple of the width and height in pixels is returned.
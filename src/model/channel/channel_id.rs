@@ -27,7 +27,19 @@ use crate::collector::{MessageCollector, ReactionCollector};
 #[cfg(feature = "collector")]
 use crate::gateway::ShardMessenger;
 #[cfg(feature = "model")]
-use crate::http::{CacheHttp, Http, Typing};
+use crate::http::{
+    paginate,
+    CacheHttp,
+    Http,
+    HttpError,
+    LightMethod,
+    Page,
+    PaginationDirection,
+    PaginationOptions,
+    Request,
+    Route,
+    Typing,
+};
 #[cfg(feature = "model")]
 use crate::json::json;
 use crate::model::prelude::*;
@@ -725,6 +737,89 @@ impl ChannelId {
         self.send_message(cache_http, builder.files(files)).await
     }
 
+    /// Uploads files to Discord ahead of sending them in the channel, returning attachments that
+    /// refer to the uploaded files.
+    ///
+    /// The files are sent straight to Discord's storage instead of being part of the ratelimited
+    /// request sending the message, which suits large files. The returned attachments are then
+    /// added to a [`CreateMessage`] like any other, and must be sent in this channel.
+    ///
+    /// # Examples
+    ///
+    /// Upload a large video, streamed from its file, then send it:
+    ///
+    /// ```rust,no_run
+    /// # use serenity::http::Http;
+    /// # use serenity::model::id::ChannelId;
+    /// # async fn run(http: &Http, channel_id: ChannelId) -> serenity::Result<()> {
+    /// use serenity::builder::{CreateAttachment, CreateMessage};
+    ///
+    /// let video = CreateAttachment::path_stream("/path/to/video.mp4").await?;
+    /// let uploaded = channel_id.upload_attachments(http, vec![video]).await?;
+    ///
+    /// let builder = CreateMessage::new().content("Highlights");
+    /// channel_id.send_files(http, uploaded, builder).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Http`] if requesting the upload slots or uploading one of the files fails,
+    /// for example if a file is larger than the upload limit of the channel. If Discord returns no
+    /// slot for one of the files, [`HttpError::MissingAttachmentUpload`] is returned before any
+    /// file is uploaded.
+    ///
+    /// [`HttpError::MissingAttachmentUpload`]: crate::http::HttpError::MissingAttachmentUpload
+    pub async fn upload_attachments(
+        self,
+        http: impl AsRef<Http>,
+        attachments: Vec<CreateAttachment>,
+    ) -> Result<Vec<CreateAttachment>> {
+        let http = http.as_ref();
+
+        let files: Vec<_> = (0_u64..)
+            .zip(&attachments)
+            .map(|(id, attachment)| {
+                json!({
+                    "id": id.to_string(),
+                    "filename": attachment.filename,
+                    "file_size": attachment.size(),
+                })
+            })
+            .collect();
+        let uploads = http
+            .create_attachment_uploads(
+                self,
+                &json!({
+                    "files": files,
+                }),
+            )
+            .await?;
+
+        // Slots are matched to files by the id they echo back, rather than by their order.
+        let mut slots: HashMap<_, _> = uploads.into_iter().map(|u| (u.id, u)).collect();
+        let uploads = (0_u64..)
+            .zip(attachments)
+            .map(|(id, attachment)| match slots.remove(&id) {
+                Some(upload) => Ok((attachment, upload)),
+                None => Err(HttpError::MissingAttachmentUpload(attachment.filename).into()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut uploaded = Vec::with_capacity(uploads.len());
+        for (attachment, upload) in uploads {
+            let mut reference =
+                CreateAttachment::uploaded(attachment.filename.clone(), &upload.upload_filename);
+            reference.description.clone_from(&attachment.description);
+
+            http.upload_attachment(&upload, attachment).await?;
+            uploaded.push(reference);
+        }
+
+        Ok(uploaded)
+    }
+
     /// Sends a message to the channel.
     ///
     /// Refer to the documentation for [`CreateMessage`] for information regarding content
@@ -1065,6 +1160,138 @@ impl ChannelId {
         http.as_ref().get_channel_joined_archived_private_threads(self, before, limit).await
     }
 
+    /// Streams over the public archived threads of the channel.
+    ///
+    /// This is accomplished and equivalent to repeated calls to
+    /// [`Self::get_archived_public_threads`]. Threads are always returned by most recently
+    /// archived first, so the direction of the options is ignored.
+    ///
+    /// **Note**: Requires the [Read Message History] permission.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use serenity::http::Http;
+    /// # use serenity::model::id::ChannelId;
+    /// # async fn run(http: Http, channel_id: ChannelId) {
+    /// use serenity::futures::StreamExt;
+    /// use serenity::http::PaginationOptions;
+    ///
+    /// let mut threads =
+    ///     channel_id.archived_public_threads_iter(&http, PaginationOptions::new()).boxed();
+    /// while let Some(thread) = threads.next().await {
+    ///     match thread {
+    ///         Ok(thread) => println!("{} was archived", thread.name),
+    ///         Err(error) => eprintln!("Uh oh! Error: {}", error),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [Read Message History]: Permissions::READ_MESSAGE_HISTORY
+    pub fn archived_public_threads_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<GuildChannel>> {
+        self.archived_threads_iter(http, false, options)
+    }
+
+    /// Streams over the private archived threads of the channel.
+    ///
+    /// This is accomplished and equivalent to repeated calls to
+    /// [`Self::get_archived_private_threads`]. Threads are always returned by most recently
+    /// archived first, so the direction of the options is ignored.
+    ///
+    /// **Note**: Requires the [Read Message History] and [Manage Threads] permissions.
+    ///
+    /// [Read Message History]: Permissions::READ_MESSAGE_HISTORY
+    /// [Manage Threads]: Permissions::MANAGE_THREADS
+    pub fn archived_private_threads_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<GuildChannel>> {
+        self.archived_threads_iter(http, true, options)
+    }
+
+    /// The archived thread endpoints take the archive timestamp of the last thread as their
+    /// cursor, which the methods of [`Http`] don't support, so the requests are made directly.
+    fn archived_threads_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        private: bool,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<GuildChannel>> {
+        let http = Arc::new(http);
+        let options = options.direction(PaginationDirection::Backward);
+        let cursor = |thread: &GuildChannel| {
+            thread.thread_metadata.as_ref().and_then(|metadata| metadata.archive_timestamp)
+        };
+
+        paginate(options, cursor, move |page| {
+            let http = Arc::clone(&http);
+            async move {
+                let mut params = vec![("limit", page.limit.to_string())];
+                if let Some(before) = page.cursor.flatten() {
+                    params.push(("before", before.to_string()));
+                }
+
+                let route = if private {
+                    Route::ChannelArchivedPrivateThreads {
+                        channel_id: self,
+                    }
+                } else {
+                    Route::ChannelArchivedPublicThreads {
+                        channel_id: self,
+                    }
+                };
+                let request = Request::new(route, LightMethod::Get).params(Some(params));
+                let data: ThreadsData = (*http).as_ref().fire(request).await?;
+
+                Ok(Page::new(data.threads).has_more(data.has_more))
+            }
+        })
+    }
+
+    /// Streams over the private archived threads of the channel that the current user has
+    /// joined.
+    ///
+    /// This is accomplished and equivalent to repeated calls to
+    /// [`Self::get_joined_archived_private_threads`]. Threads are always returned newest first,
+    /// so the direction of the options is ignored.
+    ///
+    /// **Note**: Requires the [Read Message History] permission.
+    ///
+    /// [Read Message History]: Permissions::READ_MESSAGE_HISTORY
+    pub fn joined_archived_private_threads_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<GuildChannel>> {
+        let http = Arc::new(http);
+        let options = options.direction(PaginationDirection::Backward);
+        paginate(
+            options,
+            |thread: &GuildChannel| thread.id,
+            move |page| {
+                let http = Arc::clone(&http);
+                async move {
+                    let before = page.cursor.map(ChannelId::get);
+                    let data = self
+                        .get_joined_archived_private_threads(
+                            (*http).as_ref(),
+                            before,
+                            Some(page.limit.into()),
+                        )
+                        .await?;
+
+                    Ok(Page::new(data.threads).has_more(data.has_more))
+                }
+            },
+        )
+    }
+
     /// Get a list of users that voted for this specific answer.
     ///
     /// # Errors
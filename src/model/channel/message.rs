@@ -4,6 +4,11 @@
 use std::fmt::Display;
 #[cfg(all(feature = "cache", feature = "model"))]
 use std::fmt::Write;
+#[cfg(feature = "model")]
+use std::sync::Arc;
+
+#[cfg(feature = "model")]
+use futures::stream::Stream;
 
 #[cfg(all(feature = "model", feature = "utils"))]
 use crate::builder::{Builder, CreateAllowedMentions, CreateMessage, EditMessage};
@@ -20,7 +25,7 @@ use crate::constants;
 #[cfg(feature = "collector")]
 use crate::gateway::ShardMessenger;
 #[cfg(feature = "model")]
-use crate::http::{CacheHttp, Http};
+use crate::http::{paginate, CacheHttp, Http, Page, PaginationDirection, PaginationOptions};
 use crate::model::prelude::*;
 use crate::model::utils::StrOrInt;
 #[cfg(all(feature = "model", feature = "cache"))]
@@ -1218,6 +1223,48 @@ impl MessageId {
 
         self.link(channel_id, guild_id)
     }
+
+    /// Streams over the users that reacted to the message with the given reaction.
+    ///
+    /// This is accomplished and equivalent to repeated calls to [`ChannelId::reaction_users`],
+    /// requesting up to 100 users at once. Users are always returned in the order of their IDs,
+    /// so the direction of the options is ignored.
+    ///
+    /// **Note**: Requires the [Read Message History] permission.
+    ///
+    /// [Read Message History]: Permissions::READ_MESSAGE_HISTORY
+    pub fn reaction_users_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        channel_id: ChannelId,
+        reaction_type: impl Into<ReactionType>,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<User>> {
+        let http = Arc::new(http);
+        let reaction_type = reaction_type.into();
+        let options = options.direction(PaginationDirection::Forward).max_page_size(100);
+        paginate(
+            options,
+            |user: &User| user.id,
+            move |page| {
+                let http = Arc::clone(&http);
+                let reaction_type = reaction_type.clone();
+                async move {
+                    let limit = u8::try_from(page.limit).unwrap_or(100);
+                    channel_id
+                        .reaction_users(
+                            (*http).as_ref(),
+                            self,
+                            reaction_type,
+                            Some(limit),
+                            page.cursor,
+                        )
+                        .await
+                        .map(Page::new)
+                }
+            },
+        )
+    }
 }
 
 #[cfg_attr(feature = "typesize", derive(typesize::derive::TypeSize))]
@@ -141,8 +141,9 @@ impl Emoji {
     #[allow(deprecated)]
     #[must_use]
     pub fn find_guild_id(&self, cache: impl AsRef<Cache>) -> Option<GuildId> {
-        for guild_entry in cache.as_ref().guilds.iter() {
-            let guild = guild_entry.value();
+        let cache = cache.as_ref();
+        for guild_id in cache.guilds() {
+            let Some(guild) = cache.guild(guild_id) else { continue };
 
             if guild.emojis.contains_key(&self.id) {
                 return Some(guild.id);
@@ -1,4 +1,6 @@
 use std::fmt;
+#[cfg(feature = "model")]
+use std::sync::Arc;
 
 #[cfg(feature = "model")]
 use futures::stream::Stream;
@@ -28,7 +30,16 @@ use crate::collector::{MessageCollector, ReactionCollector};
 #[cfg(feature = "collector")]
 use crate::gateway::ShardMessenger;
 #[cfg(feature = "model")]
-use crate::http::{CacheHttp, Http, UserPagination};
+use crate::http::{
+    paginate,
+    CacheHttp,
+    Http,
+    Page,
+    PaginationDirection,
+    PaginationOptions,
+    UserPagination,
+    MAX_SNOWFLAKE,
+};
 #[cfg(feature = "model")]
 use crate::internal::prelude::*;
 #[cfg(feature = "model")]
@@ -301,6 +312,59 @@ impl GuildId {
         http.as_ref().get_bans(self, target, limit).await
     }
 
+    /// Streams over all of the guild's bans.
+    ///
+    /// This is accomplished and equivalent to repeated calls to [`Self::bans`], requesting up to
+    /// 255 bans at once. Both directions are supported.
+    ///
+    /// **Note**: Requires the [Ban Members] permission.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use serenity::http::Http;
+    /// # use serenity::model::id::GuildId;
+    /// # async fn run(http: Http, guild_id: GuildId) {
+    /// use serenity::futures::StreamExt;
+    /// use serenity::http::PaginationOptions;
+    ///
+    /// let mut bans = guild_id.bans_iter(&http, PaginationOptions::new()).boxed();
+    /// while let Some(ban) = bans.next().await {
+    ///     match ban {
+    ///         Ok(ban) => println!("{} is banned", ban.user.name),
+    ///         Err(error) => eprintln!("Uh oh! Error: {}", error),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [Ban Members]: Permissions::BAN_MEMBERS
+    pub fn bans_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<Ban>> {
+        let http = Arc::new(http);
+        let options = options.max_page_size(u8::MAX.into());
+        paginate(
+            options,
+            |ban: &Ban| ban.user.id,
+            move |page| {
+                let http = Arc::clone(&http);
+                async move {
+                    let target = match page.direction {
+                        PaginationDirection::Forward => page.cursor.map(UserPagination::After),
+                        _ => Some(UserPagination::Before(
+                            page.cursor.unwrap_or(UserId::new(MAX_SNOWFLAKE)),
+                        )),
+                    };
+                    let limit = u8::try_from(page.limit).unwrap_or(u8::MAX);
+                    self.bans((*http).as_ref(), target, Some(limit)).await.map(Page::new)
+                }
+            },
+        )
+    }
+
     /// Gets a list of the guild's audit log entries
     ///
     /// **Note**: Requires the [View Audit Log] permission.
@@ -323,6 +387,42 @@ impl GuildId {
         http.as_ref().get_audit_logs(self, action_type, user_id, before, limit).await
     }
 
+    /// Streams over the guild's audit log entries, optionally filtered by action type and user.
+    ///
+    /// This is accomplished and equivalent to repeated calls to [`Self::audit_logs`], requesting
+    /// up to 100 entries at once. Entries are always returned newest first, so the direction of
+    /// the options is ignored.
+    ///
+    /// **Note**: Requires the [View Audit Log] permission.
+    ///
+    /// [View Audit Log]: Permissions::VIEW_AUDIT_LOG
+    pub fn audit_logs_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        action_type: Option<audit_log::Action>,
+        user_id: Option<UserId>,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<AuditLogEntry>> {
+        let http = Arc::new(http);
+        let options = options.direction(PaginationDirection::Backward).max_page_size(100);
+        paginate(
+            options,
+            |entry: &AuditLogEntry| entry.id,
+            move |page| {
+                let http = Arc::clone(&http);
+                async move {
+                    let limit = u8::try_from(page.limit).unwrap_or(100);
+                    let logs = (*http)
+                        .as_ref()
+                        .get_audit_logs(self, action_type, user_id, page.cursor, Some(limit))
+                        .await?;
+
+                    Ok(Page::new(logs.entries))
+                }
+            },
+        )
+    }
+
     /// Gets all of the guild's channels over the REST API.
     ///
     /// # Errors
@@ -166,8 +166,9 @@ impl RoleId {
     #[cfg(feature = "cache")]
     #[deprecated = "Use Guild::roles. This performs a loop over the entire cache!"]
     pub fn to_role_cached(self, cache: impl AsRef<Cache>) -> Option<Role> {
-        for guild_entry in cache.as_ref().guilds.iter() {
-            let guild = guild_entry.value();
+        let cache = cache.as_ref();
+        for guild_id in cache.guilds() {
+            let Some(guild) = cache.guild(guild_id) else { continue };
 
             if !guild.roles.contains_key(&self) {
                 continue;
@@ -1,3 +1,21 @@
+#[cfg(feature = "model")]
+use std::sync::Arc;
+
+#[cfg(feature = "model")]
+use futures::stream::Stream;
+
+#[cfg(feature = "model")]
+use crate::http::{
+    paginate,
+    Http,
+    Page,
+    PaginationDirection,
+    PaginationOptions,
+    UserPagination,
+    MAX_SNOWFLAKE,
+};
+#[cfg(feature = "model")]
+use crate::internal::prelude::*;
 use crate::model::prelude::*;
 
 /// Information about a guild scheduled event.
@@ -102,6 +120,55 @@ pub struct ScheduledEventUser {
     pub member: Option<Member>,
 }
 
+#[cfg(feature = "model")]
+impl ScheduledEventId {
+    /// Streams over the users interested in the event.
+    ///
+    /// This is accomplished and equivalent to repeated calls to
+    /// [`GuildId::scheduled_event_users_optioned`], requesting up to 100 users at once. Both
+    /// directions are supported.
+    ///
+    /// **Note**: Requires the [View Channel] permission for the channel associated with the event.
+    ///
+    /// [View Channel]: Permissions::VIEW_CHANNEL
+    pub fn users_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        guild_id: GuildId,
+        with_member: Option<bool>,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<ScheduledEventUser>> {
+        let http = Arc::new(http);
+        let options = options.max_page_size(100);
+        paginate(
+            options,
+            |user: &ScheduledEventUser| user.user.id,
+            move |page| {
+                let http = Arc::clone(&http);
+                async move {
+                    let target = match page.direction {
+                        PaginationDirection::Forward => page.cursor.map(UserPagination::After),
+                        _ => Some(UserPagination::Before(
+                            page.cursor.unwrap_or(UserId::new(MAX_SNOWFLAKE)),
+                        )),
+                    };
+                    let limit = Some(page.limit.into());
+                    guild_id
+                        .scheduled_event_users_optioned(
+                            (*http).as_ref(),
+                            self,
+                            limit,
+                            target,
+                            with_member,
+                        )
+                        .await
+                        .map(Page::new)
+                }
+            },
+        )
+    }
+}
+
 enum_number! {
     /// See [`ScheduledEvent::privacy_level`].
     ///
@@ -1,3 +1,23 @@
+#[cfg(feature = "model")]
+use std::sync::Arc;
+
+#[cfg(feature = "model")]
+use futures::stream::Stream;
+
+#[cfg(feature = "model")]
+use crate::http::{
+    paginate,
+    Http,
+    LightMethod,
+    Page,
+    PaginationDirection,
+    PaginationOptions,
+    Request,
+    Route,
+    MAX_SNOWFLAKE,
+};
+#[cfg(feature = "model")]
+use crate::internal::prelude::*;
 use crate::model::prelude::*;
 
 /// A premium offering that can be made available to an application's users and guilds.
@@ -82,6 +102,56 @@ pub struct Entitlement {
     pub guild_id: Option<GuildId>,
 }
 
+#[cfg(feature = "model")]
+impl ApplicationId {
+    /// Streams over the entitlements of the application, optionally only those granted to the
+    /// given user or guild.
+    ///
+    /// This is accomplished and equivalent to repeated calls to [`Http::get_entitlements`],
+    /// requesting up to 100 entitlements at once, except that the entitlements of any
+    /// application can be listed. Both directions are supported.
+    pub fn entitlements_iter<H: AsRef<Http>>(
+        self,
+        http: H,
+        user_id: Option<UserId>,
+        guild_id: Option<GuildId>,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<Entitlement>> {
+        let http = Arc::new(http);
+        let options = options.max_page_size(100);
+        paginate(
+            options,
+            |entitlement: &Entitlement| entitlement.id,
+            move |page| {
+                let http = Arc::clone(&http);
+                async move {
+                    let mut params = vec![("limit", page.limit.to_string())];
+                    if page.direction == PaginationDirection::Forward {
+                        if let Some(after) = page.cursor {
+                            params.push(("after", after.to_string()));
+                        }
+                    } else {
+                        let before = page.cursor.map_or(MAX_SNOWFLAKE, EntitlementId::get);
+                        params.push(("before", before.to_string()));
+                    }
+                    if let Some(user_id) = user_id {
+                        params.push(("user_id", user_id.to_string()));
+                    }
+                    if let Some(guild_id) = guild_id {
+                        params.push(("guild_id", guild_id.to_string()));
+                    }
+
+                    let route = Route::Entitlements {
+                        application_id: self,
+                    };
+                    let request = Request::new(route, LightMethod::Get).params(Some(params));
+                    (*http).as_ref().fire(request).await.map(Page::new)
+                }
+            },
+        )
+    }
+}
+
 enum_number! {
     /// Differentiates between Entitlement types.
     ///
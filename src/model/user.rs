@@ -5,7 +5,11 @@ use std::fmt;
 use std::fmt::Write;
 use std::num::NonZeroU16;
 use std::ops::{Deref, DerefMut};
+#[cfg(feature = "model")]
+use std::sync::Arc;
 
+#[cfg(feature = "model")]
+use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
 
 use super::prelude::*;
@@ -18,7 +22,16 @@ use crate::collector::{MessageCollector, ReactionCollector};
 #[cfg(feature = "collector")]
 use crate::gateway::ShardMessenger;
 #[cfg(feature = "model")]
-use crate::http::CacheHttp;
+use crate::http::{
+    paginate,
+    CacheHttp,
+    GuildPagination,
+    Http,
+    Page,
+    PaginationDirection,
+    PaginationOptions,
+    MAX_SNOWFLAKE,
+};
 #[cfg(feature = "model")]
 use crate::internal::prelude::*;
 #[cfg(feature = "model")]
@@ -183,6 +196,56 @@ impl CurrentUser {
         *self = builder.execute(cache_http, ()).await?;
         Ok(())
     }
+
+    /// Streams over the guilds the current user is in.
+    ///
+    /// This is accomplished and equivalent to repeated calls to [`Http::get_guilds`], requesting
+    /// up to 200 guilds at once. Both directions are supported.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use serenity::http::Http;
+    /// # use serenity::model::user::CurrentUser;
+    /// # async fn run(http: Http, user: CurrentUser) {
+    /// use serenity::futures::StreamExt;
+    /// use serenity::http::{PaginationDirection, PaginationOptions};
+    ///
+    /// let options = PaginationOptions::new().page_size(200).direction(PaginationDirection::Forward);
+    /// let mut guilds = user.guilds_iter(&http, options).boxed();
+    /// while let Some(guild) = guilds.next().await {
+    ///     match guild {
+    ///         Ok(guild) => println!("In {}", guild.name),
+    ///         Err(error) => eprintln!("Uh oh! Error: {}", error),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn guilds_iter<H: AsRef<Http>>(
+        &self,
+        http: H,
+        options: PaginationOptions,
+    ) -> impl Stream<Item = Result<GuildInfo>> {
+        let http = Arc::new(http);
+        let options = options.max_page_size(200);
+        paginate(
+            options,
+            |guild: &GuildInfo| guild.id,
+            move |page| {
+                let http = Arc::clone(&http);
+                async move {
+                    let target = match page.direction {
+                        PaginationDirection::Forward => page.cursor.map(GuildPagination::After),
+                        _ => Some(GuildPagination::Before(
+                            page.cursor.unwrap_or(GuildId::new(MAX_SNOWFLAKE)),
+                        )),
+                    };
+                    let limit = Some(page.limit.into());
+                    (*http).as_ref().get_guilds(target, limit).await.map(Page::new)
+                }
+            },
+        )
+    }
 }
 
 /// The representation of a user's status.
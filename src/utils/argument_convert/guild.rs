@@ -39,13 +39,13 @@ impl ArgumentConvert for Guild {
         _channel_id: Option<ChannelId>,
         s: &str,
     ) -> Result<Self, Self::Err> {
-        let guilds = &ctx.cache().ok_or(GuildParseError::NoCache)?.guilds;
+        let cache = ctx.cache().ok_or(GuildParseError::NoCache)?;
 
-        let lookup_by_id = || guilds.get(&s.parse().ok()?).map(|g| g.clone());
+        let lookup_by_id = || cache.guild(s.parse::<GuildId>().ok()?).map(|g| g.clone());
 
         let lookup_by_name = || {
-            guilds.iter().find_map(|m| {
-                let guild = m.value();
+            cache.guilds().into_iter().find_map(|guild_id| {
+                let guild = cache.guild(guild_id)?;
                 guild.name.eq_ignore_ascii_case(s).then(|| guild.clone())
             })
         };
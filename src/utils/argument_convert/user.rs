@@ -25,11 +25,12 @@ impl fmt::Display for UserParseError {
 
 #[cfg(feature = "cache")]
 fn lookup_by_global_cache(ctx: impl CacheHttp, s: &str) -> Option<User> {
-    let users = &ctx.cache()?.users;
+    let cache = ctx.cache()?;
+    let users = cache.users();
 
-    let lookup_by_id = || users.get(&s.parse().ok()?).map(|u| u.clone());
+    let lookup_by_id = || cache.user(s.parse::<UserId>().ok()?).map(|u| u.clone());
 
-    let lookup_by_mention = || users.get(&crate::utils::parse_user_mention(s)?).map(|u| u.clone());
+    let lookup_by_mention = || cache.user(crate::utils::parse_user_mention(s)?).map(|u| u.clone());
 
     let lookup_by_name_and_discrim = || {
         let (name, discrim) = crate::utils::parse_user_tag(s)?;
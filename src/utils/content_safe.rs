@@ -317,9 +317,9 @@ mod tests {
         guild.channels.insert(channel.id, channel.clone());
         guild.members.insert(user.id, member.clone());
         guild.roles.insert(role.id, role);
-        cache.users.insert(user.id, user.clone());
-        cache.guilds.insert(guild.id, guild.clone());
-        cache.channels.insert(channel.id, guild.id);
+        cache.backend().insert_user(user.clone());
+        cache.backend().insert_guild(guild.clone());
+        cache.backend().insert_channel(channel.id, guild.id);
 
         let with_user_mentions = "<@!100000000000000000> <@!000000000000000000> <@123> <@!123> \
         <@!123123123123123123123> <@123> <@123123123123123123> <@!invalid> \